@@ -0,0 +1,1525 @@
+use std::{collections::{BTreeSet, HashMap}, path::{Path, PathBuf}};
+use anyhow::{Result, anyhow, Ok};
+use crate::syntax;
+
+pub(crate) use crate::syntax::{
+    DuplicateKeys, IndentStyle, LuaTable, Newlines, QuoteStyle, Span, SpanTable, SpannedToken, Token, TokenStream, Tokenizer, Value, WriteOptions,
+    line_column, long_bracket_level, next_is_equal, parse_error, parse_spans, parse_stream, parse_top_level, parse_value, string_to_script, tokenize_spanned,
+};
+#[cfg(test)]
+pub(crate) use crate::syntax::{parse_tokens, tokenize};
+
+pub use batch::Outcome;
+pub use compat::AstVersion;
+pub use incremental::ParsedScript;
+pub use project::Project;
+pub use routes::BlockGraph;
+pub use stats::{FileStats, ProjectStats};
+
+pub(crate) mod alignment;
+pub(crate) mod assets;
+pub(crate) mod batch;
+pub(crate) mod bidi;
+pub(crate) mod braces;
+pub(crate) mod canonical;
+pub(crate) mod chapters;
+pub(crate) mod charset;
+pub mod commands;
+pub(crate) mod compat;
+pub(crate) mod completeness;
+pub(crate) mod conditions;
+pub(crate) mod credits;
+pub(crate) mod debug_dump;
+pub(crate) mod diagnostics;
+pub(crate) mod dedupe;
+pub(crate) mod documents;
+pub(crate) mod equivalent;
+pub(crate) mod filelist;
+pub(crate) mod gaiji;
+pub(crate) mod grouping;
+#[cfg(feature = "html-export")]
+pub(crate) mod html_export;
+pub(crate) mod hyphenate;
+pub(crate) mod incremental;
+pub(crate) mod indent;
+pub(crate) mod langs;
+pub(crate) mod length;
+pub(crate) mod links;
+pub(crate) mod lint;
+pub mod logging;
+pub(crate) mod mapping;
+pub(crate) mod page_split;
+pub mod platform;
+pub(crate) mod preview;
+pub mod project;
+pub(crate) mod quotes;
+pub(crate) mod reorder;
+pub(crate) mod repro;
+pub(crate) mod roundtrip;
+pub(crate) mod routes;
+pub(crate) mod schema;
+pub(crate) mod sections;
+pub(crate) mod shards;
+pub(crate) mod stream_prune;
+pub mod style;
+pub(crate) mod sidecar;
+pub(crate) mod splice;
+pub(crate) mod stats;
+pub(crate) mod text_scan;
+pub(crate) mod timing;
+#[cfg(feature = "update")]
+pub(crate) mod update;
+pub(crate) mod variants;
+pub(crate) mod voice;
+
+
+
+pub(crate) fn extract_secnario_toyaml(ast: &LuaTable, astver: Option<compat::AstVersion>, output: impl AsRef<Path>, scenario: &ScenarioOptions, options: &ExtractOptions) -> Result<()> {
+    if options.all_langs {
+        let mut entries = langs::side_by_side(ast, astver)?;
+        if let Some(gaiji) = load_gaiji(scenario)? {
+            for entry in entries.iter_mut() {
+                entry.texts.values_mut().for_each(|text| *text = gaiji.encode(text));
+            }
+        }
+        std::fs::write(output, serde_yaml::to_string(&entries)?)?;
+        return Ok(());
+    }
+    let mut blocks = extract_block_texts(ast, astver, scenario)?;
+    if let Some(gaiji) = load_gaiji(scenario)? {
+        for block in blocks.iter_mut() {
+            block.texts.iter_mut().for_each(|(_, text, _)| *text = gaiji.encode(text));
+        }
+    }
+    if options.per_block {
+        std::fs::write(output, documents::to_yaml(&blocks)?)?;
+        return Ok(());
+    }
+    if let Some(by) = options.group_by {
+        std::fs::write(output, serde_yaml::to_string(&grouping::group(ast, astver, &blocks, by))?)?;
+        return Ok(());
+    }
+    let entries = if options.tag_kind {
+        let names = voice::speaker_names(ast, astver);
+        let tagged: Vec<dedupe::TaggedEntry> = blocks.into_iter()
+            .flat_map(|block| {
+                let condition = block.condition;
+                block.texts.into_iter().map(move |line| (line, condition.clone()))
+            })
+            .map(|((kind, text, voice), condition)| {
+                let inferred_speaker = match voice {
+                    // the character's display name when a voiced line shows it, the vo code otherwise
+                    Some(ch) if options.infer_speakers && kind == LineKind::Narration => Some(names.get(&ch).cloned().unwrap_or(ch)),
+                    _ => None,
+                };
+                dedupe::TaggedEntry { kind, text, inferred_speaker, condition }
+            })
+            .collect();
+        serde_yaml::to_value(&tagged)?
+    } else {
+        let all_lines: Vec<String> = blocks.into_iter().flat_map(|block| block.texts).map(|(_, text, _)| text).collect();
+        if options.dedupe {
+            serde_yaml::to_value(dedupe::dedupe(all_lines))?
+        } else {
+            serde_yaml::to_value(all_lines)?
+        }
+    };
+    match (options.max_entries, entries) {
+        (Some(max_entries), serde_yaml::Value::Sequence(entries)) => shards::write(output.as_ref(), entries, max_entries),
+        (_, entries) => {
+            // write to file
+            std::fs::write(output, serde_yaml::to_string(&entries)?)?;
+            Ok(())
+        }
+    }
+}
+
+/// Whether a line is spoken by a named character or is narration.
+#[derive(clap::ValueEnum, serde::Serialize, serde::Deserialize, schemars::JsonSchema, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum LineKind {
+    Dialogue,
+    Narration,
+}
+
+/// Text of one block, in script order.
+pub(crate) struct BlockText {
+    name: String,
+    /// The block's `line = N`, its line number in the developers' original script
+    line: Option<i64>,
+    /// Each line with its kind and the `ch` of the vo entry voicing it
+    texts: Vec<(LineKind, String, Option<String>)>,
+    /// The `if` the text is shown under, `f.route==1`
+    condition: Option<String>,
+}
+
+/// Reads the lines of language channel `lang` (`text = { ja = {...} }`) of every block.
+/// `astver` is the layout given with `--astver`, if any.
+pub(crate) fn extract_blocks(ast: &LuaTable, astver: Option<compat::AstVersion>, lang: &str) -> Result<Vec<BlockText>> {
+    // extract all the text under the key "text"
+    let ast_table = ast.get("ast")
+        .ok_or(anyhow::anyhow!("ast key not found"))?
+        .as_table()
+        .ok_or(anyhow::anyhow!("ast is not a table"))?;
+
+    let version = compat::version(ast, astver);
+    let mut all_blocks = Vec::new();
+    let mut conditions = conditions::ConditionTracker::new();
+    for (block_key, block) in ast_table.fields() {
+        let block = block.as_table();
+        if !compat::is_block_name(version, block_key) || (version == compat::AstVersion::V1 && block.is_none()) {
+            continue;
+        }
+        let condition = block.and_then(|block| conditions.block(block));
+        let line = block.and_then(|block| block.get("line")).and_then(Value::as_integer);
+        let mut all_texts = Vec::new();
+        if let Some(text) = block.and_then(|block| block.get("text")).and_then(Value::as_table) {
+            let voice = voice::vo_character(text);
+            let ja = text.get(lang).and_then(Value::as_table);
+            for subja in ja.into_iter().flat_map(|ja| ja.array.iter()).filter_map(Value::as_table) {
+                // `name = {...}` marks the speaker, lines without one are narration
+                let kind = if subja.contains_key("name") { LineKind::Dialogue } else { LineKind::Narration };
+                for subj in subja.array.iter().filter_map(Value::as_string) {
+                    all_texts.push((kind, subj.to_string(), voice.cloned()));
+                }
+            }
+        }
+        all_blocks.push(BlockText { name: block_key.clone(), line, texts: all_texts, condition });
+    }
+
+    Ok(all_blocks)
+}
+
+/// Fails when the script has text but no block has a `lang` channel,
+/// naming the channels it does have.
+pub(crate) fn check_lang(ast: &LuaTable, astver: Option<compat::AstVersion>, lang: &str) -> Result<()> {
+    let mut found = BTreeSet::new();
+    for (_, block) in iter_blocks(ast, astver) {
+        let Some(text) = block.get("text").and_then(Value::as_table) else {
+            continue;
+        };
+        if text.contains_key(lang) {
+            return Ok(());
+        }
+        found.extend(text.fields().filter(|(_, channel)| channel.is_table()).map(|(key, _)| key.as_str()));
+    }
+    if found.is_empty() {
+        return Ok(());
+    }
+    let found: Vec<&str> = found.into_iter().collect();
+    Err(anyhow!("No block has a {} text channel, the script has {}", lang, found.join(", ")))
+}
+
+/// Blocks in the order and with the lines selected by `options`.
+pub(crate) fn extract_block_texts(ast: &LuaTable, astver: Option<compat::AstVersion>, options: &ScenarioOptions) -> Result<Vec<BlockText>> {
+    check_lang(ast, astver, options.lang())?;
+    let mut blocks = extract_blocks(ast, astver, options.lang())?;
+    if options.order_by_line {
+        // stable, so blocks without a line number stay in script order at the end
+        blocks.sort_by_key(|block| block.line.unwrap_or(i64::MAX));
+    }
+    for block in blocks.iter_mut() {
+        block.texts.retain(|(kind, _, _)| options.kind.is_none_or(|only| only == *kind));
+    }
+    Ok(blocks)
+}
+
+pub(crate) fn extract_lines(ast: &LuaTable, astver: Option<compat::AstVersion>, options: &ScenarioOptions) -> Result<Vec<(LineKind, String)>> {
+    Ok(extract_block_texts(ast, astver, options)?.into_iter().flat_map(|block| block.texts).map(|(kind, text, _)| (kind, text)).collect())
+}
+
+pub(crate) fn extract_secnario(ast: &LuaTable, astver: Option<compat::AstVersion>, options: &ScenarioOptions) -> Result<Vec<String>> {
+    Ok(extract_lines(ast, astver, options)?.into_iter().map(|(_, text)| text).collect())
+}
+
+/// The lines `extract` writes for a parsed script, in order.
+///
+/// ```
+/// use artemis_ast::{ParseOptions, ScenarioOptions, extract, parse};
+///
+/// let script = "ast = {\n\tblock_00000 = { text = { ja = { { \"こんにちは\" } } } },\n}\n";
+/// let ast = parse(script, &ParseOptions::default())?;
+/// assert_eq!(extract(&ast, &ParseOptions::default(), &ScenarioOptions::default())?, ["こんにちは"]);
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn extract(ast: &LuaTable, parse: &ParseOptions, scenario: &ScenarioOptions) -> Result<Vec<String>> {
+    extract_secnario(ast, parse.astver, scenario)
+}
+
+pub(crate) fn load_gaiji(options: &ScenarioOptions) -> Result<Option<gaiji::GaijiMap>> {
+    options.gaiji.as_deref().map(gaiji::GaijiMap::load).transpose()
+}
+
+/// Byte offsets of every invalid UTF-8 sequence in `bytes`.
+pub(crate) fn invalid_utf8_offsets(bytes: &[u8]) -> Vec<usize> {
+    let mut offsets = Vec::new();
+    let mut position = 0;
+    while let Err(e) = std::str::from_utf8(&bytes[position..]) {
+        offsets.push(position + e.valid_up_to());
+        match e.error_len() {
+            Some(len) => position += e.valid_up_to() + len,
+            // truncated sequence at the end of the file
+            None => break,
+        }
+    }
+    offsets
+}
+
+/// Reads a script, reporting where it is not valid UTF-8. With
+/// `--replace-invalid` the bad bytes become U+FFFD instead of failing.
+pub(crate) fn read_script(filename: &Path, options: &ParseOptions) -> Result<String> {
+    let bytes = std::fs::read(filename).map_err(|e| anyhow!("{}: {}", filename.display(), e))?;
+    let offsets = invalid_utf8_offsets(&bytes);
+    if offsets.is_empty() {
+        return Ok(String::from_utf8(bytes)?);
+    }
+    let listed: Vec<String> = offsets.iter().take(10).map(|o| format!("0x{:x}", o)).collect();
+    let message = format!(
+        "{}: {} invalid UTF-8 sequences at byte offsets {}{}",
+        filename.display(),
+        offsets.len(),
+        listed.join(", "),
+        if offsets.len() > listed.len() { ", ..." } else { "" },
+    );
+    if !options.replace_invalid {
+        return Err(anyhow!(message));
+    }
+    logging::warn(format!("{}, replaced with U+FFFD", message));
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// A zero-byte or whitespace-only script, which some games ship as a
+/// placeholder. Batch runs skip these instead of failing.
+#[derive(Debug)]
+pub(crate) struct EmptyScript(PathBuf);
+
+impl std::fmt::Display for EmptyScript {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: the script is empty", self.0.display())
+    }
+}
+
+impl std::error::Error for EmptyScript {}
+
+pub(crate) fn check_not_empty(input: &str, filename: &Path) -> Result<()> {
+    if input.trim_start_matches('\u{feff}').trim().is_empty() {
+        return Err(EmptyScript(filename.to_path_buf()).into());
+    }
+    Ok(())
+}
+
+pub(crate) fn parse_ast(filename: impl AsRef<Path>, options: &ParseOptions) -> Result<LuaTable> {
+    let input = read_script(filename.as_ref(), options)?;
+    parse_source(input, filename.as_ref(), options)
+}
+
+/// Parses script text already in memory; `filename` is only used in messages.
+pub(crate) fn parse_source(input: String, filename: &Path, options: &ParseOptions) -> Result<LuaTable> {
+    check_not_empty(&input, filename)?;
+    // hack 
+    if input.starts_with("[]") {
+        return Ok(LuaTable::new());
+    }
+    let result = parse_checked(&input, filename, options);
+    if let (Err(e), false) = (&result, options.quiet) {
+        if let Some(snippet) = diagnostics::snippet(&input, &e.to_string()) {
+            logging::warn(snippet);
+        }
+    }
+    if let (Err(e), Some(dump)) = (&result, &options.debug_dump) {
+        debug_dump::write(dump, &input, e)?;
+        logging::warn(format!("{}: wrote a debug dump to {}", filename.display(), dump.display()));
+    }
+    if let (Err(_), Some(path)) = (&result, &options.repro) {
+        match repro::write(path, &input) {
+            std::result::Result::Ok(()) => logging::warn(format!("{}: wrote a redacted snippet reproducing the error to {}", filename.display(), path.display())),
+            Err(e) => logging::warn(format!("{}: no repro snippet written: {}", filename.display(), e)),
+        }
+    }
+    result
+}
+
+/// Parses a script held in memory, with the checks and repairs `options`
+/// ask for.
+///
+/// ```
+/// use artemis_ast::{ParseOptions, Value, parse};
+///
+/// let script = "astver = 2.0\nast = {\n\tblock_00000 = { text = { ja = { { \"こんにちは\" } } } },\n}\n";
+/// let ast = parse(script, &ParseOptions::default())?;
+/// assert_eq!(ast.get("astver").and_then(Value::as_float), Some(2.0));
+/// assert!(ast["ast"].as_table().unwrap().contains_key("block_00000"));
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn parse(script: &str, options: &ParseOptions) -> Result<LuaTable> {
+    parse_source(script.to_string(), Path::new("<script>"), options)
+}
+
+/// Parses `input` as it streams through the tokenizer. Only a script that
+/// fails is checked for unbalanced braces, which takes a masked copy of it,
+/// and only `--repair` makes a repaired one.
+pub(crate) fn parse_checked(input: &str, filename: &Path, options: &ParseOptions) -> Result<LuaTable> {
+    let e = match parse_streaming(input, filename, options) {
+        std::result::Result::Ok(ast) => return Ok(ast),
+        Err(e) => e,
+    };
+    let report = braces::check(input);
+    if report.is_balanced() {
+        return Err(recover(e, input, filename, options));
+    }
+    if !options.repair {
+        return Err(anyhow!("{}: {}", filename.display(), report));
+    }
+    logging::warn(format!("{}: repairing {}", filename.display(), report));
+    let repaired = braces::repair(input, &report);
+    parse_streaming(&repaired, filename, options).map_err(|e| recover(e, &repaired, filename, options))
+}
+
+pub(crate) fn parse_streaming(input: &str, filename: &Path, options: &ParseOptions) -> Result<LuaTable> {
+    syntax::parse(input, &options.read()).map_err(|e| locate(e.into(), input, filename))
+}
+
+/// With `--keep-going`, the error of every block instead of the first.
+pub(crate) fn recover(e: anyhow::Error, input: &str, filename: &Path, options: &ParseOptions) -> anyhow::Error {
+    if !options.keep_going {
+        return e;
+    }
+    let (_, errors) = parse_recovering(input, filename, options);
+    if errors.len() <= 1 {
+        return e;
+    }
+    let list: Vec<String> = errors.iter().map(|e| format!("  {}", e)).collect();
+    anyhow!("{}: {} errors\n{}", filename.display(), errors.len(), list.join("\n"))
+}
+
+/// Turns the byte offsets of a [`ParseError`] into lines and columns of
+/// `input`, and names the file in the other errors of the syntax.
+pub(crate) fn locate(e: anyhow::Error, input: &str, filename: &Path) -> anyhow::Error {
+    let error = match e.downcast_ref::<syntax::Error>() {
+        Some(syntax::Error::Parse(error)) => error,
+        Some(_) => return anyhow!("{}: {}", filename.display(), e),
+        None => return e,
+    };
+    let (line, column) = line_column(input, error.position);
+    let opened = match error.opened {
+        Some(opened) => {
+            let (line, column) = line_column(input, opened);
+            format!(" (the table opened at line {}, column {} is never closed)", line, column)
+        }
+        None => String::new(),
+    };
+    anyhow!("{}: {} at line {}, column {}{}", filename.display(), error.message, line, column, opened)
+}
+
+/// Byte offsets of the lines starting a block, `block_00000 = {`.
+pub(crate) fn block_starts(input: &str) -> Vec<usize> {
+    let mut starts = Vec::new();
+    let mut offset = 0;
+    for line in input.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        let name_len = trimmed.find(|c: char| !(c.is_alphanumeric() || c == '_')).unwrap_or(trimmed.len());
+        if trimmed.starts_with("block_") && trimmed[name_len..].trim_start().starts_with('=') {
+            starts.push(offset + line.len() - trimmed.len());
+        }
+        offset += line.len();
+    }
+    starts
+}
+
+/// Reads the `block_x = {...}` field starting at `start`. Only separators and
+/// the `}` closing the ast table may follow it before `end`.
+pub(crate) fn parse_block_at(input: &str, start: usize, end: usize, options: &ParseOptions) -> Result<(String, Value)> {
+    let tokens = Tokenizer::starting_at(input, start)
+        .options(&options.read())
+        .take_while(|token| token.as_ref().map_or(true, |(_, span)| span.start < end));
+    let mut stream = TokenStream::new(tokens, &options.read());
+    let (token, span) = stream.next()?;
+    let Token::Identifier(name) = token else {
+        return Err(parse_error(span.start, "Expected a block name").into());
+    };
+    if !next_is_equal(&mut stream)? {
+        return Err(parse_error(stream.end, "Expected '=' after Identifier").into());
+    }
+    let value = parse_value(&mut stream)?;
+    while stream.peek()?.is_some() {
+        match stream.next()? {
+            (Token::Comma | Token::CloseBrace, _) => {}
+            (token, span) => return Err(parse_error(span.start, format!("Unexpected token after the block: {:?}", token)).into()),
+        }
+    }
+    Ok((name, value))
+}
+
+/// Parses a script one block at a time, so an error only costs the block it
+/// is in: returns what could be read, with the error of every block that
+/// could not. Lines before the first block are read as the top level, with
+/// the `ast` table closed after them.
+pub(crate) fn parse_recovering(input: &str, filename: &Path, options: &ParseOptions) -> (LuaTable, Vec<anyhow::Error>) {
+    let starts = block_starts(input);
+    let header_end = starts.first().copied().unwrap_or(input.len());
+    let mut errors = Vec::new();
+    let locate = |e: anyhow::Error| locate(e, input, filename);
+    let header = Tokenizer::new(input)
+        .options(&options.read())
+        .take_while(|token| token.as_ref().map_or(true, |(_, span)| span.start < header_end))
+        .chain((!starts.is_empty()).then_some(std::result::Result::Ok((Token::CloseBrace, header_end..header_end))));
+    let mut ast = parse_stream(header, &options.read()).unwrap_or_else(|e| {
+        errors.push(locate(e.into()));
+        LuaTable::new()
+    });
+    let ends = starts.iter().skip(1).copied().chain(std::iter::once(input.len()));
+    for (&start, end) in starts.iter().zip(ends) {
+        match parse_block_at(input, start, end, options) {
+            std::result::Result::Ok((name, block)) => {
+                if let Some(Value::Table(table)) = ast.get_mut("ast") {
+                    table.insert(name, block);
+                }
+            }
+            Err(e) => errors.push(locate(e)),
+        }
+    }
+    (ast, errors)
+}
+
+
+/// Reads a translation, joining the shards back together when `yaml_file`
+/// is the manifest of a `--max-entries` extraction.
+pub(crate) fn read_translation(yaml_file: &Path) -> Result<String> {
+    let content = std::fs::read_to_string(yaml_file)?;
+    match shards::load(yaml_file, &content)? {
+        Some(joined) => Ok(serde_yaml::to_string(&joined)?),
+        None => Ok(content),
+    }
+}
+
+pub(crate) fn read_yaml_as_strings(yaml_file: impl AsRef<Path>) -> Result<Vec<String>> {
+    let content = read_translation(yaml_file.as_ref())?;
+    let parsed: dedupe::TranslationFile = serde_yaml::from_str(&content)?;
+    parsed.into_strings()
+}
+
+
+/// The whole script as a string, for callers that still edit it as text.
+///
+/// ```
+/// use artemis_ast::{ParseOptions, WriteOptions, parse, reconstruct_script};
+///
+/// let script = "ast = {\n\tblock_00000={\n\t\tline=18\n\t}\n}\n";
+/// let ast = parse(script, &ParseOptions::default())?;
+/// assert_eq!(reconstruct_script(&ast, &WriteOptions::default())?, script);
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn reconstruct_script(ast: &LuaTable, options: &WriteOptions) -> Result<String> {
+    Ok(syntax::write(ast, options)?)
+}
+
+/// Streams the script to `path` without holding it in memory.
+pub(crate) fn write_script_file(ast: &LuaTable, path: &Path, options: &WriteOptions) -> Result<()> {
+    use std::io::Write;
+
+    /// Lets the writer of the syntax, which knows only `fmt::Write`, stream
+    /// to a file, keeping the io error it cannot return.
+    struct Adapter<W> {
+        inner: W,
+        error: Option<std::io::Error>,
+    }
+
+    impl<W: Write> std::fmt::Write for Adapter<W> {
+        fn write_str(&mut self, s: &str) -> std::fmt::Result {
+            self.inner.write_all(s.as_bytes()).map_err(|e| {
+                self.error = Some(e);
+                std::fmt::Error
+            })
+        }
+    }
+
+    let mut file = Adapter { inner: std::io::BufWriter::new(std::fs::File::create(path)?), error: None };
+    if let Err(e) = syntax::write_script(ast, &mut file, options) {
+        return Err(file.error.map_or_else(|| e.into(), Into::into));
+    }
+    file.inner.flush()?;
+    Ok(())
+}
+
+
+/// Iterates over the `(name, table)` pairs of every block in the ast:
+/// `block_*` tables, or every table for astver 1.x. `astver` is the layout
+/// given with `--astver`, if any.
+pub(crate) fn iter_blocks(ast: &LuaTable, astver: Option<compat::AstVersion>) -> impl Iterator<Item = (&String, &LuaTable)> {
+    let version = compat::version(ast, astver);
+    ast.get("ast")
+        .and_then(Value::as_table)
+        .into_iter()
+        .flat_map(LuaTable::fields)
+        .filter(move |(key, _)| compat::is_block_name(version, key))
+        .filter_map(|(key, block)| Some((key, block.as_table()?)))
+}
+
+
+/// Name of a command entry such as `{"bg", time=2000, file="bg001a"}`.
+pub(crate) fn command_name(item: &Value) -> Option<&str> {
+    item.as_table()?.array.first()?.as_string().map(String::as_str)
+}
+
+/// Looks up a named attribute (`time=2000`) of a command entry.
+pub(crate) fn command_attr<'a>(item: &'a Value, key: &str) -> Option<&'a Value> {
+    item.as_table()?.get(key)
+}
+
+
+/// Drops every command and text from the blocks, keeping only how they
+/// link together.
+pub(crate) fn prune_ast(ast: &mut LuaTable) {
+    if let Some(ast_table) = ast.get_mut("ast").and_then(Value::as_table_mut) {
+        for (_, block) in ast_table.fields_mut() {
+            if let Some(block) = block.as_table_mut() {
+                block.array.clear();
+                block.retain_fields(|key| key == "linknext" || key == "line");
+            }
+        }
+    }
+}
+
+
+
+
+#[derive(clap::Args, Debug, Default)]
+pub struct ParseOptions {
+    /// Best-effort repair of unbalanced braces instead of failing
+    #[arg(long, global = true)]
+    pub repair: bool,
+    /// Replace invalid UTF-8 with U+FFFD instead of failing
+    #[arg(long, global = true)]
+    pub replace_invalid: bool,
+    /// When parsing fails, write the tokens with their positions to this file, string contents left out, for bug reports
+    #[arg(long, global = true)]
+    pub debug_dump: Option<PathBuf>,
+    /// When parsing fails, write a small snippet with its text redacted that fails the same way, for bug reports
+    #[arg(long, global = true)]
+    pub repro: Option<PathBuf>,
+    /// Report parse errors without the source line they point at
+    #[arg(long, short, global = true)]
+    pub quiet: bool,
+    /// When parsing fails, go on with the next block and report the errors of every block
+    #[arg(long, global = true)]
+    pub keep_going: bool,
+    /// Read scripts with this astver layout instead of the one they declare
+    #[arg(long, global = true, value_enum)]
+    pub astver: Option<compat::AstVersion>,
+    /// What to do with a key written twice in the same table
+    #[arg(long, global = true, value_enum, default_value_t)]
+    pub duplicate_keys: DuplicateKeys,
+    /// How deeply tables may nest before parsing stops with an error [default: 200]
+    #[arg(long, global = true)]
+    pub max_depth: Option<usize>,
+    /// Fail on unknown escape sequences such as \k instead of keeping them as written with a warning
+    #[arg(long, global = true)]
+    pub strict: bool,
+}
+
+impl ParseOptions {
+    /// The options the syntax reads with, its warnings going to the log.
+    pub fn read(&self) -> syntax::ReadOptions {
+        syntax::ReadOptions {
+            duplicate_keys: self.duplicate_keys,
+            max_depth: self.max_depth,
+            strict: self.strict,
+            warn: Some(|message| logging::warn(message)),
+        }
+    }
+}
+
+/// Options deciding which lines are extracted and in what order. Merge
+/// must be given the same ones as the extraction it reads back.
+#[derive(clap::Args, Debug, Default)]
+pub struct ScenarioOptions {
+    /// Order lines by each block's `line = N` instead of script order
+    #[arg(long)]
+    pub order_by_line: bool,
+    /// Yaml table of private-use glyphs shown as <name> tokens in the extracted text
+    #[arg(long)]
+    pub gaiji: Option<PathBuf>,
+    /// Only handle dialogue (lines with a `name`) or narration, e.g. to split work between translators
+    #[arg(long, value_enum)]
+    pub kind: Option<LineKind>,
+    /// Language channel to read the lines from, such as en, zh or ko [default: ja]
+    #[arg(long)]
+    pub lang: Option<String>,
+}
+
+/// The language channel the original scripts are written in.
+pub(crate) const DEFAULT_LANG: &str = "ja";
+
+impl ScenarioOptions {
+    pub fn lang(&self) -> &str {
+        self.lang.as_deref().unwrap_or(DEFAULT_LANG)
+    }
+}
+
+#[derive(clap::Args, Debug, Default)]
+pub(crate) struct ExtractOptions {
+    /// Collapse repeated lines into a single entry listing every position it occurs at
+    #[arg(long)]
+    dedupe: bool,
+    /// Also write a <input>.meta sidecar recording spans, literal forms and hashes
+    #[arg(long)]
+    meta: bool,
+    /// Write each line as a record tagged `dialogue` or `narration`
+    #[arg(long, conflicts_with = "dedupe")]
+    tag_kind: bool,
+    /// With --tag-kind, record who is heard on narrated lines that carry a vo entry, marked as inferred
+    #[arg(long, requires = "tag_kind")]
+    infer_speakers: bool,
+    /// Write one yaml document per block, which merge also accepts for just some of the blocks
+    #[arg(long, conflicts_with_all = ["dedupe", "tag_kind"])]
+    per_block: bool,
+    /// Split the output into numbered files of at most N entries, listed by a manifest written to the output path
+    #[arg(long, value_name = "N", conflicts_with = "per_block")]
+    max_entries: Option<usize>,
+    /// Read the lines straight from the tokens without building the tree, for large batches. Writes a plain list
+    #[arg(long, conflicts_with_all = ["dedupe", "tag_kind", "per_block", "group_by", "order_by_line", "kind"])]
+    fast: bool,
+    /// Write the lines grouped by speaker, block or chapter, each with the id merge puts it back by
+    #[arg(long, value_enum, conflicts_with_all = ["dedupe", "tag_kind", "per_block", "max_entries"])]
+    group_by: Option<grouping::GroupBy>,
+    /// Write every language channel (ja, en, zht, ...) side by side, one entry per line, as a reference for translating
+    #[arg(long, conflicts_with_all = ["dedupe", "tag_kind", "per_block", "max_entries", "fast", "group_by", "lang", "kind", "order_by_line"])]
+    all_langs: bool,
+}
+
+#[derive(clap::Args, Debug, Default)]
+pub(crate) struct PruneOptions {
+    /// Prune as a token filter in constant memory, for scripts too large to parse whole.
+    /// The script is copied as written, so --repair and --escape-non-ascii do not apply
+    #[arg(long)]
+    streaming: bool,
+}
+
+#[derive(clap::Args, Debug, Default)]
+pub(crate) struct MergeOptions {
+    /// Append a summary of each merge (file, entries changed, translation hash, time) to this log
+    #[arg(long)]
+    log: Option<PathBuf>,
+    /// The translation is the JSON saved from an html-export page instead of yaml
+    #[cfg(feature = "html-export")]
+    #[arg(long)]
+    from_html_export: bool,
+    /// Write where each translation entry was merged to this JSON file, for debugging misaligned merges
+    #[arg(long)]
+    emit_mapping: Option<PathBuf>,
+    /// Merge even if the script carries the marker of an earlier merge
+    #[arg(long)]
+    force: bool,
+    /// Variants the engine has text channels for, written next to the merged channel as <channel>_<variant> (ja_female); other variants are reported and left out
+    #[arg(long, value_delimiter = ',')]
+    variant_channels: Vec<String>,
+    #[command(flatten)]
+    length: length::LengthOptions,
+    #[command(flatten)]
+    hyphenate: hyphenate::HyphenateOptions,
+    /// Add LRM/RLM marks to translated lines mixing right-to-left and left-to-right text, as the engine does not reorder them
+    #[arg(long)]
+    direction_marks: bool,
+    /// Write each changed string literal with the quote it had rather than --quote-style, and add no merge marker; the rest of the script is kept as it was either way
+    #[arg(long)]
+    surgical: bool,
+    /// Move what does not fit of a line wrapping past --max-rows onto a copy of its page right after it, and report each split
+    #[arg(long, requires = "max_rows", conflicts_with = "surgical")]
+    split_long_lines: bool,
+    #[command(flatten)]
+    lint: lint::LintOptions,
+}
+
+
+/// A source line whose quoted literal was not found in the script text.
+#[derive(Debug)]
+pub(crate) struct UnusedReplacement(pub(crate) String);
+
+impl std::fmt::Display for UnusedReplacement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Not all replacements were used: {:?} was not found in the script", self.0)
+    }
+}
+
+impl std::error::Error for UnusedReplacement {}
+
+pub(crate) fn extract_file(input: &Path, output: &Path, parse: &ParseOptions, scenario: &ScenarioOptions, options: &ExtractOptions) -> Result<()> {
+    if options.fast {
+        return extract_fast(input, output, parse, scenario, options);
+    }
+    let ast = parse_ast(input, parse)?;
+    if ast.is_empty() {
+        return Ok(());
+    }
+    if options.meta {
+        sidecar::write(input, &read_script(input, parse)?, scenario.lang(), parse)?;
+    }
+    extract_secnario_toyaml(&ast, parse.astver, output, scenario, options)
+}
+
+/// `extract --fast`: the same plain list, without parsing the script into values.
+pub(crate) fn extract_fast(input: &Path, output: &Path, parse: &ParseOptions, scenario: &ScenarioOptions, options: &ExtractOptions) -> Result<()> {
+    let script = read_script(input, parse)?;
+    check_not_empty(&script, input)?;
+    // the same hack as parse_source
+    if script.starts_with("[]") {
+        return Ok(());
+    }
+    if options.meta {
+        sidecar::write(input, &script, scenario.lang(), parse)?;
+    }
+    let mut texts = text_scan::texts(&script, scenario.lang(), parse).map_err(|e| anyhow!("{}: {}", input.display(), e))?;
+    if texts.is_empty() && scenario.lang.is_some() {
+        logging::warn(format!("{}: no {} lines found", input.display(), scenario.lang()));
+    }
+    if let Some(gaiji) = load_gaiji(scenario)? {
+        texts = texts.iter().map(|text| gaiji.encode(text)).collect();
+    }
+    match options.max_entries {
+        Some(max_entries) => shards::write(output, texts.into_iter().map(serde_yaml::Value::String).collect(), max_entries),
+        None => Ok(std::fs::write(output, serde_yaml::to_string(&texts)?)?),
+    }
+}
+
+/// Rewrites a script the way this tool writes every script: entries in the
+/// order they were written, indented and separated by `write`, comments
+/// kept. The marker of an earlier merge stays on the first line.
+pub(crate) fn format_script(script: &str, filename: &Path, parse: &ParseOptions, write: &WriteOptions) -> Result<String> {
+    let (marker, body) = match strip_merged_marker(script) {
+        Some(body) => (&script[..script.len() - body.len()], body),
+        None => ("", script),
+    };
+    let ast = parse_source(body.to_string(), filename, parse)?;
+    Ok(marker.to_string() + &reconstruct_script(&ast, write)?)
+}
+
+pub(crate) fn prune_file(input: &Path, output: &Path, parse: &ParseOptions, write: &WriteOptions, options: &PruneOptions) -> Result<()> {
+    if options.streaming {
+        let reader = std::io::BufReader::new(std::fs::File::open(input)?);
+        let writer = std::io::BufWriter::new(std::fs::File::create(output)?);
+        return stream_prune::prune(reader, writer);
+    }
+    let mut ast = parse_ast(input, parse)?;
+    if ast.is_empty() {
+        return Ok(());
+    }
+    prune_ast(&mut ast);
+    write_script_file(&ast, output, write)?;
+    Ok(())
+}
+
+/// First line of every merged script, so a second merge does not apply
+/// positional translations over text that is already translated.
+pub(crate) const MERGED_MARKER: &str = "-- merged by artemis_ast";
+
+/// Splits off the marker line of an earlier merge, if the script has one.
+pub(crate) fn strip_merged_marker(script: &str) -> Option<&str> {
+    let first_line_end = script.find('\n').map_or(script.len(), |end| end + 1);
+    script.starts_with(MERGED_MARKER).then(|| &script[first_line_end..])
+}
+
+pub(crate) fn merge_file(ast_input: &Path, yaml_input: &Path, output: &Path, parse: &ParseOptions, write: &WriteOptions, scenario: &ScenarioOptions, options: &MergeOptions) -> Result<()> {
+    if options.surgical && write.minify {
+        return Err(anyhow!("--surgical keeps the script's own formatting and cannot be combined with --minify"));
+    }
+    let script = read_script(ast_input, parse)?;
+    let script = match strip_merged_marker(&script) {
+        Some(_) if !options.force => {
+            logging::warn(format!("{}: already merged, skipping (use --force to merge again)", ast_input.display()));
+            return Ok(());
+        }
+        Some(body) => body.to_string(),
+        None => script,
+    };
+    let ast = parse_source(script.clone(), ast_input, parse)?;
+    if ast.is_empty() {
+        return Ok(());
+    }
+    let blocks = extract_block_texts(&ast, parse.astver, scenario)?;
+    let mut variants = Vec::new();
+    #[cfg(feature = "html-export")]
+    let from_page = if options.from_html_export {
+        let old_secnario = extract_secnario(&ast, parse.astver, scenario)?;
+        let shown = match load_gaiji(scenario)? {
+            Some(gaiji) => old_secnario.iter().map(|text| gaiji.encode(text)).collect(),
+            None => old_secnario.clone(),
+        };
+        let secnario = html_export::load_translations(yaml_input, &shown)?;
+        Some((old_secnario, secnario))
+    } else {
+        None
+    };
+    #[cfg(not(feature = "html-export"))]
+    let from_page = None;
+    let (old_secnario, mut secnario) = match from_page {
+        Some(pair) => pair,
+        None => {
+            let content = read_translation(yaml_input)?;
+            match documents::parse(&content)? {
+                Some(documents) => documents::pair(&blocks, documents)?,
+                None => {
+                    let parsed: dedupe::TranslationFile = serde_yaml::from_str(&content)?;
+                    let labels: Vec<String> = blocks.iter()
+                        .flat_map(|block| block.texts.iter().map(|_| block.name.clone()))
+                        .collect();
+                    let old_secnario: Vec<String> = blocks.iter().flat_map(|block| &block.texts).map(|(_, text, _)| text.clone()).collect();
+                    variants = parsed.variants();
+                    let secnario = parsed.into_strings()?;
+                    if let Some(drift) = alignment::check(&labels, &old_secnario, &secnario) {
+                        return Err(anyhow!("{}: {}", yaml_input.display(), drift));
+                    }
+                    (old_secnario, secnario)
+                }
+            }
+        }
+    };
+    if let Some(gaiji) = load_gaiji(scenario)? {
+        secnario = secnario.iter().map(|text| gaiji.decode(text)).collect();
+        for entry in variants.iter_mut() {
+            entry.values_mut().for_each(|text| *text = gaiji.decode(text));
+        }
+    }
+    for (index, width) in length::check_lengths(&secnario, &options.length) {
+        logging::warn(format!("{}: entry {} is {} wide: {}", yaml_input.display(), index, width, secnario[index]));
+    }
+    for (index, rows) in length::check_rows(&secnario, &options.length).into_iter().filter(|_| !options.split_long_lines) {
+        logging::warn(format!("{}: entry {} wraps to {} rows: {}", yaml_input.display(), index, rows, secnario[index]));
+    }
+    if options.lint.lint_numbers {
+        for (index, problem) in lint::check_numbers(&old_secnario, &secnario) {
+            logging::warn(format!("{}: entry {}: {}: {}", yaml_input.display(), index, problem, secnario[index]));
+        }
+    }
+    if let Some(language) = options.lint.lint_typography {
+        for (index, text) in secnario.iter().enumerate() {
+            for problem in lint::check_typography(text, language) {
+                logging::warn(format!("{}: entry {}: {}: {}", yaml_input.display(), index, problem, text));
+            }
+        }
+    }
+    if options.lint.lint_bidi {
+        for (index, text) in secnario.iter().enumerate().filter(|(_, text)| bidi::needs_marks(text)) {
+            logging::warn(format!("{}: entry {}: mixes right-to-left and left-to-right text without direction marks: {}", yaml_input.display(), index, text));
+        }
+    }
+    // after the checks, which measure the text as the translator wrote it
+    if let Some(hyphenator) = hyphenate::Hyphenator::load(&options.hyphenate)? {
+        secnario = secnario.iter().map(|text| hyphenator.apply(text)).collect();
+        for entry in variants.iter_mut() {
+            entry.values_mut().for_each(|text| *text = hyphenator.apply(text));
+        }
+    }
+    if options.direction_marks {
+        secnario = secnario.iter().map(|text| bidi::insert_marks(text)).collect();
+        for entry in variants.iter_mut() {
+            entry.values_mut().for_each(|text| *text = bidi::insert_marks(text));
+        }
+    }
+    let entries = match &options.emit_mapping {
+        Some(_) => mapping::build(&script, 1, scenario.lang(), parse, &old_secnario, &secnario)?,
+        None => Vec::new(),
+    };
+    let positions: HashMap<String, usize> = old_secnario.iter().enumerate().rev().map(|(i, text)| (text.clone(), i)).collect();
+    let changed = old_secnario.iter().zip(&secnario).filter(|(old, new)| old != new).count();
+    if let Some(meta) = sidecar::load(ast_input)? {
+        if meta.source_sha256 != sha256_hex(script.as_bytes()) {
+            logging::warn(format!("{}: sidecar is stale, the script changed since extraction", ast_input.display()));
+        }
+    }
+    let replaced = splice::splice_texts(&script, &blocks, &secnario, scenario.lang(), parse, write, options.surgical);
+    let s = replaced.map_err(|e| {
+        let Some(UnusedReplacement(text)) = e.downcast_ref() else {
+            return e;
+        };
+        let block = blocks.iter().find(|block| block.texts.iter().any(|(_, line, _)| line == text));
+        let block = block.map_or("?", |block| &block.name);
+        let context = match positions.get(text) {
+            Some(index) => format!("{}: entry {} in {}", ast_input.display(), index, block),
+            None => format!("{}: a line in {} that {} has no entry for", ast_input.display(), block, yaml_input.display()),
+        };
+        e.context(context)
+    })?;
+
+    for (index, variant) in variants::unsupported(&variants, &options.variant_channels) {
+        logging::warn(format!("{}: entry {}: the engine has no channel for variant {}, only the main text was merged", yaml_input.display(), index, variant));
+    }
+    let s = variants::apply(&s, &secnario, &variants, &options.variant_channels, write)?;
+    let s = if options.split_long_lines || write.minify {
+        let mut merged = parse_checked(&s, output, parse)?;
+        if options.split_long_lines {
+            for split in page_split::split_long_lines(&mut merged, parse.astver, &options.length) {
+                logging::warn(format!("{}: {}: split over {} pages: {}", output.display(), split.block, split.pages, split.text));
+            }
+        }
+        reconstruct_script(&merged, write)?
+    } else {
+        s
+    };
+
+    // each line goes back over the literal it was extracted from, so
+    // merging again over a surgical merge is harmless
+    let marker = if options.surgical {
+        String::new()
+    } else {
+        format!("{} from {} sha256:{}\n", MERGED_MARKER, yaml_input.display(), sha256_hex(&std::fs::read(yaml_input)?))
+    };
+    std::fs::write(output, marker + &s)?;
+    if let Some(path) = &options.emit_mapping {
+        let script = output.display().to_string();
+        mapping::write(path, &mapping::Mapping { script, translation: yaml_input.display().to_string(), entries })?;
+    }
+
+    if let Some(log) = &options.log {
+        append_merge_log(log, ast_input, yaml_input, output, changed)?;
+    }
+    Ok(())
+}
+
+/// `script` with its lines replaced by `texts`, one for each line `extract`
+/// gives, in the same order. The rest of the script is kept as written.
+///
+/// ```
+/// use artemis_ast::{ParseOptions, ScenarioOptions, WriteOptions, merge};
+///
+/// let script = "ast = {\n\tblock_00000 = { text = { ja = { { \"こんにちは\" } } } },\n}\n";
+/// let merged = merge(script, &["Hello"], &ParseOptions::default(), &WriteOptions::default(), &ScenarioOptions::default())?;
+/// assert_eq!(merged, script.replace("こんにちは", "Hello"));
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn merge(script: &str, texts: &[impl AsRef<str>], parse: &ParseOptions, write: &WriteOptions, scenario: &ScenarioOptions) -> Result<String> {
+    let ast = parse_source(script.to_string(), Path::new("<script>"), parse)?;
+    let blocks = extract_block_texts(&ast, parse.astver, scenario)?;
+    let texts: Vec<String> = texts.iter().map(|text| text.as_ref().to_string()).collect();
+    splice::splice_texts(script, &blocks, &texts, scenario.lang(), parse, write, false)
+}
+
+pub(crate) fn sha256_hex(data: &[u8]) -> String {
+    use sha2::{Digest, Sha256};
+    Sha256::digest(data).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub(crate) fn append_merge_log(log: &Path, ast_input: &Path, yaml_input: &Path, output: &Path, changed: usize) -> Result<()> {
+    use std::io::Write;
+
+    let hash = sha256_hex(&std::fs::read(yaml_input)?);
+    let entry = format!(
+        "- {} merged {} -> {}: {} entries changed, {} sha256:{}\n",
+        humantime::format_rfc3339_seconds(std::time::SystemTime::now()),
+        ast_input.display(),
+        output.display(),
+        changed,
+        yaml_input.display(),
+        hash,
+    );
+    // a single write per entry keeps lines intact when batch workers share the log
+    let mut file = std::fs::OpenOptions::new().create(true).append(true).open(log)?;
+    file.write_all(entry.as_bytes())?;
+    Ok(())
+}
+
+/// A new directory for one test, apart from those of other tests and of
+/// other runs going at the same time.
+#[cfg(test)]
+pub(crate) fn test_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("artemis_ast_{}_{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ast() {
+        let input = r#"astver = 2.0
+        ast = {
+            block_00000 = {
+                {"savetitle", text="俺たちの新しい日常"},
+                {"bg", time=2000, file="bg001a", path=":bg/"},
+                {"se", file="seアラーム", loop=1, id=1},
+                {"fg", ch="妃愛", size="no", mode=1, path=":fg/hiy[表情]/", file="hiy_nob0700", ex05="hiy_nob0000", face="b0032", head="hiy_nob", lv=2.2, id=20},
+                {"text"},
+                text = {
+                    vo = {
+                        {"vo", file="fem_hiy_00052", ch="hiy"},
+                    },
+                    ja = {
+                        {
+                            name = {"妃愛"},
+                            "「お兄、あさー……むふー……」",
+                            {"rt2"},
+                        },
+                    },
+                },
+                linknext = "block_00001",
+                line = 18,
+            },
+        }
+        "#;
+    
+        let tokens = tokenize(input).unwrap();
+        let _value = parse_tokens(&tokens).unwrap();
+    }
+
+
+    #[test]
+    fn test_format_script() {
+        let input = "-- merged by artemis_ast from a.yaml sha256:00\n-- note\nastver = 2.0\nast = {\n  block_00000 = { text = { ja = { { \"a\" ; } } }, line=1 },\n}\n";
+        let formatted = format_script(input, Path::new("a.ast"), &ParseOptions::default(), &WriteOptions::default()).unwrap();
+        assert!(formatted.starts_with("-- merged by artemis_ast from a.yaml sha256:00\n"));
+        assert!(formatted.contains("-- note"));
+        assert!(formatted.contains("\tblock_00000={\n\t\ttext={"));
+        let again = format_script(&formatted, Path::new("a.ast"), &ParseOptions::default(), &WriteOptions::default()).unwrap();
+        assert_eq!(again, formatted);
+    }
+
+    #[test]
+    fn test_prune_ast() {
+        let input = r#"astver = 2.0
+        ast = {
+            block_00000 = {
+                {"savetitle", text="俺たちの新しい日常"},
+                {"bg", time=2000, file="bg001a", path=":bg/"},
+                {"se", file="seアラーム", loop=1, id=1},
+                {"fg", ch="妃愛", size="no", mode=1, path=":fg/hiy[表情]/", file="hiy_nob0700", ex05="hiy_nob0000", face="b0032", head="hiy_nob", lv=2.2, id=20},
+                {"text"},
+                text = {
+                    vo = {
+                        {"vo", file="fem_hiy_00052", ch="hiy"},
+                    },
+                    ja = {
+                        {
+                            name = {"妃愛"},
+                            "「お兄、あさー……むふー……」",
+                            {"rt2"},
+                        },
+                    },
+                },
+                linknext = "block_00001",
+                line = 18,
+            },
+        }
+        "#;
+    
+        let tokens = tokenize(input).unwrap();
+        let mut value = parse_tokens(&tokens).unwrap();
+        prune_ast(&mut value);
+        let s = reconstruct_script(&value, &WriteOptions::default()).unwrap();
+        println!("{}", s);
+    }
+
+    #[test]
+    fn test_reconstruct() {
+        let input = r#"astver = 2.0
+        ast = {
+            block_00000 = {
+                {"savetitle", text="俺たちの新しい日常"},
+                {"bg", time=2000, file="bg001a", path=":bg/"},
+                {"se", file="seアラーム", loop=1, id=1},
+                {"fg", ch="妃愛", size="no", mode=1, path=":fg/hiy[表情]/", file="hiy_nob0700", ex05="hiy_nob0000", face="b0032", head="hiy_nob", lv=2.2, id=20},
+                {"text"},
+                text = {
+                    vo = {
+                        {"vo", file="fem_hiy_00052", ch="hiy"},
+                    },
+                    ja = {
+                        {
+                            name = {"妃愛"},
+                            "「お兄、あさー……むふー……」",
+                            {"rt2"},
+                        },
+                    },
+                },
+                linknext = "block_00001",
+                line = 18,
+            },
+        }
+        "#;
+    
+        let tokens = tokenize(input).unwrap();
+        let value = parse_tokens(&tokens).unwrap();
+        let s = reconstruct_script(&value, &WriteOptions::default()).unwrap();
+        println!("{}", s);
+    }
+
+    #[test]
+    fn test_merge() {
+        let input = r#"astver = 2.0
+        ast = {
+            block_00000 = {
+                {"savetitle", text="俺たちの新しい日常"},
+                {"bg", time=2000, file="bg001a", path=":bg/"},
+                {"se", file="seアラーム", loop=1, id=1},
+                {"fg", ch="妃愛", size="no", mode=1, path=":fg/hiy[表情]/", file="hiy_nob0700", ex05="hiy_nob0000", face="b0032", head="hiy_nob", lv=2.2, id=20},
+                {"text"},
+                text = {
+                    vo = {
+                        {"vo", file="fem_hiy_00052", ch="hiy"},
+                    },
+                    ja = {
+                        {
+                            name = {"妃愛"},
+                            "「お兄、あさー……むふー……」",
+                            {"rt2"},
+                        },
+                    },
+                },
+                linknext = "block_00001",
+                line = 18,
+            },
+        }
+        "#;
+    
+        let tokens = tokenize(input).unwrap();
+        let _value = parse_tokens(&tokens).unwrap();
+    }
+
+
+
+    #[test]
+    fn test_escaped_text_round_trip() {
+        let input = r#"ast = { block_00000 = { text = { ja = { { "「\"お兄\"」\\n", 'it\'s\\' } } } } }"#;
+        let ast = parse_tokens(&tokenize(input).unwrap()).unwrap();
+        let script = reconstruct_script(&ast, &WriteOptions::default()).unwrap();
+        assert_eq!(extract_secnario(&parse_tokens(&tokenize(&script).unwrap()).unwrap(), None, &ScenarioOptions::default()).unwrap(), vec!["「\"お兄\"」\\n", "it's\\"]);
+    }
+
+    #[test]
+    fn test_unknown_escape_passthrough() {
+        let input = r#"text = "wait\kthen""#;
+        let tokens = tokenize(input).unwrap();
+        assert_eq!(tokens[2], Token::StringLiteral(r"wait\kthen".to_string()));
+        let ast = parse_tokens(&tokens).unwrap();
+        assert_eq!(reconstruct_script(&ast, &WriteOptions::default()).unwrap().trim(), input);
+
+        let options = ParseOptions { strict: true, ..Default::default() };
+        let error = parse_checked(input, Path::new("a.ast"), &options).unwrap_err();
+        assert_eq!(error.to_string(), "a.ast: Unknown escape sequence \\k at line 1, column 8");
+    }
+
+    #[test]
+    fn test_invalid_utf8_offsets() {
+        let mut bytes = "あ".as_bytes().to_vec();
+        bytes.push(0xff);
+        bytes.extend_from_slice(b"ok");
+        bytes.extend_from_slice(&[0xe3, 0x81]);
+        assert_eq!(invalid_utf8_offsets(&bytes), vec![3, 6]);
+        assert!(invalid_utf8_offsets("ok".as_bytes()).is_empty());
+    }
+
+    #[test]
+    fn test_order_by_line() {
+        let input = r#"ast = {
+            block_00000 = { text = { ja = { { "second" } } }, line = 20 },
+            block_00001 = { text = { ja = { { "first" } } }, line = 10 },
+            block_00002 = { text = { ja = { { "unnumbered" } } } },
+        }
+        "#;
+
+        let value = parse_tokens(&tokenize(input).unwrap()).unwrap();
+        let by_line = ScenarioOptions { order_by_line: true, ..Default::default() };
+        assert_eq!(extract_secnario(&value, None, &ScenarioOptions::default()).unwrap(), vec!["second", "first", "unnumbered"]);
+        assert_eq!(extract_secnario(&value, None, &by_line).unwrap(), vec!["first", "second", "unnumbered"]);
+    }
+
+    #[test]
+    fn test_lang() {
+        let input = r#"ast = {
+            block_00000 = { text = { ja = { { "「お兄」" } }, en = { { "\"Bro\"" } } } },
+            block_00001 = { text = { ja = { { "……" } } } },
+        }
+        "#;
+        let value = parse_tokens(&tokenize(input).unwrap()).unwrap();
+        let en = ScenarioOptions { lang: Some("en".to_string()), ..Default::default() };
+        assert_eq!(extract_secnario(&value, None, &en).unwrap(), vec!["\"Bro\""]);
+        assert_eq!(text_scan::texts(input, "en", &ParseOptions::default()).unwrap(), vec!["\"Bro\""]);
+        let ko = ScenarioOptions { lang: Some("ko".to_string()), ..Default::default() };
+        assert_eq!(extract_secnario(&value, None, &ko).unwrap_err().to_string(), "No block has a ko text channel, the script has en, ja");
+    }
+
+    #[test]
+    fn test_line_kind() {
+        let input = r#"ast = {
+            block_00000 = { text = { ja = { { name = {"妃愛"}, "「お兄」" } } } },
+            block_00001 = { text = { ja = { { "……" } } } },
+        }
+        "#;
+
+        let value = parse_tokens(&tokenize(input).unwrap()).unwrap();
+        let lines = extract_lines(&value, None, &ScenarioOptions::default()).unwrap();
+        assert_eq!(lines, vec![(LineKind::Dialogue, "「お兄」".to_string()), (LineKind::Narration, "……".to_string())]);
+        let narration = ScenarioOptions { kind: Some(LineKind::Narration), ..Default::default() };
+        assert_eq!(extract_secnario(&value, None, &narration).unwrap(), vec!["……"]);
+    }
+
+    #[test]
+    fn test_comments() {
+        let input = "-- generated\nastver = 2.0 --[[ old:\n ast = { ]] ast = {\n\tblock_00000 = { --[==[ ]] ]==] line = -18, -- trailing\n\t\ttext = { ja = { { \"a--b\" } } } },\n}\n";
+        let value = parse_tokens(&tokenize(input).unwrap()).unwrap();
+        assert_eq!(extract_secnario(&value, None, &ScenarioOptions::default()).unwrap(), vec!["a--b"]);
+        assert!(tokenize("--[[ open").is_err());
+    }
+
+    #[test]
+    fn test_hex_integers() {
+        let tokens = tokenize("{\"bg\", color=0xFFFFFF, mask=-0X1f, 0}").unwrap();
+        assert_eq!(tokens[5], Token::IntegerLiteral(0xFFFFFF, Some("0xFFFFFF".to_string())));
+        assert_eq!(tokens[9], Token::IntegerLiteral(-0x1f, Some("-0X1f".to_string())));
+        assert_eq!(tokens[11], Token::IntegerLiteral(0, None));
+        assert!(tokenize("0x").is_err());
+        assert_eq!(tokenize("t = {time=1.2.3}").unwrap_err().to_string(), "Invalid number 1.2.3: invalid float literal at line 1, column 11");
+        assert_eq!(tokenize("n = 99999999999999999999").unwrap_err().to_string(), "Invalid number 99999999999999999999: number too large to fit in target type at line 1, column 5");
+        assert_eq!(tokenize("n = ３").unwrap(), vec![Token::Identifier("n".to_string()), Token::Equal, Token::Identifier("３".to_string())]);
+    }
+
+    #[test]
+    fn test_value_conversions() {
+        let value = Value::from(vec![Value::from("bg"), Value::from(18)]);
+        let table: LuaTable = value.try_into().unwrap();
+        assert_eq!(String::try_from(table.array.into_iter().next().unwrap()).unwrap(), "bg");
+        assert!(i64::try_from(Value::from(1.5)).is_err());
+    }
+
+    #[test]
+    fn test_float_literals() {
+        let tokens = tokenize("{1e-3, 2.5E2, .5, -.25, 3e+1, 1.}").unwrap();
+        let floats: Vec<f64> = tokens.iter().filter_map(|t| match t {
+            Token::FloatLiteral(f, _) => Some(*f),
+            _ => None,
+        }).collect();
+        assert_eq!(floats, vec![0.001, 250.0, 0.5, -0.25, 30.0, 1.0]);
+        assert!(tokenize("-.x").is_err());
+
+        let input = "ast = {\n\tvolume = { 1e-3, 2.5E2, .5 },\n}\n";
+        let ast = parse_tokens(&tokenize(input).unwrap()).unwrap();
+        let script = reconstruct_script(&ast, &WriteOptions::default()).unwrap();
+        let reparsed = parse_tokens(&tokenize(&script).unwrap()).unwrap();
+        assert_eq!(format!("{:?}", reparsed), format!("{:?}", ast));
+    }
+
+    #[test]
+    fn test_number_literals_kept() {
+        let input = "astver = 2.0\nast = {\n\tblock_00000 = { {\"fg\", lv=2.20, time=1e5, color=0xFF, 3, 1.5} },\n}\n";
+        let mut ast = parse_tokens(&tokenize(input).unwrap()).unwrap();
+        let script = reconstruct_script(&ast, &WriteOptions::default()).unwrap();
+        assert!(script.contains("astver = 2.0"));
+        assert!(script.contains("lv=2.20") && script.contains("time=1e5") && script.contains("color=0xFF"), "{}", script);
+        assert!(script.contains("\t\t\t3,\n") && script.contains("\t\t\t1.5,\n"), "{}", script);
+
+        let block = ast.get_mut("ast").and_then(Value::as_table_mut).and_then(|ast| ast.get_mut("block_00000")).and_then(Value::as_table_mut).unwrap();
+        let fg = block.array[0].as_table_mut().unwrap();
+        if let Some(Value::Float(lv, _)) = fg.get_mut("lv") {
+            *lv = 3.5;
+        }
+        assert!(reconstruct_script(&ast, &WriteOptions::default()).unwrap().contains("lv=3.5"));
+    }
+
+    #[test]
+    fn test_empty_script() {
+        for input in ["", " \r\n\t", "\u{feff}\n"] {
+            let e = parse_source(input.to_string(), Path::new("a.ast"), &ParseOptions::default()).unwrap_err();
+            assert_eq!(e.to_string(), "a.ast: the script is empty");
+            assert!(e.downcast_ref::<EmptyScript>().is_some());
+        }
+        assert!(parse_source("[]".to_string(), Path::new("a.ast"), &ParseOptions::default()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_long_strings() {
+        let options = WriteOptions { long_strings: true, ..Default::default() };
+        assert_eq!(string_to_script("He said \"hi\"", &options), "[[He said \"hi\"]]");
+        assert_eq!(string_to_script("plain", &options), "\"plain\"");
+        assert_eq!(string_to_script("a\r\nb", &options), "\"a\\r\\nb\"");
+        for text in ["He said \"hi\"\nthen left", "\"[[x]]\"", "\nstarts on a new line", "\"ends\"]", "'q' ]=] ]]"] {
+            let written = string_to_script(text, &options);
+            assert!(written.starts_with('['), "{}", written);
+            assert_eq!(tokenize(&written).unwrap(), vec![Token::StringLiteral(text.to_string())], "{}", written);
+        }
+        let escape = WriteOptions { newlines: Some(Newlines::Escape), ..options };
+        assert_eq!(string_to_script("\"a\"\nb", &escape), "\"\\\"a\\\"\\nb\"");
+        let raw = WriteOptions { newlines: Some(Newlines::Raw), ..Default::default() };
+        assert_eq!(string_to_script("a\nb", &raw), "[[a\nb]]");
+        assert_eq!(string_to_script("\"a\"", &raw), "\"\\\"a\\\"\"");
+        let input = "t = { [==[\r\n{ \"}\" ]] ]==], x = 1 }";
+        let ast = parse_checked(input, Path::new("a.ast"), &ParseOptions::default()).unwrap();
+        assert_eq!(ast["t"].as_table().unwrap().array[0].as_string().unwrap(), "{ \"}\" ]] ");
+        assert!(tokenize("t = [[open").is_err());
+    }
+
+    #[test]
+    fn test_minify() {
+        let input = "astver = 2.0\n-- note\nast = {\n\tblock_00000 = { -- opening\n\t\t{\"bg\", time = 2000},\n\t\ttext = { ja = { { \"a b\" } } },\n\t},\n}\n";
+        let ast = parse_checked(input, Path::new("a.ast"), &ParseOptions::default()).unwrap();
+        let options = WriteOptions { minify: true, ..Default::default() };
+        let script = reconstruct_script(&ast, &options).unwrap();
+        assert_eq!(script, "astver=2.0\nast={block_00000={{\"bg\",time=2000},text={ja={{\"a b\"}}}}}\n");
+        assert!(equivalent::first_difference(&ast, &parse_tokens(&tokenize(&script).unwrap()).unwrap()).is_none());
+    }
+
+    #[test]
+    fn test_top_level_order() {
+        let input = "astver = 2.0\nast = {}\nversion = \"1\"\nzz = 1\naa = 2\n";
+        let ast = parse_tokens(&tokenize(input).unwrap()).unwrap();
+        assert_eq!(reconstruct_script(&ast, &WriteOptions::default()).unwrap(), input);
+    }
+
+    #[test]
+    fn test_comments_kept() {
+        let input = "-- generated\nast = {\n\t-- chapter 1\n\tblock_00000 = { -- opening\n\t\t\"fg\", --[[ bg ]] text = \"a\",\n\t\t-- todo\n\t},\n}\n";
+        let mut ast = parse_checked(input, Path::new("a.ast"), &ParseOptions::default()).unwrap();
+        let script = reconstruct_script(&ast, &WriteOptions::default()).unwrap();
+        assert_eq!(script, "-- generated\nast = {\n\t-- chapter 1\n\tblock_00000={\n\t\t-- opening\n\t\t\"fg\",\n\t\t--[[ bg ]]\n\t\ttext=\"a\"\n\t\t-- todo\n\t}\n}\n");
+
+        let block = ast.get_mut("ast").and_then(Value::as_table_mut).and_then(|ast| ast.get_mut("block_00000")).and_then(Value::as_table_mut).unwrap();
+        block.remove("text");
+        assert!(!reconstruct_script(&ast, &WriteOptions::default()).unwrap().contains("bg"));
+    }
+
+    #[test]
+    fn test_strip_merged_marker() {
+        let merged = format!("{} from a.yaml sha256:00\nast = {{}}\n", MERGED_MARKER);
+        assert_eq!(strip_merged_marker(&merged), Some("ast = {}\n"));
+        assert_eq!(strip_merged_marker("ast = {}\n"), None);
+        assert!(parse_tokens(&tokenize(&merged).unwrap()).unwrap().contains_key("ast"));
+    }
+
+    #[test]
+    fn test_single_quoted_strings() {
+        let tokens = tokenize(r#"{'bg', file='it\'s "bg"', '\65'}"#).unwrap();
+        assert_eq!(tokens[1], Token::StringLiteral("bg".to_string()));
+        assert_eq!(tokens[5], Token::StringLiteral("it's \"bg\"".to_string()));
+        assert_eq!(tokens[7], Token::StringLiteral("A".to_string()));
+    }
+
+    #[test]
+    fn test_error_locations() {
+        let error = tokenize("ast = {\n\tblock_00000 = { ~ }\n}").unwrap_err();
+        assert_eq!(error.to_string(), "Unexpected character: ~ at line 2, column 18");
+
+        let parse = |input: &str| parse_source(input.to_string(), Path::new("a.ast"), &ParseOptions::default()).unwrap_err().to_string();
+        assert_eq!(parse("astver = 2.0\nast = {\n\t= 1,\n}\n"), "a.ast: Unexpected token: Equal at line 3, column 2");
+        assert_eq!(parse("astver = 2.0\nast"), "a.ast: Unexpected end of input at line 2, column 4");
+        assert_eq!(parse("astver = 2.0\ntitle = \"「お兄"), "a.ast: Unexpected end of file, expected \" to close the string at line 2, column 9");
+        let error = parse_tokens(&tokenize("ast = {\n\tblock_00000 = { line = 18,").unwrap()).unwrap_err();
+        assert_eq!(error.to_string(), "Unexpected end of input, expected '}' (at 10), the table opened at 5 is never closed");
+    }
+
+    #[test]
+    fn test_keep_going() {
+        let input = "astver = 2.0\nast = {\n\tblock_00000 = { = 1 },\n\tblock_00001 = { line = 2 },\n\tblock_00002 = { \"~xy\" ~ },\n}\n";
+        let (ast, errors) = parse_recovering(input, Path::new("a.ast"), &ParseOptions::default());
+        let errors: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+        assert_eq!(errors, vec![
+            "a.ast: Unexpected token: Equal at line 3, column 18",
+            "a.ast: Unexpected character: ~ at line 5, column 24",
+        ]);
+        let blocks: Vec<&String> = iter_blocks(&ast, None).map(|(name, _)| name).collect();
+        assert_eq!(blocks, vec!["block_00001"]);
+        assert!(ast.contains_key("astver"));
+
+        let options = ParseOptions { keep_going: true, ..ParseOptions::default() };
+        let error = parse_source(input.to_string(), Path::new("a.ast"), &options).unwrap_err().to_string();
+        assert!(error.starts_with("a.ast: 2 errors\n  a.ast: Unexpected token"), "{}", error);
+    }
+
+    #[test]
+    fn test_tokenizer_is_lazy() {
+        let mut tokens = Tokenizer::new("ast = { 1 ~ }");
+        assert_eq!(tokens.next().unwrap().unwrap(), (Token::Identifier("ast".to_string()), 0..3));
+        assert_eq!(tokens.by_ref().take(3).count(), 3);
+        assert!(tokens.next().unwrap().is_err());
+        assert!(tokens.next().is_none());
+    }
+
+    #[test]
+    fn test_bracketed_keys() {
+        let input = "ast = {\n\tsystem = { [\"save title\"] = \"x\", [ 1 ] = { ['a.b'] = 2 }, [-2] = 3 },\n}\n";
+        let ast = parse_tokens(&tokenize(input).unwrap()).unwrap();
+        let system = ast["ast"].as_table().unwrap().get("system").and_then(Value::as_table).unwrap();
+        assert!(system.contains_key("save title"));
+        assert!(system.contains_key("[1]"));
+        let script = reconstruct_script(&ast, &WriteOptions::default()).unwrap();
+        assert!(script.contains("[\"save title\"]=") && script.contains("[\"a.b\"]=") && script.contains("[1]=") && script.contains("[-2]="));
+        let reparsed = parse_tokens(&tokenize(&script).unwrap()).unwrap();
+        assert_eq!(format!("{:?}", reparsed), format!("{:?}", ast));
+        assert!(tokenize("[\"a\" = 1").is_err());
+    }
+
+    #[test]
+    fn test_max_depth() {
+        let input = "ast = {\n\tblock_00000 = { { {} } },\n}\n";
+        let parse = |max_depth| parse_source(input.to_string(), Path::new("a.ast"), &ParseOptions { max_depth, ..ParseOptions::default() });
+        assert!(parse(Some(4)).is_ok());
+        assert_eq!(parse(Some(3)).unwrap_err().to_string(), "a.ast: Tables nested more than 3 deep at line 2, column 20");
+
+        let deep = format!("t = {}{}", "{".repeat(100_000), "}".repeat(100_000));
+        assert!(parse_checked(&deep, Path::new("a.ast"), &ParseOptions::default()).unwrap_err().to_string().contains("nested more than 200 deep"));
+    }
+
+
+    #[test]
+    fn test_bare_words() {
+        let input = "t = {\n\tnil,\n\t\"true\",\n\tloop=true\n}\n";
+        let ast = parse_tokens(&tokenize(input).unwrap()).unwrap();
+        let table = ast["t"].as_table().unwrap();
+        assert!(matches!(&table.array[0], Value::BareWord(word) if word == "nil"));
+        assert_eq!(table.array[1].as_string().unwrap(), "true");
+        assert_eq!(reconstruct_script(&ast, &WriteOptions::default()).unwrap(), input);
+        let quoted = parse_tokens(&tokenize("t = { nil, \"true\", loop=\"true\" }").unwrap()).unwrap();
+        assert_eq!(equivalent::first_difference(&ast, &quoted).unwrap().path, "t.loop");
+    }
+
+    #[test]
+    fn test_duplicate_keys() {
+        let input = "ast = {\n\tblock_00000 = { mode = 1, mode = 2, mode = 3 },\n}\n";
+        let parse = |duplicate_keys| {
+            let options = ParseOptions { duplicate_keys, ..ParseOptions::default() };
+            parse_source(input.to_string(), Path::new("a.ast"), &options)
+        };
+        let mode = |duplicate_keys| {
+            let ast = parse(duplicate_keys).unwrap();
+            let block = ast["ast"].as_table().unwrap().get("block_00000").and_then(Value::as_table).unwrap();
+            format!("{:?}", block.get("mode").unwrap())
+        };
+        assert_eq!(mode(DuplicateKeys::First), "Integer(1, None)");
+        assert_eq!(mode(DuplicateKeys::Last), "Integer(3, None)");
+        assert_eq!(mode(DuplicateKeys::KeepAll), format!("{:?}", Value::from(vec![Value::from(1), Value::from(2), Value::from(3)])));
+        assert_eq!(parse(DuplicateKeys::Error).unwrap_err().to_string(), "a.ast: Duplicate key mode at line 2, column 28");
+    }
+
+    #[test]
+    fn test_semicolon_separators() {
+        let input = "ast = {\n\tblock_00000 = { {\"bg\"; file=\"bg001a\"}; line = 18; },\n}\n";
+        let ast = parse_tokens(&tokenize(input).unwrap()).unwrap();
+        let options = WriteOptions { separator: syntax::Separator::Semicolon, ..Default::default() };
+        let script = reconstruct_script(&ast, &options).unwrap();
+        assert!(script.contains(";\n") && !script.contains(','));
+        let reparsed = parse_tokens(&tokenize(&script).unwrap()).unwrap();
+        assert_eq!(format!("{:?}", reparsed), format!("{:?}", ast));
+    }
+
+    #[test]
+    fn test_merge_log() {
+        let dir = crate::test_dir("merge_log");
+        let log = dir.join("merge.log");
+        let yaml = dir.join("a.yaml");
+        std::fs::write(&yaml, "abc").unwrap();
+        std::fs::write(&log, "- earlier entry\n").unwrap();
+        append_merge_log(&log, Path::new("a.ast"), &yaml, Path::new("out/a.ast"), 3).unwrap();
+        append_merge_log(&log, Path::new("b.ast"), &yaml, Path::new("out/b.ast"), 0).unwrap();
+
+        let text = std::fs::read_to_string(&log).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], "- earlier entry");
+        let hash = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+        for (line, (name, changed)) in lines[1..].iter().zip([("a", 3), ("b", 0)]) {
+            let (time, rest) = line.strip_prefix("- ").unwrap().split_once(' ').unwrap();
+            humantime::parse_rfc3339(time).unwrap();
+            assert_eq!(rest, format!("merged {}.ast -> out/{}.ast: {} entries changed, {} sha256:{}", name, name, changed, yaml.display(), hash));
+        }
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
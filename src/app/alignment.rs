@@ -0,0 +1,91 @@
+use std::fmt;
+
+/// Lines shown on each side of the point where alignment drifted.
+const CONTEXT_LINES: usize = 2;
+
+/// Openings that mark a line as speech in either language.
+const QUOTE_OPENINGS: &[char] = &['「', '『', '（', '"', '“', '\'', '‘', '('];
+
+fn is_quoted(text: &str) -> bool {
+    text.trim_start().starts_with(QUOTE_OPENINGS)
+}
+
+/// Where the translation stops lining up with the source.
+#[derive(Debug, PartialEq)]
+pub struct Drift {
+    pub source_len: usize,
+    pub translation_len: usize,
+    /// First entry that probably belongs to a different source line
+    pub index: usize,
+    /// Block of the source line at `index`, `None` past the end of the source
+    pub block: Option<String>,
+    /// `(index, source, translation)` around `index`
+    pub context: Vec<(usize, Option<String>, Option<String>)>,
+}
+
+impl fmt::Display for Drift {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "the script has {} lines but the translation has {}", self.source_len, self.translation_len)?;
+        match &self.block {
+            Some(block) => write!(f, "; alignment probably drifts at entry {} in {}", self.index, block)?,
+            None => write!(f, "; alignment probably drifts at entry {}", self.index)?,
+        }
+        for (index, source, translation) in &self.context {
+            let marker = if *index == self.index { ">" } else { " " };
+            write!(f, "\n{} {:>5} source:      {}", marker, index, source.as_deref().unwrap_or("(none)"))?;
+            write!(f, "\n  {:>5} translation: {}", "", translation.as_deref().unwrap_or("(none)"))?;
+        }
+        Ok(())
+    }
+}
+
+/// Compares the entry counts of `source` and `translation`, `None` when they
+/// match. Otherwise the drift is placed at the first entry where one side is
+/// quoted speech and the other is not, which is usually the first line that
+/// was dropped or split, or at the end of the shorter list. `blocks` names
+/// the block of every source line.
+pub fn check(blocks: &[String], source: &[String], translation: &[String]) -> Option<Drift> {
+    if source.len() == translation.len() {
+        return None;
+    }
+    let index = source.iter()
+        .zip(translation)
+        .position(|(source, translated)| is_quoted(source) != is_quoted(translated))
+        .unwrap_or(source.len().min(translation.len()));
+    let end = (index + CONTEXT_LINES + 1).min(source.len().max(translation.len()));
+    let context = (index.saturating_sub(CONTEXT_LINES)..end)
+        .map(|i| (i, source.get(i).cloned(), translation.get(i).cloned()))
+        .collect();
+    Some(Drift {
+        source_len: source.len(),
+        translation_len: translation.len(),
+        index,
+        block: blocks.get(index).cloned(),
+        context,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(items: &[&str]) -> Vec<String> {
+        items.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_drift() {
+        let blocks = strings(&["block_00000", "block_00000", "block_00001", "block_00002"]);
+        let source = strings(&["「お兄」", "……", "「むふー」", "朝だ"]);
+        let translation = strings(&["\"Big bro\"", "\"Mmh\"", "It's morning"]);
+        let drift = check(&blocks, &source, &translation).unwrap();
+        assert_eq!(drift.index, 1);
+        assert_eq!(drift.block.as_deref(), Some("block_00000"));
+        assert_eq!(drift.context.len(), 4);
+        assert!(drift.to_string().starts_with("the script has 4 lines but the translation has 3"));
+
+        assert!(check(&blocks, &source, &source).is_none());
+        let drift = check(&blocks, &source, &source[..3]).unwrap();
+        assert_eq!((drift.index, drift.block.as_deref()), (3, Some("block_00002")));
+    }
+}
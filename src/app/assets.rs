@@ -0,0 +1,222 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    path::Path,
+};
+use anyhow::Result;
+use crate::{LuaTable, Value};
+
+/// Attributes naming an image or sound file.
+const FILE_ATTRS: &[&str] = &["file"];
+/// Layered sprite parts carried by `fg` entries besides their base file.
+const PART_ATTRS: &[&str] = &["face", "head"];
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct AssetUse {
+    pub command: String,
+    /// Attribute holding the name: `file`, `face`, `head` or `exNN`
+    pub attr: String,
+    pub name: String,
+    /// Base sprite a part belongs to, so a renamed part can be traced back
+    pub owner: Option<String>,
+    /// The command's `path`, such as `:bg/` or `:fg/hiy/[表情]/`
+    pub path: Option<String>,
+    pub block: String,
+}
+
+fn is_part_attr(key: &str) -> bool {
+    PART_ATTRS.contains(&key)
+        || key.strip_prefix("ex").is_some_and(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()))
+}
+
+fn attr_text(value: &Value) -> Option<String> {
+    value.as_string().cloned().or_else(|| value.as_integer().map(|i| i.to_string()))
+}
+
+fn collect(value: &Value, block: &str, uses: &mut Vec<AssetUse>) {
+    if let Some(command) = crate::command_name(value) {
+        let owner = crate::command_attr(value, "file").and_then(attr_text);
+        let path = crate::command_attr(value, "path").and_then(Value::as_string);
+        let attrs = value.as_table().into_iter().flat_map(LuaTable::fields);
+        for (key, attr) in attrs {
+            let is_file = FILE_ATTRS.contains(&key.as_str());
+            if !is_file && !is_part_attr(key) {
+                continue;
+            }
+            if let Some(name) = attr_text(attr) {
+                uses.push(AssetUse {
+                    command: command.to_string(),
+                    attr: key.clone(),
+                    name,
+                    owner: if is_file { None } else { owner.clone() },
+                    path: path.cloned(),
+                    block: block.to_string(),
+                });
+            }
+        }
+    }
+    if let Value::Table(table) = value {
+        table.values().for_each(|item| collect(item, block, uses));
+    }
+}
+
+/// Every asset referenced by the script, including `vo` entries nested in text.
+pub fn asset_uses(ast: &LuaTable, astver: Option<crate::compat::AstVersion>) -> Vec<AssetUse> {
+    let mut uses = Vec::new();
+    for (block_key, block) in crate::iter_blocks(ast, astver) {
+        block.values().for_each(|item| collect(item, block_key, &mut uses));
+    }
+    uses.sort();
+    uses
+}
+
+fn describe(asset: &AssetUse) -> String {
+    let name = format!("{}{}", asset.path.as_deref().unwrap_or(""), asset.name);
+    match &asset.owner {
+        Some(owner) => format!("{} {}={} (of {})", asset.command, asset.attr, name, owner),
+        None => format!("{} {}={}", asset.command, asset.attr, name),
+    }
+}
+
+pub fn print_report(uses: &[AssetUse]) {
+    let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+    for asset in uses {
+        *counts.entry(describe(asset)).or_default() += 1;
+    }
+    for (asset, count) in counts {
+        println!("{:>6}  {}", count, asset);
+    }
+}
+
+/// The files under an asset directory without their extensions, lowercased
+/// since the game runs on case-insensitive Windows file systems.
+#[derive(Default)]
+struct AssetFiles {
+    stems: HashSet<String>,
+    /// Each file's directories and stem, relative to the asset directory
+    paths: Vec<Vec<String>>,
+}
+
+impl AssetFiles {
+    fn collect(&mut self, dir: &Path, parents: &mut Vec<String>) -> Result<()> {
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            let Some(name) = path.file_name().and_then(|name| name.to_str()).map(str::to_lowercase) else {
+                continue;
+            };
+            if path.is_dir() {
+                parents.push(name);
+                self.collect(&path, parents)?;
+                parents.pop();
+            } else if let Some(stem) = path.file_stem().and_then(|stem| stem.to_str()).map(str::to_lowercase) {
+                self.stems.insert(stem.clone());
+                self.paths.push(parents.iter().cloned().chain(std::iter::once(stem)).collect());
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether a file matches an asset. Names with a `path` resolve from the
+    /// asset directory, `:fg/hiy/` being `fg/hiy/`, where a `[表情]` style
+    /// template stands for any text; names without one may be anywhere.
+    fn contains(&self, asset: &AssetUse) -> bool {
+        let Some(path) = &asset.path else {
+            return self.stems.contains(&asset.name.to_lowercase());
+        };
+        let full = format!("{}/{}", path.trim_start_matches(':'), asset.name).replace('\\', "/").to_lowercase();
+        let pattern: Vec<&str> = full.split('/').filter(|segment| !segment.is_empty()).collect();
+        self.paths.iter().any(|file| {
+            file.len() == pattern.len() && file.iter().zip(&pattern).all(|(segment, pattern)| template_matches(pattern, segment))
+        })
+    }
+}
+
+/// Matches one path segment against a pattern whose `[...]` parts stand for any text.
+fn template_matches(pattern: &str, text: &str) -> bool {
+    let mut literals = Vec::new();
+    let mut rest = pattern;
+    while let Some((literal, after)) = rest.split_once('[').and_then(|(literal, after)| Some((literal, after.split_once(']')?.1))) {
+        literals.push(literal);
+        rest = after;
+    }
+    if literals.is_empty() {
+        return pattern == text;
+    }
+    literals.push(rest);
+    let (first, last) = (literals[0], literals[literals.len() - 1]);
+    let Some(mut remaining) = text.strip_prefix(first) else {
+        return false;
+    };
+    for literal in &literals[1..literals.len() - 1] {
+        match remaining.find(literal) {
+            Some(at) => remaining = &remaining[at + literal.len()..],
+            None => return false,
+        }
+    }
+    remaining.ends_with(last)
+}
+
+/// Uses whose name matches no file under `asset_dir`. Scripts name assets
+/// without extension, so any file with the same stem counts.
+pub fn missing_assets<'a>(uses: &'a [AssetUse], asset_dir: &Path) -> Result<Vec<&'a AssetUse>> {
+    let mut files = AssetFiles::default();
+    files.collect(asset_dir, &mut Vec::new())?;
+    Ok(uses.iter().filter(|asset| !files.contains(asset)).collect())
+}
+
+pub fn print_missing(missing: &[&AssetUse]) {
+    for asset in missing {
+        println!("{}: {} is missing", asset.block, describe(asset));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sprite_parts() {
+        let input = r#"ast = {
+            block_00000 = {
+                {"fg", ch="hiy", file="fem_hiy_01a", face="hiy_smile", head="hiy_head2", ex05="hiy_blush", size="m"},
+                text = { vo = { {"vo", file="fem_hiy_00052", ch="hiy"} } },
+            },
+        }
+        "#;
+        let ast = crate::parse_tokens(&crate::tokenize(input).unwrap()).unwrap();
+        let uses = asset_uses(&ast, None);
+        let described: Vec<String> = uses.iter().map(describe).collect();
+        assert_eq!(described, vec![
+            "fg ex05=hiy_blush (of fem_hiy_01a)",
+            "fg face=hiy_smile (of fem_hiy_01a)",
+            "fg file=fem_hiy_01a",
+            "fg head=hiy_head2 (of fem_hiy_01a)",
+            "vo file=fem_hiy_00052",
+        ]);
+    }
+
+    #[test]
+    fn test_asset_paths() {
+        let dir = std::env::temp_dir().join("artemis_ast_assets_test");
+        let _ = std::fs::remove_dir_all(&dir);
+        for file in ["bg/BG001a.png", "fg/hiy/smile/hiy_face01.png", "sound/se001.ogg"] {
+            std::fs::create_dir_all(dir.join(file).parent().unwrap()).unwrap();
+            std::fs::write(dir.join(file), b"").unwrap();
+        }
+        let input = r#"ast = {
+            block_00000 = {
+                {"bg", file="bg001a", path=":bg/"},
+                {"bg", file="se001", path=":bg/"},
+                {"fg", file="hiy_face01", path=":fg/hiy/[表情]/"},
+                {"fg", file="hiy_face01", path=":fg/[表情]/"},
+                {"se", file="se001"},
+            },
+        }
+        "#;
+        let ast = crate::parse_tokens(&crate::tokenize(input).unwrap()).unwrap();
+        let uses = asset_uses(&ast, None);
+        let missing: Vec<String> = missing_assets(&uses, &dir).unwrap().into_iter().map(describe).collect();
+        assert_eq!(missing, vec!["bg file=:bg/se001", "fg file=:fg/[表情]/hiy_face01"]);
+        assert!(template_matches("hiy_[表情]_a", "hiy_smile_a"));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,477 @@
+use std::{
+    collections::{HashSet, VecDeque},
+    io::Write,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::{Condvar, Mutex},
+};
+use anyhow::{Result, anyhow};
+use clap::{Args, ValueEnum};
+use crate::{EmptyScript, ParseOptions, WriteOptions};
+
+/// Rough ratio between the size of a script on disk and the peak memory
+/// needed to tokenize, parse and rewrite it. Measured as the growth of the
+/// peak resident size between a 0.45 MB and a 4.5 MB script of 3,000 and
+/// 30,000 blocks: about 36 bytes per byte of script for extract and prune,
+/// and 55 for merge, which holds the script, its ast, the translation and
+/// the output at once. Rounded up so a merge is not underestimated.
+const MEMORY_FACTOR: u64 = 64;
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum BatchAction {
+    Extract,
+    Prune,
+    Merge,
+}
+
+#[derive(Args, Debug)]
+pub struct BatchArgs {
+    /// Action applied to every ast file
+    action: BatchAction,
+    /// Directory searched recursively for .ast files
+    input_dir: PathBuf,
+    /// Directory receiving the outputs, mirroring the input layout
+    output_dir: PathBuf,
+    /// Directory holding the translated files for merge (defaults to input_dir)
+    #[arg(long)]
+    yaml_dir: Option<PathBuf>,
+    /// Extension of the translated files read by merge
+    #[arg(long, default_value = "yaml")]
+    yaml_ext: String,
+    /// Number of files processed in parallel (defaults to the number of cores)
+    #[arg(short, long)]
+    jobs: Option<usize>,
+    /// Memory ceiling in MiB for the scripts being processed at the same time
+    #[arg(long)]
+    max_memory: Option<u64>,
+    /// Only process .ast files that git reports as modified or untracked in input_dir
+    #[arg(long)]
+    changed_only: bool,
+    /// Only process the files listed in this file, one per line, relative to input_dir
+    #[arg(long)]
+    files_from: Option<PathBuf>,
+    /// Merge only the scripts chapters.yaml in the yaml directory lists for this chapter, reading their translations from chapters/<name>/
+    #[arg(long, conflicts_with = "files_from")]
+    chapter: Option<String>,
+    /// Record every finished file as ok, failed or empty in this file while the run goes
+    #[arg(long)]
+    report: Option<PathBuf>,
+    /// Skip the files the --report of an earlier run lists as done, retrying the failed and unprocessed ones
+    #[arg(long, requires = "report")]
+    resume: bool,
+    #[command(flatten)]
+    scenario: crate::ScenarioOptions,
+    #[command(flatten)]
+    extract: crate::ExtractOptions,
+    #[command(flatten)]
+    prune: crate::PruneOptions,
+    #[command(flatten)]
+    merge: crate::MergeOptions,
+}
+
+/// Blocks workers until the estimated memory of the in-flight scripts fits
+/// under the configured ceiling.
+struct MemoryBudget {
+    limit: u64,
+    used: Mutex<u64>,
+    freed: Condvar,
+}
+
+impl MemoryBudget {
+    fn new(limit: u64) -> Self {
+        MemoryBudget { limit, used: Mutex::new(0), freed: Condvar::new() }
+    }
+
+    /// Reserves `cost` bytes and returns the amount actually reserved. A file
+    /// larger than the whole budget is clamped so it can still run alone.
+    fn acquire(&self, cost: u64) -> u64 {
+        let cost = cost.min(self.limit);
+        let mut used = self.used.lock().unwrap();
+        while *used + cost > self.limit {
+            used = self.freed.wait(used).unwrap();
+        }
+        *used += cost;
+        cost
+    }
+
+    fn release(&self, cost: u64) {
+        *self.used.lock().unwrap() -= cost;
+        self.freed.notify_all();
+    }
+}
+
+pub fn collect_ast_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_ast_files(&path, files)?;
+        } else if path.extension().is_some_and(|ext| ext == "ast") {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Files under `dir` with uncommitted changes, as absolute paths. Staged,
+/// unstaged and untracked files all count; deleted ones are left out.
+fn changed_files(dir: &Path) -> Result<HashSet<PathBuf>> {
+    let git = |args: &[&str]| -> Result<Vec<u8>> {
+        let output = Command::new("git").arg("-C").arg(dir).args(args).output()
+            .map_err(|e| anyhow!("Failed to run git: {}", e))?;
+        if !output.status.success() {
+            return Err(anyhow!("git {} failed in {}: {}", args.join(" "), dir.display(),
+                String::from_utf8_lossy(&output.stderr).trim()));
+        }
+        Ok(output.stdout)
+    };
+    let toplevel = String::from_utf8(git(&["rev-parse", "--show-toplevel"])?)?;
+    let toplevel = PathBuf::from(toplevel.trim_end());
+    let status = git(&["status", "--porcelain", "-z", "--untracked-files=all", "--", "."])?;
+
+    let mut changed = HashSet::new();
+    let mut entries = status.split(|&b| b == 0).filter(|entry| !entry.is_empty());
+    while let Some(entry) = entries.next() {
+        if entry.len() < 4 {
+            continue;
+        }
+        let path = toplevel.join(String::from_utf8_lossy(&entry[3..]).as_ref());
+        // renames and copies are followed by the original path
+        if matches!(entry[0], b'R' | b'C') {
+            entries.next();
+        }
+        if let Ok(path) = path.canonicalize() {
+            changed.insert(path);
+        }
+    }
+    Ok(changed)
+}
+
+/// Progress of a run, one `<status>\t<path>` line per finished file with
+/// its path relative to input_dir, written as each file finishes so a run
+/// that stops halfway can be resumed.
+struct Report {
+    file: Mutex<std::fs::File>,
+}
+
+impl Report {
+    /// Starts a new report, or adds to the one of the run being resumed.
+    fn open(path: &Path, resume: bool) -> Result<Self> {
+        let file = std::fs::OpenOptions::new().create(true).write(true).append(resume).truncate(!resume).open(path)
+            .map_err(|e| anyhow!("Failed to open report {}: {}", path.display(), e))?;
+        Ok(Report { file: Mutex::new(file) })
+    }
+
+    fn record(&self, status: &str, relative: &Path) -> Result<()> {
+        // a single write per line keeps lines intact across workers
+        let line = format!("{}\t{}\n", status, relative.display());
+        self.file.lock().unwrap().write_all(line.as_bytes())?;
+        Ok(())
+    }
+}
+
+/// Files a report lists as done, processed or found empty, by their last
+/// entry: a file that failed and then went through counts as done.
+fn finished(content: &str) -> HashSet<PathBuf> {
+    let mut done = HashSet::new();
+    for (status, path) in content.lines().filter_map(|line| line.split_once('\t')) {
+        if matches!(status, "ok" | "empty") {
+            done.insert(PathBuf::from(path));
+        } else {
+            done.remove(Path::new(path));
+        }
+    }
+    done
+}
+
+/// The scripts a run over many went through, by how it went for each.
+#[derive(Debug, Default)]
+pub struct Outcome {
+    pub done: Vec<PathBuf>,
+    /// Empty placeholder scripts, skipped
+    pub empty: Vec<PathBuf>,
+    pub failed: Vec<(PathBuf, anyhow::Error)>,
+    /// Scripts left out because the report of the run being resumed has them as done
+    pub resumed: usize,
+}
+
+impl Outcome {
+    pub fn is_ok(&self) -> bool {
+        self.failed.is_empty()
+    }
+
+    /// Scripts gone through, those left out by a resume aside.
+    pub fn total(&self) -> usize {
+        self.done.len() + self.empty.len() + self.failed.len()
+    }
+
+    /// Files `script` by how `result` went, returning the status a report
+    /// records for it.
+    pub(crate) fn record(&mut self, script: PathBuf, result: Result<()>) -> &'static str {
+        match result {
+            Err(e) if e.downcast_ref::<EmptyScript>().is_some() => {
+                self.empty.push(script);
+                "empty"
+            }
+            Err(e) => {
+                self.failed.push((script, e));
+                "failed"
+            }
+            _ => {
+                self.done.push(script);
+                "ok"
+            }
+        }
+    }
+}
+
+/// Runs `f` for one script of a run over many, with the messages it logs,
+/// and its error if it fails, grouped under the script.
+pub(crate) fn handle(f: impl FnOnce() -> Result<()>) -> Result<()> {
+    crate::logging::scoped(|| {
+        let result = f();
+        match &result {
+            Err(e) if e.downcast_ref::<EmptyScript>().is_some() => crate::logging::warn(format!("{:#}, skipped", e)),
+            Err(e) => crate::logging::warn(format!("{:#}", e)),
+            _ => {}
+        }
+        result
+    })
+}
+
+/// The directories of a batch run, as long paths on Windows.
+struct Dirs {
+    input: PathBuf,
+    output: PathBuf,
+    yaml: PathBuf,
+}
+
+fn process_file(args: &BatchArgs, dirs: &Dirs, parse: &ParseOptions, write: &WriteOptions, input: &Path) -> Result<()> {
+    let relative = input.strip_prefix(&dirs.input)?;
+    let output = dirs.output.join(relative);
+    if let Some(parent) = output.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    match args.action {
+        BatchAction::Extract => crate::extract_file(input, &output.with_extension("yaml"), parse, &args.scenario, &args.extract),
+        BatchAction::Prune => crate::prune_file(input, &output, parse, write, &args.prune),
+        BatchAction::Merge => {
+            let yaml_input = match &args.chapter {
+                Some(chapter) => crate::chapters::translation_path(&dirs.yaml, chapter, relative, &args.yaml_ext),
+                None => dirs.yaml.join(relative).with_extension(&args.yaml_ext),
+            };
+            crate::merge_file(input, &yaml_input, &output, parse, write, &args.scenario, &args.merge)
+        }
+    }
+}
+
+/// Runs the batch action over the scripts `args` select, returning how each
+/// went. Failing scripts do not stop the others.
+pub fn run(args: &BatchArgs, parse: &ParseOptions, write: &WriteOptions) -> Result<Outcome> {
+    if args.merge.emit_mapping.is_some() {
+        return Err(anyhow!("--emit-mapping records a single merge and cannot be used with batch"));
+    }
+    let input = crate::platform::long_path(&args.input_dir)?;
+    let dirs = Dirs {
+        yaml: args.yaml_dir.as_deref().map_or(Ok(input.clone()), crate::platform::long_path)?,
+        output: crate::platform::long_path(&args.output_dir)?,
+        input,
+    };
+    let mut files = Vec::new();
+    match (&args.files_from, &args.chapter) {
+        (Some(list), _) => {
+            for file in crate::filelist::read(list)? {
+                let file = dirs.input.join(file);
+                if !file.is_file() {
+                    return Err(anyhow!("{} lists {}, which does not exist", list.display(), file.display()));
+                }
+                files.push(file);
+            }
+        }
+        (None, Some(chapter)) => {
+            if !matches!(args.action, BatchAction::Merge) {
+                return Err(anyhow!("--chapter only applies to merge"));
+            }
+            let manifest = crate::chapters::ChapterManifest::load(&dirs.yaml)?;
+            for script in manifest.scripts(chapter)? {
+                let file = dirs.input.join(script);
+                if !file.is_file() {
+                    return Err(anyhow!("Chapter {} lists {}, which does not exist", chapter, file.display()));
+                }
+                files.push(file);
+            }
+        }
+        (None, None) => collect_ast_files(&dirs.input, &mut files)?,
+    }
+    files.sort();
+    if args.changed_only {
+        let changed = changed_files(&dirs.input)?;
+        files.retain(|file| file.canonicalize().is_ok_and(|file| changed.contains(&file)));
+    }
+    let mut resumed = 0;
+    if let (true, Some(report)) = (args.resume, &args.report) {
+        let content = std::fs::read_to_string(report)
+            .map_err(|e| anyhow!("Cannot resume from {}: {}", report.display(), e))?;
+        let done = finished(&content);
+        let before = files.len();
+        files.retain(|file| file.strip_prefix(&dirs.input).is_ok_and(|relative| !done.contains(relative)));
+        resumed = before - files.len();
+    }
+    let report = args.report.as_deref().map(|path| Report::open(path, args.resume)).transpose()?;
+
+    let jobs = match args.jobs {
+        Some(0) => return Err(anyhow!("--jobs must be at least 1")),
+        Some(jobs) => jobs,
+        None => std::thread::available_parallelism().map_or(1, |n| n.get()),
+    };
+    let budget = args.max_memory.map(|mib| MemoryBudget::new(mib * 1024 * 1024));
+    let total = files.len();
+    let queue = Mutex::new(files.into_iter().collect::<VecDeque<_>>());
+    let outcome = Mutex::new(Outcome { resumed, ..Outcome::default() });
+
+    std::thread::scope(|scope| {
+        for _ in 0..jobs.min(total) {
+            scope.spawn(|| loop {
+                let Some(input) = queue.lock().unwrap().pop_front() else {
+                    break;
+                };
+                let cost = std::fs::metadata(&input).map_or(0, |m| m.len() * MEMORY_FACTOR);
+                let reserved = budget.as_ref().map(|b| b.acquire(cost));
+                let result = handle(|| process_file(args, &dirs, parse, write, &input));
+                if let (Some(budget), Some(reserved)) = (&budget, reserved) {
+                    budget.release(reserved);
+                }
+                let relative = input.strip_prefix(&dirs.input).map(Path::to_path_buf);
+                let status = outcome.lock().unwrap().record(input, result);
+                if let (Some(report), std::result::Result::Ok(relative)) = (&report, relative) {
+                    if let Err(e) = report.record(status, &relative) {
+                        crate::logging::warn(format!("Failed to update the report: {}", e));
+                    }
+                }
+            });
+        }
+    });
+    Ok(outcome.into_inner().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_finished() {
+        let report = "ok\ta.ast\nfailed\tb.ast\nempty\tsub/c.ast\nok\td.ast\nfailed\td.ast\nfailed\te.ast\nok\te.ast\nok\tf.a";
+        let mut done: Vec<PathBuf> = finished(report).into_iter().collect();
+        done.sort();
+        assert_eq!(done, ["a.ast", "e.ast", "f.a", "sub/c.ast"].map(PathBuf::from));
+    }
+
+    #[test]
+    fn test_memory_budget() {
+        let budget = MemoryBudget::new(100);
+        assert_eq!(budget.acquire(60), 60);
+        assert_eq!(budget.acquire(40), 40);
+        budget.release(40);
+        budget.release(60);
+        assert_eq!(*budget.used.lock().unwrap(), 0);
+
+        // a file larger than the whole budget runs alone
+        assert_eq!(budget.acquire(500), 100);
+        budget.release(100);
+
+        let first = budget.acquire(60);
+        let acquired = std::sync::atomic::AtomicBool::new(false);
+        std::thread::scope(|scope| {
+            let waiting = scope.spawn(|| {
+                let cost = budget.acquire(60);
+                acquired.store(true, std::sync::atomic::Ordering::SeqCst);
+                cost
+            });
+            std::thread::sleep(std::time::Duration::from_millis(50));
+            assert!(!acquired.load(std::sync::atomic::Ordering::SeqCst));
+            budget.release(first);
+            assert_eq!(waiting.join().unwrap(), 60);
+        });
+        assert_eq!(*budget.used.lock().unwrap(), 60);
+    }
+
+    /// A new directory with the scripts a.ast, b.ast and c.ast under `scripts`.
+    fn scripts_dir(name: &str) -> PathBuf {
+        let dir = crate::test_dir(name);
+        std::fs::create_dir_all(dir.join("scripts")).unwrap();
+        for name in ["a", "b", "c"] {
+            let script = format!("ast = {{\n\tblock_00000 = {{ text = {{ ja = {{ {{ \"{}\" }} }} }} }},\n}}\n", name);
+            std::fs::write(dir.join("scripts").join(name).with_extension("ast"), script).unwrap();
+        }
+        dir
+    }
+
+    /// `batch extract` with `args` from `dir/scripts` to `dir/yaml`.
+    fn run_batch(dir: &Path, args: &[&str]) -> Result<Outcome> {
+        use clap::Parser;
+
+        #[derive(Parser)]
+        struct Cli {
+            #[command(flatten)]
+            batch: BatchArgs,
+        }
+
+        let scripts = dir.join("scripts").display().to_string();
+        let output = dir.join("yaml").display().to_string();
+        let cli = Cli::try_parse_from(["batch", "extract", &scripts, &output].iter().chain(args)).unwrap();
+        run(&cli.batch, &ParseOptions::default(), &WriteOptions::default())
+    }
+
+    #[test]
+    fn test_jobs() {
+        let dir = scripts_dir("batch_jobs_zero");
+        assert_eq!(run_batch(&dir, &["--jobs", "0"]).unwrap_err().to_string(), "--jobs must be at least 1");
+        std::fs::remove_dir_all(dir).unwrap();
+
+        for jobs in ["1", "2", "8"] {
+            let dir = scripts_dir("batch_jobs");
+            let outcome = run_batch(&dir, &["--jobs", jobs]).unwrap();
+            assert_eq!(outcome.done.len(), 3, "--jobs {}", jobs);
+            assert!(dir.join("yaml/b.yaml").is_file());
+            std::fs::remove_dir_all(dir).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_max_memory() {
+        // the budget only holds workers back, never drops a script
+        let dir = scripts_dir("batch_max_memory");
+        assert_eq!(run_batch(&dir, &["--jobs", "3", "--max-memory", "1"]).unwrap().done.len(), 3);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_changed_only() {
+        let dir = scripts_dir("batch_changed_only");
+        let scripts = dir.join("scripts");
+        let git = |args: &[&str]| {
+            let status = Command::new("git").arg("-C").arg(&dir)
+                .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+                .args(args).output().unwrap().status;
+            assert!(status.success(), "git {:?}", args);
+        };
+        git(&["init", "-q"]);
+        git(&["add", "."]);
+        git(&["commit", "-q", "-m", "scripts"]);
+        // a.ast is modified, b.ast untouched, c.ast deleted and d.ast new
+        std::fs::write(scripts.join("a.ast"), "ast = {\n\tblock_00000 = { text = { ja = { { \"A\" } } } },\n}\n").unwrap();
+        std::fs::remove_file(scripts.join("c.ast")).unwrap();
+        std::fs::copy(scripts.join("b.ast"), scripts.join("d.ast")).unwrap();
+
+        let mut changed: Vec<PathBuf> = changed_files(&scripts).unwrap().into_iter().collect();
+        changed.sort();
+        let scripts = scripts.canonicalize().unwrap();
+        assert_eq!(changed, [scripts.join("a.ast"), scripts.join("d.ast")]);
+
+        let mut done: Vec<String> = run_batch(&dir, &["--changed-only"]).unwrap().done.iter()
+            .map(|path| path.file_name().unwrap().to_string_lossy().into_owned())
+            .collect();
+        done.sort();
+        assert_eq!(done, ["a.ast", "d.ast"]);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,93 @@
+/// Marks that set the direction of the text around them: LRM, RLM, the
+/// Arabic letter mark, and the embedding and isolate controls.
+const DIRECTION_MARKS: &[char] = &[
+    '\u{200E}', '\u{200F}', '\u{061C}',
+    '\u{202A}', '\u{202B}', '\u{202C}', '\u{202D}', '\u{202E}',
+    '\u{2066}', '\u{2067}', '\u{2068}', '\u{2069}',
+];
+
+const LRM: char = '\u{200E}';
+const RLM: char = '\u{200F}';
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Direction {
+    Ltr,
+    Rtl,
+}
+
+/// The direction of a strongly directional character: Hebrew and Arabic
+/// script are right-to-left, other letters left-to-right. Digits, spaces
+/// and punctuation take the direction of the text around them.
+fn direction(ch: char) -> Option<Direction> {
+    match ch {
+        '\u{0590}'..='\u{08FF}' | '\u{FB1D}'..='\u{FDFF}' | '\u{FE70}'..='\u{FEFF}' => Some(Direction::Rtl),
+        _ if ch.is_alphabetic() => Some(Direction::Ltr),
+        _ => None,
+    }
+}
+
+fn is_mixed(text: &str) -> bool {
+    let mut directions = text.chars().filter_map(direction);
+    let Some(first) = directions.next() else {
+        return false;
+    };
+    directions.any(|other| other != first)
+}
+
+/// Whether a line mixes right-to-left and left-to-right text without any
+/// direction mark. The engine lays text out in logical order, so such a line
+/// shows punctuation and embedded names on the wrong side.
+pub fn needs_marks(text: &str) -> bool {
+    is_mixed(text) && !text.contains(DIRECTION_MARKS)
+}
+
+/// Closes every run written against the line's direction with a mark of the
+/// line's direction, so the punctuation and spaces after the run go back to
+/// the line: "!Hello שלום" reads "!Hello<RLM> שלום". The line's direction is
+/// that of its first letter. Lines that need no marks are returned as they are.
+pub fn insert_marks(text: &str) -> String {
+    if !needs_marks(text) {
+        return text.to_string();
+    }
+    let Some(base) = text.chars().find_map(direction) else {
+        return text.to_string();
+    };
+    let mark = if base == Direction::Rtl { RLM } else { LRM };
+    let mut output = String::with_capacity(text.len() + 8);
+    let mut in_opposite_run = false;
+    for ch in text.chars() {
+        match direction(ch) {
+            Some(found) if found == base && in_opposite_run => {
+                output.push(mark);
+                in_opposite_run = false;
+            }
+            Some(found) if found != base => in_opposite_run = true,
+            _ => {}
+        }
+        // close the run before anything that is not part of a word
+        if in_opposite_run && direction(ch).is_none() && !ch.is_numeric() && ch != '\'' && ch != '-' {
+            output.push(mark);
+            in_opposite_run = false;
+        }
+        output.push(ch);
+    }
+    if in_opposite_run {
+        output.push(mark);
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_direction_marks() {
+        assert!(!needs_marks("שלום, עולם!"));
+        assert!(!needs_marks("Hello, world!"));
+        assert!(needs_marks("שלום Hiyori!"));
+        assert_eq!(insert_marks("שלום Hiyori!"), "שלום Hiyori\u{200F}!");
+        assert_eq!(insert_marks("مرحبا Mr. Sora, كيف"), "مرحبا Mr\u{200F}. Sora\u{200F}, كيف");
+        assert!(!needs_marks(&insert_marks("שלום Hiyori!")));
+    }
+}
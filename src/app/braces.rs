@@ -0,0 +1,228 @@
+use std::fmt;
+
+/// How many unclosed `{` are listed in the error message.
+const REPORTED_OPENS: usize = 5;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnclosedBrace {
+    /// 1-based position of the `{`
+    pub line: usize,
+    pub column: usize,
+    /// Nesting depth the brace was opened at, 0 for top level tables
+    pub depth: usize,
+    /// 0-based line a `}` most likely belongs in front of, `None` for end of file
+    pub suggestion: Option<usize>,
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct BraceReport {
+    pub unclosed: Vec<UnclosedBrace>,
+    /// 1-based `(line, column)` of every `}` without an opening brace
+    pub unmatched_close: Vec<(usize, usize)>,
+}
+
+impl BraceReport {
+    pub fn is_balanced(&self) -> bool {
+        self.unclosed.is_empty() && self.unmatched_close.is_empty()
+    }
+}
+
+impl fmt::Display for BraceReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unbalanced braces: {} unclosed '{{', {} unmatched '}}'", self.unclosed.len(), self.unmatched_close.len())?;
+        let skipped = self.unclosed.len().saturating_sub(REPORTED_OPENS);
+        for open in &self.unclosed[skipped..] {
+            write!(f, "\n  '{{' at line {}:{} is never closed", open.line, open.column)?;
+            match open.suggestion {
+                Some(line) => write!(f, ", a '}}' is probably missing before line {}", line + 1)?,
+                None => write!(f, ", a '}}' is probably missing at the end of the file")?,
+            }
+        }
+        for (line, column) in &self.unmatched_close {
+            write!(f, "\n  '}}' at line {}:{} has no matching '{{'", line, column)?;
+        }
+        std::result::Result::Ok(())
+    }
+}
+
+fn indentation(line: &str) -> usize {
+    line.chars()
+        .take_while(|c| c.is_whitespace())
+        .map(|c| if c == '\t' { 4 } else { 1 })
+        .sum()
+}
+
+/// Blanks out Lua comments and the text of long-bracket strings with
+/// spaces, keeping every other character, and so every line and column,
+/// where it was.
+fn mask_comments(input: &str) -> String {
+    let mut masked = String::with_capacity(input.len());
+    let mut quote: Option<char> = None;
+    let mut rest = input;
+    while let Some(ch) = rest.chars().next() {
+        let comment_len = if quote.is_some() || !rest.starts_with("--") {
+            0
+        } else {
+            match crate::long_bracket_level(&rest[2..]) {
+                Some(level) => {
+                    let close = format!("]{}]", "=".repeat(level));
+                    rest.find(&close).map_or(rest.len(), |end| end + close.len())
+                }
+                None => rest.find('\n').unwrap_or(rest.len()),
+            }
+        };
+        if comment_len > 0 {
+            masked.extend(rest[..comment_len].chars().map(|c| if c == '\n' { c } else { ' ' }));
+            rest = &rest[comment_len..];
+            continue;
+        }
+        // `[==[text]==]`: the brackets stay, the text may hold braces and quotes
+        if let Some(level) = crate::long_bracket_level(rest).filter(|_| quote.is_none()) {
+            let open = level + 2;
+            let close = format!("]{}]", "=".repeat(level));
+            let end = rest[open..].find(&close).map_or(rest.len(), |end| open + end);
+            masked.push_str(&rest[..open]);
+            masked.extend(rest[open..end].chars().map(|c| if c == '\n' { c } else { ' ' }));
+            rest = &rest[end..];
+            continue;
+        }
+        match ch {
+            '\\' if quote.is_some() => {
+                masked.push(ch);
+                rest = &rest[1..];
+                if let Some(escaped) = rest.chars().next() {
+                    masked.push(escaped);
+                    rest = &rest[escaped.len_utf8()..];
+                }
+                continue;
+            }
+            '"' | '\'' if quote.is_none() => quote = Some(ch),
+            _ if quote == Some(ch) => quote = None,
+            _ => {}
+        }
+        masked.push(ch);
+        rest = &rest[ch.len_utf8()..];
+    }
+    masked
+}
+
+/// Finds unbalanced braces outside string literals and comments. A `}` starting a line
+/// closes the brace opened at the same indentation, so deeper braces still
+/// open at that point are the unclosed ones. Their closing brace is guessed
+/// to sit before the first following line indented no deeper than the line
+/// that opened them.
+pub fn check(input: &str) -> BraceReport {
+    let masked = mask_comments(input);
+    let lines: Vec<&str> = masked.lines().collect();
+    let mut report = BraceReport::default();
+    // (line index, column, depth) of every open brace
+    let mut stack: Vec<(usize, usize, usize)> = Vec::new();
+    let mut unclosed = Vec::new();
+    let mut quote: Option<char> = None;
+
+    for (line_index, line) in lines.iter().enumerate() {
+        let indent = indentation(line);
+        let first = line.chars().position(|c| !c.is_whitespace());
+        let mut chars = line.chars().enumerate();
+        while let Some((column, ch)) = chars.next() {
+            match ch {
+                '\\' if quote.is_some() => {
+                    chars.next();
+                }
+                '"' | '\'' if quote.is_none() => quote = Some(ch),
+                _ if quote == Some(ch) => quote = None,
+                '{' if quote.is_none() => stack.push((line_index, column, stack.len())),
+                '}' if quote.is_none() => {
+                    if first == Some(column) {
+                        while let Some(&open) = stack.last() {
+                            if open.0 == line_index || indentation(lines[open.0]) <= indent {
+                                break;
+                            }
+                            unclosed.push(open);
+                            stack.pop();
+                        }
+                    }
+                    if stack.pop().is_none() {
+                        report.unmatched_close.push((line_index + 1, column + 1));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    unclosed.extend(stack);
+    unclosed.sort();
+
+    for (line_index, column, depth) in unclosed {
+        let indent = indentation(lines[line_index]);
+        let suggestion = (line_index + 1..lines.len())
+            .find(|&i| !lines[i].trim().is_empty() && indentation(lines[i]) <= indent);
+        report.unclosed.push(UnclosedBrace { line: line_index + 1, column: column + 1, depth, suggestion });
+    }
+    report
+}
+
+/// Best-effort repair: drops unmatched `}` and inserts the missing ones at
+/// the suggested lines.
+pub fn repair(input: &str, report: &BraceReport) -> String {
+    let mut lines: Vec<String> = input.lines().map(str::to_string).collect();
+
+    // remove from the right so earlier columns stay valid
+    for (line, column) in report.unmatched_close.iter().rev() {
+        let text = &mut lines[line - 1];
+        if let Some((offset, _)) = text.char_indices().nth(column - 1) {
+            text.remove(offset);
+        }
+    }
+
+    let mut insertions: Vec<(usize, usize, String)> = Vec::new();
+    for open in report.unclosed.iter() {
+        let indent: String = lines[open.line - 1].chars().take_while(|c| c.is_whitespace()).collect();
+        let at = open.suggestion.unwrap_or(lines.len());
+        // top level tables are statements and must not be followed by a comma
+        let closer = if open.depth == 0 { "}" } else { "}," };
+        insertions.push((at, open.depth, format!("{}{}", indent, closer)));
+    }
+    // close the innermost brace first when several land on the same line
+    insertions.sort_by_key(|(at, depth, _)| (*at, std::cmp::Reverse(*depth)));
+    for (shift, (at, _, closer)) in insertions.into_iter().enumerate() {
+        lines.insert(at + shift, closer);
+    }
+
+    let mut output = lines.join("\n");
+    output.push('\n');
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missing_close_brace() {
+        let input = "ast = {\n\tblock_00000 = {\n\t\t{\"bg\", file=\"{bg}\"},\n\t\tline = 18,\n\tblock_00001 = {\n\t\tline = 20,\n\t},\n}\n";
+        let report = check(input);
+        assert_eq!(report.unclosed.len(), 1);
+        assert_eq!(report.unclosed[0].line, 2);
+        assert_eq!(report.unclosed[0].suggestion, Some(4));
+
+        let repaired = repair(input, &report);
+        assert!(check(&repaired).is_balanced());
+        let tokens = crate::tokenize(&repaired).unwrap();
+        crate::parse_tokens(&tokens).unwrap();
+    }
+
+    #[test]
+    fn test_braces_in_comments() {
+        let input = "-- }\nast = { --[[ {\n\t{ ]]\n\tblock_00000 = {\n\t\tline = 18, -- {\n\t},\n}\n";
+        assert!(check(input).is_balanced());
+    }
+
+    #[test]
+    fn test_unmatched_close_brace() {
+        let input = "astver = 2.0\nast = {\n\tblock_00000 = {\n\t\tline = 18,\n\t}},\n}\n";
+        let report = check(input);
+        assert_eq!(report.unmatched_close, vec![(6, 1)]);
+        assert!(check(&repair(input, &report)).is_balanced());
+    }
+}
@@ -0,0 +1,75 @@
+use serde_json::{Map, Number};
+use crate::{LuaTable, Value};
+
+/// A number as Lua compares it: `2.0` is the integer `2`. NaN and the
+/// infinities have no JSON number, so they become strings.
+fn number(f: f64) -> serde_json::Value {
+    if f.fract() == 0.0 && f >= i64::MIN as f64 && f < i64::MAX as f64 {
+        return serde_json::Value::from(f as i64);
+    }
+    match Number::from_f64(f) {
+        Some(number) => serde_json::Value::Number(number),
+        None => serde_json::Value::String(f.to_string()),
+    }
+}
+
+/// Named fields sorted by key.
+fn fields<'a>(fields: impl Iterator<Item = (&'a String, &'a Value)>) -> Map<String, serde_json::Value> {
+    let mut sorted: Vec<(&String, &Value)> = fields.collect();
+    sorted.sort_by_key(|(key, _)| *key);
+    sorted.into_iter().map(|(key, value)| (key.clone(), canonical(value))).collect()
+}
+
+/// A table without named fields is an array; any other is
+/// `{"array": [...], "fields": {...}}`, `array` left out when empty, so the
+/// two shapes can never be mistaken for each other.
+fn table(table: &LuaTable) -> serde_json::Value {
+    let array: Vec<serde_json::Value> = table.array.iter().map(canonical).collect();
+    if table.fields().next().is_none() {
+        return serde_json::Value::Array(array);
+    }
+    let mut object = Map::new();
+    if !array.is_empty() {
+        object.insert("array".to_string(), serde_json::Value::Array(array));
+    }
+    object.insert("fields".to_string(), serde_json::Value::Object(fields(table.fields())));
+    serde_json::Value::Object(object)
+}
+
+fn canonical(value: &Value) -> serde_json::Value {
+    match value {
+        Value::Integer(i, _) => serde_json::Value::from(*i),
+        Value::Float(f, _) => number(*f),
+        Value::String(s) => serde_json::Value::String(s.clone()),
+        Value::BareWord(word) => serde_json::json!({ "bare": word }),
+        Value::Table(t) => table(t),
+        Value::SpContent(sp) => serde_json::json!({ "sp": sp }),
+    }
+}
+
+/// The script as compact JSON that is the same for any two scripts
+/// [`crate::equivalent`] finds equivalent: formatting, comments, key order and
+/// the spelling of numbers are all gone.
+pub fn to_json(ast: &LuaTable) -> String {
+    serde_json::Value::Object(fields(ast.fields())).to_string()
+}
+
+/// sha256 of [`to_json`], for caching and finding duplicate scripts.
+pub fn hash(ast: &LuaTable) -> String {
+    crate::sha256_hex(to_json(ast).as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_canonical_json() {
+        let parse = |input: &str| crate::parse_tokens(&crate::tokenize(input).unwrap()).unwrap();
+        let a = parse("astver = 2.0\nast = { block_00000 = { {\"bg\", time=2000, lv=0x10}, linknext = \"b\" } }");
+        let b = parse("-- same\nast={block_00000={linknext='b';{'bg';lv=16,time=2e3}}}\nastver=2");
+        assert_eq!(to_json(&a), r#"{"ast":{"fields":{"block_00000":{"array":[{"array":["bg"],"fields":{"lv":16,"time":2000}}],"fields":{"linknext":"b"}}}},"astver":2}"#);
+        assert_eq!(hash(&a), hash(&b));
+        assert_ne!(hash(&a), hash(&parse("astver = 2.5\nast = {}")));
+    }
+}
@@ -0,0 +1,63 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    path::{Path, PathBuf},
+};
+use anyhow::{Result, anyhow};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Name of the chapter manifest, at the root of the translation directory.
+pub const MANIFEST: &str = "chapters.yaml";
+
+/// Lists the scripts each chapter covers. The translation of `common/01.ast`
+/// in chapter `common` is `chapters/common/01.yaml`.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, PartialEq)]
+pub struct ChapterManifest {
+    /// Chapter name -> scripts, relative to the script directory
+    pub chapters: BTreeMap<String, Vec<PathBuf>>,
+}
+
+impl ChapterManifest {
+    pub fn load(yaml_dir: &Path) -> Result<Self> {
+        let path = yaml_dir.join(MANIFEST);
+        let content = std::fs::read_to_string(&path)
+            .map_err(|e| anyhow!("Failed to read chapter manifest {}: {}", path.display(), e))?;
+        serde_yaml::from_str(&content).map_err(|e| anyhow!("{}: {}", path.display(), e))
+    }
+
+    /// The scripts of chapter `name`. Two scripts with the same file name
+    /// would share a translation file, so they are rejected.
+    pub fn scripts(&self, name: &str) -> Result<&[PathBuf]> {
+        let scripts = self.chapters.get(name).ok_or_else(|| {
+            let known: Vec<&str> = self.chapters.keys().map(String::as_str).collect();
+            anyhow!("{} has no chapter {} (chapters: {})", MANIFEST, name, known.join(", "))
+        })?;
+        let mut names = HashSet::new();
+        for script in scripts {
+            if !names.insert(script.file_stem()) {
+                return Err(anyhow!("Chapter {} lists more than one script named {}", name, script.display()));
+            }
+        }
+        Ok(scripts)
+    }
+}
+
+/// `chapters/<chapter>/<script name>.<ext>` under the translation directory.
+pub fn translation_path(yaml_dir: &Path, chapter: &str, script: &Path, ext: &str) -> PathBuf {
+    yaml_dir.join("chapters").join(chapter).join(script.file_name().unwrap_or_default()).with_extension(ext)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chapter_scripts() {
+        let manifest: ChapterManifest = serde_yaml::from_str("chapters:\n  common: [common/01.ast, common/02.ast]\n  hiyori: [hiyori/01.ast, extra/01.ast]\n").unwrap();
+        assert_eq!(manifest.scripts("common").unwrap().len(), 2);
+        assert!(manifest.scripts("hiyori").is_err());
+        assert!(manifest.scripts("yuzu").unwrap_err().to_string().contains("common, hiyori"));
+        let path = translation_path(Path::new("tl"), "common", Path::new("common/02.ast"), "yaml");
+        assert_eq!(path, Path::new("tl/chapters/common/02.yaml"));
+    }
+}
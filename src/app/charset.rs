@@ -0,0 +1,110 @@
+use std::collections::BTreeMap;
+use crate::{LuaTable, Value};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum WritingSystem {
+    Latin,
+    Kana,
+    Han,
+    Hangul,
+    Cyrillic,
+}
+
+impl WritingSystem {
+    const ALL: [WritingSystem; 5] = [
+        WritingSystem::Latin,
+        WritingSystem::Kana,
+        WritingSystem::Han,
+        WritingSystem::Hangul,
+        WritingSystem::Cyrillic,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            WritingSystem::Latin => "latin",
+            WritingSystem::Kana => "kana",
+            WritingSystem::Han => "han",
+            WritingSystem::Hangul => "hangul",
+            WritingSystem::Cyrillic => "cyrillic",
+        }
+    }
+
+    pub fn of(ch: char) -> Option<Self> {
+        match ch as u32 {
+            0x41..=0x5A | 0x61..=0x7A | 0xC0..=0x24F => Some(WritingSystem::Latin),
+            0x3040..=0x30FF | 0x31F0..=0x31FF | 0xFF66..=0xFF9F => Some(WritingSystem::Kana),
+            0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0xF900..=0xFAFF | 0x20000..=0x2FFFF => Some(WritingSystem::Han),
+            0x1100..=0x11FF | 0x3130..=0x318F | 0xAC00..=0xD7AF => Some(WritingSystem::Hangul),
+            0x400..=0x4FF => Some(WritingSystem::Cyrillic),
+            _ => None,
+        }
+    }
+}
+
+/// Number of lines of each language channel containing each writing system.
+pub type ChannelReport = BTreeMap<String, BTreeMap<WritingSystem, usize>>;
+
+fn channel_lines<'a>(value: &'a Value, lines: &mut Vec<&'a String>) {
+    // `ja = { { name = {...}, "line", {"rt2"} } }`: only the bare strings are text
+    for group in value.as_table().into_iter().flat_map(|channel| channel.array.iter()).filter_map(Value::as_table) {
+        lines.extend(group.array.iter().filter_map(Value::as_string));
+    }
+}
+
+/// Counts, for every language channel under `text` (ja, en, cn, ...), the
+/// lines using each writing system.
+pub fn report(ast: &LuaTable, astver: Option<crate::compat::AstVersion>) -> ChannelReport {
+    let mut report = ChannelReport::new();
+    for (_, block) in crate::iter_blocks(ast, astver) {
+        let channels = block.get("text").and_then(Value::as_table).into_iter().flat_map(LuaTable::fields);
+        for (channel, value) in channels.filter(|(channel, _)| *channel != "vo") {
+            let counts = report.entry(channel.clone()).or_default();
+            let mut lines = Vec::new();
+            channel_lines(value, &mut lines);
+            for line in lines {
+                for system in WritingSystem::ALL {
+                    if line.chars().any(|ch| WritingSystem::of(ch) == Some(system)) {
+                        *counts.entry(system).or_default() += 1;
+                    }
+                }
+            }
+        }
+    }
+    report
+}
+
+pub fn print_report(report: &ChannelReport) {
+    for (channel, counts) in report {
+        let found: Vec<String> = counts.iter()
+            .map(|(system, lines)| format!("{} {}", system.name(), lines))
+            .collect();
+        if found.is_empty() {
+            println!("  {}: no letters", channel);
+        } else {
+            println!("  {} (lines using each): {}", channel, found.join(", "));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_channel_report() {
+        let input = r#"ast = {
+            block_00000 = { text = {
+                vo = { {"vo", file="fem_hiy_00052"} },
+                ja = { { name = {"妃愛"}, "「お兄、あさー」", {"rt2"} } },
+                en = { { name = {"Hiyori"}, "\"Big bro, morning\"" } },
+            } },
+            block_00001 = { text = { ja = { { "……" } }, en = { { "むふー" } } } },
+        }
+        "#;
+        let ast = crate::parse_tokens(&crate::tokenize(input).unwrap()).unwrap();
+        let report = report(&ast, None);
+        assert_eq!(report.keys().collect::<Vec<_>>(), vec!["en", "ja"]);
+        assert_eq!(report["ja"], BTreeMap::from([(WritingSystem::Kana, 1), (WritingSystem::Han, 1)]));
+        assert_eq!(report["en"], BTreeMap::from([(WritingSystem::Latin, 1), (WritingSystem::Kana, 1)]));
+    }
+}
@@ -0,0 +1,872 @@
+use std::{
+    collections::BTreeSet,
+    ffi::OsString,
+    path::PathBuf,
+};
+use anyhow::{Result, anyhow};
+use clap::{Args, Subcommand};
+use crate::{
+    ExtractOptions, MergeOptions, ParseOptions, PruneOptions, ScenarioOptions, WriteOptions,
+    assets, canonical, charset, completeness, credits, equivalent, indent, links, preview, quotes, reorder, roundtrip, routes, schema, sections, stats, timing, voice,
+};
+#[cfg(feature = "html-export")]
+use crate::html_export;
+#[cfg(feature = "update")]
+use crate::update;
+
+/// Prefix of external executables that act as extra subcommands, git style:
+/// `artemis_ast foo` runs `artemis_ast-foo`.
+const PLUGIN_PREFIX: &str = "artemis_ast-";
+
+/// Options shared by every subcommand.
+pub struct Context<'a> {
+    pub parse: &'a ParseOptions,
+    pub write: &'a WriteOptions,
+}
+
+pub trait Command {
+    fn run(&self, ctx: &Context) -> Result<()>;
+}
+
+/// A command that ran to completion but failed, such as a check that found
+/// problems, which it has printed already. `main` reports `reason` and
+/// exits with `code`.
+#[derive(Debug)]
+pub struct CheckFailed {
+    pub code: i32,
+    pub reason: String,
+}
+
+impl std::fmt::Display for CheckFailed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.reason)
+    }
+}
+
+impl std::error::Error for CheckFailed {}
+
+fn failed(reason: impl Into<String>) -> anyhow::Error {
+    CheckFailed { code: 1, reason: reason.into() }.into()
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Commands {
+    /// Extract all secnario text to yaml
+    Extract(Extract),
+    /// Prune the ast file, remove all secnario text (for steam release)
+    Prune(Prune),
+    /// Merge corresponding secnario text back to ast file
+    Merge(Merge),
+    /// Remove all voice (vo) tables, keeping everything else (for unvoiced builds)
+    StripVo(StripVo),
+    /// List spoken lines that have no voice (vo) table
+    RequireVo(RequireVo),
+    /// Rewrite scripts in place in one consistent style (indentation, separators, spacing), keeping their order and comments
+    Fmt(Fmt),
+    /// Sum the declared durations (time=, waits) of every block
+    Timing(Timing),
+    /// Render the lines of a script into a mock textbox, flagging overflow
+    LayoutPreview(LayoutPreview),
+    /// Extract, merge the same text back and re-extract, reporting any difference
+    RoundtripCheck(RoundtripCheck),
+    /// Add a staff roll or translation notice as a new block spliced into the linknext chain
+    InjectCredits(InjectCredits),
+    /// Check that 「」『』“” are balanced in every line, and quoted consistently in a translation
+    LintQuotes(LintQuotes),
+    /// Check that linknext chains run forward: no missing targets, self-links or loops
+    LintLinks(LintLinks),
+    /// Check for indentation mixing tabs and spaces, optionally writing a copy indented with tabs only
+    LintIndent(LintIndent),
+    /// Check whether two scripts are the same apart from formatting and key order
+    Equivalent(Equivalent),
+    /// Write a script as canonical JSON (sorted keys, 2.0 written as 2), or its sha256, so equivalent scripts compare equal
+    Canonical(Canonical),
+    /// Count the images and sounds a script uses, including fg sprite parts (ex, face, head)
+    Assets(Assets),
+    /// Report which writing systems (latin, kana, han, hangul, cyrillic) each language channel uses
+    Charsets(Charsets),
+    /// Report how much of each translation is filled in, failing below a threshold
+    Completeness(Completeness),
+    /// Write an HTML page for reviewing translations side by side, saved back as JSON for merge --from-html-export
+    #[cfg(feature = "html-export")]
+    HtmlExport(HtmlExport),
+    /// List the in-game chapters set by savetitle, with the amount of text in each
+    Sections(Sections),
+    /// Write the line, translation and length-check counts of a set of scripts as JSON for a dashboard, without any text
+    Stats(Stats),
+    /// Re-link scenes following an edit list of moves, inserts and skips, rewriting linknext chains
+    Reorder(Reorder),
+    /// Estimate the amount of text on every route from the first block to an ending
+    Routes(Routes),
+    /// Print the JSON Schema of one of the files this tool reads or writes
+    Schema(Schema),
+    /// Run extract/prune/merge over every ast file under a directory
+    Batch(crate::batch::BatchArgs),
+    /// List the artemis_ast-<name> plugins found next to this executable and on PATH
+    Plugins,
+    /// Check GitHub for a newer release
+    #[cfg(feature = "update")]
+    CheckUpdate,
+    #[command(external_subcommand)]
+    External(Vec<OsString>),
+}
+
+impl Command for Commands {
+    fn run(&self, ctx: &Context) -> Result<()> {
+        match self {
+            Commands::Extract(command) => command.run(ctx),
+            Commands::Prune(command) => command.run(ctx),
+            Commands::Merge(command) => command.run(ctx),
+            Commands::StripVo(command) => command.run(ctx),
+            Commands::RequireVo(command) => command.run(ctx),
+            Commands::Fmt(command) => command.run(ctx),
+            Commands::Timing(command) => command.run(ctx),
+            Commands::LayoutPreview(command) => command.run(ctx),
+            Commands::RoundtripCheck(command) => command.run(ctx),
+            Commands::InjectCredits(command) => command.run(ctx),
+            Commands::LintQuotes(command) => command.run(ctx),
+            Commands::LintLinks(command) => command.run(ctx),
+            Commands::LintIndent(command) => command.run(ctx),
+            Commands::Equivalent(command) => command.run(ctx),
+            Commands::Canonical(command) => command.run(ctx),
+            Commands::Assets(command) => command.run(ctx),
+            Commands::Charsets(command) => command.run(ctx),
+            Commands::Completeness(command) => command.run(ctx),
+            #[cfg(feature = "html-export")]
+            Commands::HtmlExport(command) => command.run(ctx),
+            Commands::Sections(command) => command.run(ctx),
+            Commands::Stats(command) => command.run(ctx),
+            Commands::Reorder(command) => command.run(ctx),
+            Commands::Routes(command) => command.run(ctx),
+            Commands::Schema(command) => command.run(ctx),
+            Commands::Batch(args) => run_batch(args, ctx),
+            Commands::Plugins => {
+                for name in find_plugins(&plugin_dirs(std::env::var_os("PATH"))) {
+                    println!("{}", name);
+                }
+                Ok(())
+            }
+            #[cfg(feature = "update")]
+            Commands::CheckUpdate => update::check(),
+            Commands::External(args) => run_plugin(args, &plugin_dirs(std::env::var_os("PATH"))),
+        }
+    }
+}
+
+#[derive(Args, Debug)]
+pub struct Extract {
+    input: PathBuf,
+    output: PathBuf,
+    #[command(flatten)]
+    scenario: ScenarioOptions,
+    #[command(flatten)]
+    options: ExtractOptions,
+}
+
+impl Command for Extract {
+    fn run(&self, ctx: &Context) -> Result<()> {
+        println!("Extracting secnario text from {} to {}", self.input.display(), self.output.display());
+        crate::extract_file(&self.input, &self.output, ctx.parse, &self.scenario, &self.options)
+    }
+}
+
+#[derive(Args, Debug)]
+pub struct Prune {
+    input: PathBuf,
+    output: PathBuf,
+    #[command(flatten)]
+    options: PruneOptions,
+}
+
+impl Command for Prune {
+    fn run(&self, ctx: &Context) -> Result<()> {
+        crate::prune_file(&self.input, &self.output, ctx.parse, ctx.write, &self.options)
+    }
+}
+
+#[derive(Args, Debug)]
+pub struct Merge {
+    ast_input: PathBuf,
+    yaml_input: PathBuf,
+    output: PathBuf,
+    #[command(flatten)]
+    scenario: ScenarioOptions,
+    #[command(flatten)]
+    options: MergeOptions,
+}
+
+impl Command for Merge {
+    fn run(&self, ctx: &Context) -> Result<()> {
+        crate::merge_file(&self.ast_input, &self.yaml_input, &self.output, ctx.parse, ctx.write, &self.scenario, &self.options)
+    }
+}
+
+#[derive(Args, Debug)]
+pub struct StripVo {
+    input: PathBuf,
+    output: PathBuf,
+}
+
+impl Command for StripVo {
+    fn run(&self, ctx: &Context) -> Result<()> {
+        let mut ast = crate::parse_ast(&self.input, ctx.parse)?;
+        if ast.is_empty() {
+            return Ok(());
+        }
+        voice::strip_vo(&mut ast);
+        crate::write_script_file(&ast, &self.output, ctx.write)
+    }
+}
+
+#[derive(Args, Debug)]
+pub struct InjectCredits {
+    input: PathBuf,
+    output: PathBuf,
+    /// A page of text; repeat for more pages
+    #[arg(long, required_unless_present = "text_file")]
+    text: Vec<String>,
+    /// Read the pages from this file, one per line, blank lines skipped
+    #[arg(long, conflicts_with = "text")]
+    text_file: Option<PathBuf>,
+    /// Language to write the text under; repeat for several [default: every language the script has]
+    #[arg(long)]
+    lang: Vec<String>,
+    /// Block the credits follow, taking over its linknext [default: the last block]
+    #[arg(long)]
+    after: Option<String>,
+    /// Name of the new block [default: the next free block_NNNNN]
+    #[arg(long)]
+    name: Option<String>,
+}
+
+impl Command for InjectCredits {
+    fn run(&self, ctx: &Context) -> Result<()> {
+        let mut ast = crate::parse_ast(&self.input, ctx.parse)?;
+        let pages = match &self.text_file {
+            Some(path) => std::fs::read_to_string(path)?.lines().map(str::trim_end).filter(|line| !line.is_empty()).map(String::from).collect(),
+            None => self.text.clone(),
+        };
+        let credits = credits::Credits { pages, languages: self.lang.clone(), after: self.after.clone(), name: self.name.clone() };
+        let name = credits::inject(&mut ast, ctx.parse.astver, &credits)?;
+        crate::write_script_file(&ast, &self.output, ctx.write)?;
+        println!("Added {} to {}", name, self.output.display());
+        Ok(())
+    }
+}
+
+#[derive(Args, Debug)]
+pub struct RequireVo {
+    input: PathBuf,
+}
+
+impl Command for RequireVo {
+    fn run(&self, ctx: &Context) -> Result<()> {
+        let ast = crate::parse_ast(&self.input, ctx.parse)?;
+        let missing = voice::missing_vo(&ast, ctx.parse.astver);
+        for (block, speaker) in missing.iter() {
+            println!("{}: {} speaks without a vo entry", block, speaker);
+        }
+        if !missing.is_empty() {
+            return Err(failed(format!("{} blocks have a speaker without a vo entry", missing.len())));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Args, Debug)]
+pub struct Timing {
+    input: PathBuf,
+}
+
+impl Command for Timing {
+    fn run(&self, ctx: &Context) -> Result<()> {
+        let ast = crate::parse_ast(&self.input, ctx.parse)?;
+        timing::print_report(&timing::block_timings(&ast, ctx.parse.astver));
+        Ok(())
+    }
+}
+
+#[derive(Args, Debug)]
+pub struct LayoutPreview {
+    input: PathBuf,
+    #[command(flatten)]
+    scenario: ScenarioOptions,
+    #[command(flatten)]
+    options: preview::PreviewOptions,
+}
+
+impl Command for LayoutPreview {
+    fn run(&self, ctx: &Context) -> Result<()> {
+        let ast = crate::parse_ast(&self.input, ctx.parse)?;
+        if ast.is_empty() {
+            return Ok(());
+        }
+        preview::preview(&crate::extract_secnario(&ast, ctx.parse.astver, &self.scenario)?, &self.options)
+    }
+}
+
+#[derive(Args, Debug)]
+pub struct RoundtripCheck {
+    input: PathBuf,
+    #[command(flatten)]
+    scenario: ScenarioOptions,
+}
+
+impl Command for RoundtripCheck {
+    fn run(&self, ctx: &Context) -> Result<()> {
+        let report = roundtrip::check(&self.input, ctx.parse, ctx.write, &self.scenario)?;
+        println!("{}", report);
+        if !report.is_clean() {
+            return Err(failed(format!("{}: the round trip is not clean", self.input.display())));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Args, Debug)]
+pub struct LintQuotes {
+    input: PathBuf,
+    translation: Option<PathBuf>,
+    #[command(flatten)]
+    scenario: ScenarioOptions,
+}
+
+impl Command for LintQuotes {
+    fn run(&self, ctx: &Context) -> Result<()> {
+        let ast = crate::parse_ast(&self.input, ctx.parse)?;
+        let source = crate::extract_secnario(&ast, ctx.parse.astver, &self.scenario)?;
+        let mut findings = Vec::new();
+        for (index, text) in source.iter().enumerate() {
+            if let Some(problem) = quotes::unbalanced(text) {
+                findings.push(format!("{}: entry {}: {}: {}", self.input.display(), index, problem, text));
+            }
+        }
+        if let Some(translation) = &self.translation {
+            let mut translated = crate::read_yaml_as_strings(translation)?;
+            if let Some(gaiji) = crate::load_gaiji(&self.scenario)? {
+                translated = translated.iter().map(|text| gaiji.decode(text)).collect();
+            }
+            for (index, text) in translated.iter().enumerate() {
+                if let Some(problem) = quotes::unbalanced(text) {
+                    findings.push(format!("{}: entry {}: {}: {}", translation.display(), index, problem, text));
+                }
+            }
+            for (index, problem) in quotes::inconsistent_styles(&source, &translated) {
+                findings.push(format!("{}: entry {}: {}: {}", translation.display(), index, problem, translated[index]));
+            }
+        }
+        for finding in findings.iter() {
+            println!("{}", finding);
+        }
+        if !findings.is_empty() {
+            return Err(failed(format!("{} quoting problems", findings.len())));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Args, Debug)]
+pub struct LintLinks {
+    input: PathBuf,
+    /// Allow links back to earlier blocks, for scripts that loop on purpose
+    #[arg(long)]
+    allow_cycles: bool,
+}
+
+impl Command for LintLinks {
+    fn run(&self, ctx: &Context) -> Result<()> {
+        let ast = crate::parse_ast(&self.input, ctx.parse)?;
+        let problems = links::check_links(&ast, ctx.parse.astver, self.allow_cycles);
+        for problem in problems.iter() {
+            println!("{}: {}", self.input.display(), problem);
+        }
+        if !problems.is_empty() {
+            return Err(failed(format!("{}: {} problems", self.input.display(), problems.len())));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Args, Debug)]
+pub struct LintIndent {
+    input: PathBuf,
+    /// Write the script re-indented with tabs to this file
+    #[arg(long)]
+    fix: Option<PathBuf>,
+    /// Columns a tab stands for when converting spaces
+    #[arg(long, default_value_t = 4)]
+    tab_width: usize,
+}
+
+impl Command for LintIndent {
+    fn run(&self, ctx: &Context) -> Result<()> {
+        let script = crate::read_script(&self.input, ctx.parse)?;
+        if let Some(output) = &self.fix {
+            std::fs::write(output, indent::normalize(&script, self.tab_width))?;
+            return Ok(());
+        }
+        let problems = indent::check(&script);
+        for (line, problem) in problems.iter() {
+            println!("{}:{}: {}", self.input.display(), line, problem);
+        }
+        if !problems.is_empty() {
+            return Err(failed(format!("{}: {} problems", self.input.display(), problems.len())));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Args, Debug)]
+pub struct Fmt {
+    /// Scripts to format
+    #[arg(required = true)]
+    inputs: Vec<PathBuf>,
+    /// Only list the scripts that are not formatted, failing if there are any
+    #[arg(long)]
+    check: bool,
+}
+
+impl Command for Fmt {
+    fn run(&self, ctx: &Context) -> Result<()> {
+        let inputs = crate::filelist::expand(&self.inputs)?;
+        let mut unformatted = 0;
+        for input in &inputs {
+            let script = crate::read_script(input, ctx.parse)?;
+            let formatted = crate::format_script(&script, input, ctx.parse, ctx.write)?;
+            if formatted == script {
+                continue;
+            }
+            if self.check {
+                println!("{}", input.display());
+                unformatted += 1;
+            } else {
+                std::fs::write(input, formatted)?;
+            }
+        }
+        if unformatted > 0 {
+            return Err(failed(format!("{} of {} scripts are not formatted", unformatted, inputs.len())));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Args, Debug)]
+pub struct Equivalent {
+    a: PathBuf,
+    b: PathBuf,
+}
+
+impl Command for Equivalent {
+    fn run(&self, ctx: &Context) -> Result<()> {
+        let first = crate::parse_ast(&self.a, ctx.parse)?;
+        let second = crate::parse_ast(&self.b, ctx.parse)?;
+        match equivalent::first_difference(&first, &second) {
+            None => println!("{} and {} are equivalent", self.a.display(), self.b.display()),
+            Some(difference) => {
+                println!("{} and {} differ at {}", self.a.display(), self.b.display(), difference);
+                for path in [&self.a, &self.b] {
+                    let input = crate::read_script(path, ctx.parse)?;
+                    // a value missing from one side has no line there
+                    if let Some(span) = crate::parse_spans(&input)?.get(&difference.path) {
+                        let (line, column) = crate::line_column(&input, span.start);
+                        println!("  {}:{}:{}", path.display(), line, column);
+                    }
+                }
+                return Err(failed(format!("{} and {} are not equivalent", self.a.display(), self.b.display())));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Args, Debug)]
+pub struct Canonical {
+    input: PathBuf,
+    /// File to write, standard output when left out
+    output: Option<PathBuf>,
+    /// Write the sha256 of the canonical JSON instead of the JSON
+    #[arg(long)]
+    hash: bool,
+}
+
+impl Command for Canonical {
+    fn run(&self, ctx: &Context) -> Result<()> {
+        let ast = crate::parse_ast(&self.input, ctx.parse)?;
+        let text = if self.hash { canonical::hash(&ast) } else { canonical::to_json(&ast) };
+        match &self.output {
+            Some(output) => std::fs::write(output, text + "\n")?,
+            None => println!("{}", text),
+        }
+        Ok(())
+    }
+}
+
+#[derive(Args, Debug)]
+pub struct Assets {
+    input: PathBuf,
+    /// Also check that every referenced asset exists under this directory
+    #[arg(long)]
+    asset_dir: Option<PathBuf>,
+}
+
+impl Command for Assets {
+    fn run(&self, ctx: &Context) -> Result<()> {
+        let ast = crate::parse_ast(&self.input, ctx.parse)?;
+        let uses = assets::asset_uses(&ast, ctx.parse.astver);
+        assets::print_report(&uses);
+        if let Some(asset_dir) = &self.asset_dir {
+            let missing = assets::missing_assets(&uses, asset_dir)?;
+            assets::print_missing(&missing);
+            if !missing.is_empty() {
+                return Err(failed(format!("{} assets are missing", missing.len())));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Args, Debug)]
+pub struct Charsets {
+    #[arg(required_unless_present = "files_from")]
+    inputs: Vec<PathBuf>,
+    /// Also read the scripts listed in this file, one per line
+    #[arg(long)]
+    files_from: Option<PathBuf>,
+}
+
+impl Command for Charsets {
+    fn run(&self, ctx: &Context) -> Result<()> {
+        let mut inputs = crate::filelist::expand(&self.inputs)?;
+        if let Some(list) = &self.files_from {
+            inputs.extend(crate::filelist::read(list)?);
+        }
+        for input in inputs.iter() {
+            let ast = crate::parse_ast(input, ctx.parse)?;
+            println!("{}", input.display());
+            charset::print_report(&charset::report(&ast, ctx.parse.astver));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Args, Debug)]
+pub struct Completeness {
+    /// Translated yaml files, in any layout merge accepts
+    #[arg(required_unless_present = "files_from")]
+    inputs: Vec<PathBuf>,
+    /// Also read the translations listed in this file, one per line
+    #[arg(long)]
+    files_from: Option<PathBuf>,
+    /// Exit with an error when less than this share of all entries is translated, e.g. 95%
+    #[arg(long, value_name = "N%", value_parser = completeness::parse_percent)]
+    fail_if_untranslated: Option<f64>,
+}
+
+impl Command for Completeness {
+    fn run(&self, _ctx: &Context) -> Result<()> {
+        let mut inputs = crate::filelist::expand(&self.inputs)?;
+        if let Some(list) = &self.files_from {
+            inputs.extend(crate::filelist::read(list)?);
+        }
+        let mut total = completeness::Completeness::default();
+        for input in inputs.iter() {
+            let file = completeness::Completeness::of(&crate::read_yaml_as_strings(input)?);
+            println!("{}: {}", input.display(), file);
+            total.add(file);
+        }
+        println!("total: {}", total);
+        if let Some(threshold) = self.fail_if_untranslated {
+            if total.percent() < threshold {
+                return Err(failed(format!("{:.1}% is below the required {}%", total.percent(), threshold)));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Args, Debug)]
+pub struct Sections {
+    /// Scripts in reading order; a chapter can run over several files
+    #[arg(required_unless_present = "files_from")]
+    inputs: Vec<PathBuf>,
+    /// Also read the scripts listed in this file, one per line
+    #[arg(long)]
+    files_from: Option<PathBuf>,
+    #[command(flatten)]
+    scenario: ScenarioOptions,
+}
+
+impl Command for Sections {
+    fn run(&self, ctx: &Context) -> Result<()> {
+        let mut inputs = crate::filelist::expand(&self.inputs)?;
+        if let Some(list) = &self.files_from {
+            inputs.extend(crate::filelist::read(list)?);
+        }
+        let mut found = Vec::new();
+        for input in inputs.iter() {
+            let ast = match crate::parse_ast(input, ctx.parse) {
+                Err(e) if e.downcast_ref::<crate::EmptyScript>().is_some() => {
+                    crate::logging::warn(format!("{}, skipped", e));
+                    continue;
+                }
+                result => result?,
+            };
+            sections::add_script(&mut found, input, &ast, ctx.parse.astver, &self.scenario)?;
+        }
+        sections::print_report(&found);
+        Ok(())
+    }
+}
+
+#[derive(Args, Debug)]
+pub struct Stats {
+    /// JSON file to write
+    output: PathBuf,
+    /// Scripts to count
+    #[arg(required_unless_present = "files_from")]
+    inputs: Vec<PathBuf>,
+    /// Also read the scripts listed in this file, one per line
+    #[arg(long)]
+    files_from: Option<PathBuf>,
+    /// Directory holding the translations, named after each script with a .yaml extension
+    #[arg(long)]
+    yaml_dir: Option<PathBuf>,
+    #[command(flatten)]
+    scenario: ScenarioOptions,
+    #[command(flatten)]
+    length: crate::length::LengthOptions,
+}
+
+impl Command for Stats {
+    fn run(&self, ctx: &Context) -> Result<()> {
+        let mut inputs = crate::filelist::expand(&self.inputs)?;
+        if let Some(list) = &self.files_from {
+            inputs.extend(crate::filelist::read(list)?);
+        }
+        let mut files = Vec::new();
+        let mut empty = Vec::new();
+        for input in inputs.iter() {
+            let ast = match crate::parse_ast(input, ctx.parse) {
+                Err(e) if e.downcast_ref::<crate::EmptyScript>().is_some() => {
+                    crate::logging::warn(format!("{}, skipped", e));
+                    empty.push(input.display().to_string());
+                    continue;
+                }
+                result => result?,
+            };
+            let translation = match (&self.yaml_dir, input.file_name()) {
+                (Some(dir), Some(name)) if dir.join(name).with_extension("yaml").exists() => {
+                    Some(crate::read_yaml_as_strings(dir.join(name).with_extension("yaml"))?)
+                }
+                _ => None,
+            };
+            files.push(stats::FileStats::of(input, &ast, ctx.parse.astver, translation.as_deref(), &self.scenario, &self.length)?);
+        }
+        let stats = stats::ProjectStats { empty, ..stats::ProjectStats::new(files) };
+        std::fs::write(&self.output, serde_json::to_string_pretty(&stats)?)?;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "html-export")]
+#[derive(Args, Debug)]
+pub struct HtmlExport {
+    input: PathBuf,
+    output: PathBuf,
+    /// Prefill the editable fields from this translation instead of the source text
+    #[arg(long)]
+    translation: Option<PathBuf>,
+    #[command(flatten)]
+    scenario: ScenarioOptions,
+}
+
+#[cfg(feature = "html-export")]
+impl Command for HtmlExport {
+    fn run(&self, ctx: &Context) -> Result<()> {
+        let ast = crate::parse_ast(&self.input, ctx.parse)?;
+        let mut source = crate::extract_secnario(&ast, ctx.parse.astver, &self.scenario)?;
+        if let Some(gaiji) = crate::load_gaiji(&self.scenario)? {
+            source = source.iter().map(|text| gaiji.encode(text)).collect();
+        }
+        let translation = match &self.translation {
+            Some(path) => crate::read_yaml_as_strings(path)?,
+            None => source.clone(),
+        };
+        if translation.len() != source.len() {
+            return Err(anyhow!("The translation has {} lines but the script has {}", translation.len(), source.len()));
+        }
+        let script = self.input.file_name().unwrap_or_default().to_string_lossy();
+        std::fs::write(&self.output, html_export::render(&script, &source, &translation)?)?;
+        Ok(())
+    }
+}
+
+#[derive(Args, Debug)]
+pub struct Reorder {
+    input: PathBuf,
+    output: PathBuf,
+    /// Yaml list of edits, `- { op: move, scene: block_00010, until: block_00012, after: block_00003 }`
+    #[arg(long)]
+    edits: PathBuf,
+}
+
+impl Command for Reorder {
+    fn run(&self, ctx: &Context) -> Result<()> {
+        let mut ast = crate::parse_ast(&self.input, ctx.parse)?;
+        let edits = reorder::load(&self.edits)?;
+        reorder::apply(&mut ast, ctx.parse.astver, &edits).map_err(|e| anyhow!("{}: {}", self.edits.display(), e))?;
+        for problem in links::check_links(&ast, ctx.parse.astver, true) {
+            crate::logging::warn(format!("{}: {}", self.output.display(), problem));
+        }
+        crate::write_script_file(&ast, &self.output, ctx.write)?;
+        Ok(())
+    }
+}
+
+#[derive(Args, Debug)]
+pub struct Routes {
+    input: PathBuf,
+    /// Stop after this many routes, branching scripts can have a great many
+    #[arg(long, default_value_t = 100)]
+    max_routes: usize,
+}
+
+impl Command for Routes {
+    fn run(&self, ctx: &Context) -> Result<()> {
+        let ast = crate::parse_ast(&self.input, ctx.parse)?;
+        let graph = routes::build(&ast, ctx.parse.astver)?;
+        routes::print_report(&graph, &routes::routes(&graph, self.max_routes));
+        Ok(())
+    }
+}
+
+#[derive(Args, Debug)]
+pub struct Schema {
+    #[arg(value_enum)]
+    format: schema::SchemaFormat,
+}
+
+impl Command for Schema {
+    fn run(&self, _ctx: &Context) -> Result<()> {
+        println!("{}", serde_json::to_string_pretty(&schema::schema(self.format))?);
+        Ok(())
+    }
+}
+
+fn exe_dir() -> Option<PathBuf> {
+    Some(std::env::current_exe().ok()?.parent()?.to_path_buf())
+}
+
+/// Directories searched for plugins: the one holding this executable, then
+/// those of `path`, the value of PATH.
+fn plugin_dirs(path: Option<OsString>) -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = exe_dir().into_iter().collect();
+    if let Some(path) = path {
+        dirs.extend(std::env::split_paths(&path));
+    }
+    dirs
+}
+
+fn find_plugins(dirs: &[PathBuf]) -> BTreeSet<String> {
+    let mut names = BTreeSet::new();
+    for dir in dirs {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            continue;
+        };
+        for path in entries.filter_map(|entry| entry.ok()).map(|entry| entry.path()) {
+            let Some(stem) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                continue;
+            };
+            if let Some(name) = stem.strip_prefix(PLUGIN_PREFIX) {
+                if path.is_file() {
+                    names.insert(name.to_string());
+                }
+            }
+        }
+    }
+    names
+}
+
+/// Runs `batch`, printing how the scripts went.
+fn run_batch(args: &crate::batch::BatchArgs, ctx: &Context) -> Result<()> {
+    let outcome = crate::batch::run(args, ctx.parse, ctx.write)?;
+    if outcome.resumed > 0 {
+        println!("Resumed: {} of {} files already done", outcome.resumed, outcome.resumed + outcome.total());
+    }
+    println!("Processed {} files, {} failed, {} empty and skipped", outcome.total(), outcome.failed.len(), outcome.empty.len());
+    if !outcome.is_ok() {
+        return Err(anyhow!("{} of {} files failed", outcome.failed.len(), outcome.total()));
+    }
+    Ok(())
+}
+
+/// Runs `artemis_ast-<name>` from the first of `dirs` holding it with the
+/// remaining arguments, failing with its exit code if it fails.
+fn run_plugin(args: &[OsString], dirs: &[PathBuf]) -> Result<()> {
+    let (name, rest) = args.split_first().ok_or_else(|| anyhow!("missing subcommand"))?;
+    let file_name = format!("{}{}{}", PLUGIN_PREFIX, name.to_string_lossy(), std::env::consts::EXE_SUFFIX);
+    let program = dirs.iter()
+        .map(|dir| dir.join(&file_name))
+        .find(|path| path.is_file())
+        .ok_or_else(|| anyhow!("unknown command {:?}, and no {} plugin was found", name, file_name))?;
+
+    let status = std::process::Command::new(&program).args(rest).status()
+        .map_err(|e| anyhow!("failed to run {}: {}", program.display(), e))?;
+    if !status.success() {
+        return Err(CheckFailed { code: status.code().unwrap_or(1), reason: format!("{} failed with {}", file_name, status) }.into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A directory holding the plugins `artemis_ast-hello`, which records
+    /// its arguments next to itself, `artemis_ast-fail`, which exits with
+    /// 3, and `artemis_ast-extract`, which a built-in command hides.
+    #[cfg(unix)]
+    fn plugin_dir() -> PathBuf {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = crate::test_dir("plugins");
+        for (name, body) in [("hello", "echo \"$@\" > \"$(dirname \"$0\")/args\""), ("fail", "exit 3"), ("extract", "exit 1")] {
+            let path = dir.join(format!("{}{}", PLUGIN_PREFIX, name));
+            std::fs::write(&path, format!("#!/bin/sh\n{}\n", body)).unwrap();
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        }
+        dir
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_plugins() {
+        use clap::Parser;
+
+        #[derive(Parser)]
+        struct Cli {
+            #[command(subcommand)]
+            command: Commands,
+        }
+
+        let dir = plugin_dir();
+        let dirs = plugin_dirs(Some(std::env::join_paths([&dir]).unwrap()));
+        assert_eq!(dirs.last(), Some(&dir));
+        let found = find_plugins(&dirs);
+        assert!(["extract", "fail", "hello"].iter().all(|name| found.contains(*name)), "{:?}", found);
+
+        let args = |command: &str| Cli::try_parse_from(["artemis_ast", command, "a.ast", "b.yaml"]).unwrap().command;
+        assert!(matches!(args("extract"), Commands::Extract(_)));
+        let Commands::External(hello) = args("hello") else {
+            panic!("hello is not run as a plugin");
+        };
+        run_plugin(&hello, &dirs).unwrap();
+        assert_eq!(std::fs::read_to_string(dir.join("args")).unwrap(), "a.ast b.yaml\n");
+
+        let error = run_plugin(&["fail".into()], &dirs).unwrap_err();
+        assert_eq!(error.downcast_ref::<CheckFailed>().map(|failed| failed.code), Some(3));
+        let error = run_plugin(&["missing".into()], &dirs).unwrap_err();
+        assert!(error.to_string().starts_with("unknown command \"missing\""), "{}", error);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,81 @@
+use clap::ValueEnum;
+use crate::{LuaTable, Span, Token, Value};
+
+/// The layout of a script's `ast` table.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum AstVersion {
+    /// astver 1.x: blocks are named after their label, without the `block_` prefix
+    #[value(name = "1")]
+    V1,
+    /// astver 2.x: blocks are named `block_00000`, `block_00001`, ...
+    #[value(name = "2")]
+    V2,
+}
+
+/// `1`, `1.5` and `"1.5"` are 1.x, anything else is taken as 2.x.
+fn from_value(value: &Value) -> AstVersion {
+    let major = match value {
+        Value::Integer(i, _) => Some(*i as f64),
+        Value::Float(f, _) => Some(*f),
+        Value::String(s) => s.parse().ok(),
+        _ => None,
+    };
+    match major {
+        Some(major) if (1.0..2.0).contains(&major) => AstVersion::V1,
+        _ => AstVersion::V2,
+    }
+}
+
+/// The version to read a parsed script with: `forced`, from `--astver`, if
+/// given, its `astver` otherwise.
+pub fn version(ast: &LuaTable, forced: Option<AstVersion>) -> AstVersion {
+    match forced {
+        Some(version) => version,
+        None => ast.get("astver").map_or(AstVersion::V2, from_value),
+    }
+}
+
+/// Like [`version`], for a script that is only tokenized.
+pub fn version_of_tokens(tokens: &[(Token, Span)], forced: Option<AstVersion>) -> AstVersion {
+    if let Some(version) = forced {
+        return version;
+    }
+    let declared = tokens.windows(3).find_map(|window| match window {
+        [(Token::Identifier(key), _), (Token::Equal, _), (value, _)] if key == "astver" => match value {
+            Token::IntegerLiteral(i, _) => Some(Value::from(*i)),
+            Token::FloatLiteral(f, _) => Some(Value::from(*f)),
+            Token::StringLiteral(s) => Some(Value::String(s.clone())),
+            _ => None,
+        },
+        _ => None,
+    });
+    declared.as_ref().map_or(AstVersion::V2, from_value)
+}
+
+/// Whether the field `name` of the `ast` table is a block.
+pub fn is_block_name(version: AstVersion, name: &str) -> bool {
+    match version {
+        AstVersion::V1 => true,
+        AstVersion::V2 => name.starts_with("block_"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_astver_1_labels() {
+        let input = r#"astver = 1.2
+            ast = {
+                opening = { text = { ja = { { "朝だ。" } } }, linknext = "morning" },
+                morning = { text = { ja = { { name = {"妃愛"}, "「お兄」" } } } },
+            }"#;
+        let ast = crate::parse_tokens(&crate::tokenize(input).unwrap()).unwrap();
+        assert_eq!(version(&ast, None), AstVersion::V1);
+        let texts = crate::extract_secnario(&ast, None, &crate::ScenarioOptions::default()).unwrap();
+        assert_eq!(texts, vec!["朝だ。", "「お兄」"]);
+        assert!(crate::extract_secnario(&ast, Some(AstVersion::V2), &crate::ScenarioOptions::default()).unwrap().is_empty());
+        assert_eq!(version_of_tokens(&crate::tokenize_spanned(input).unwrap(), None), AstVersion::V1);
+    }
+}
@@ -0,0 +1,60 @@
+use std::fmt;
+
+/// How many entries of a translation have been filled in.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Completeness {
+    pub translated: usize,
+    pub total: usize,
+}
+
+impl Completeness {
+    /// Entries that are not empty or whitespace only count as translated.
+    pub fn of(texts: &[String]) -> Self {
+        let translated = texts.iter().filter(|text| !text.trim().is_empty()).count();
+        Completeness { translated, total: texts.len() }
+    }
+
+    pub fn add(&mut self, other: Completeness) {
+        self.translated += other.translated;
+        self.total += other.total;
+    }
+
+    /// An empty translation has nothing left to do, so it is complete.
+    pub fn percent(&self) -> f64 {
+        if self.total == 0 {
+            100.0
+        } else {
+            self.translated as f64 * 100.0 / self.total as f64
+        }
+    }
+}
+
+impl fmt::Display for Completeness {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{} translated ({:.1}%)", self.translated, self.total, self.percent())
+    }
+}
+
+/// `90` or `90%`, between 0 and 100.
+pub fn parse_percent(value: &str) -> Result<f64, String> {
+    let percent: f64 = value.trim_end_matches('%').parse().map_err(|_| format!("{} is not a percentage", value))?;
+    if !(0.0..=100.0).contains(&percent) {
+        return Err(format!("{} is not between 0% and 100%", value));
+    }
+    Ok(percent)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_completeness() {
+        let texts = vec!["Morning!".to_string(), "".to_string(), "  ".to_string(), "Hi".to_string()];
+        let completeness = Completeness::of(&texts);
+        assert_eq!(completeness, Completeness { translated: 2, total: 4 });
+        assert_eq!(completeness.to_string(), "2/4 translated (50.0%)");
+        assert_eq!(parse_percent("95%"), Ok(95.0));
+        assert!(parse_percent("120").is_err());
+    }
+}
@@ -0,0 +1,91 @@
+use crate::{LuaTable, Value};
+
+/// One branch of an open `{"if", exp=...}`: the conditions of the branches
+/// before it, and its own, `None` for `{"else"}`.
+struct Branch {
+    earlier: Vec<String>,
+    exp: Option<String>,
+}
+
+impl Branch {
+    fn describe(&self) -> String {
+        let negated = (!self.earlier.is_empty()).then(|| format!("not ({})", self.earlier.join(" or ")));
+        match (negated, &self.exp) {
+            (Some(negated), Some(exp)) => format!("{} and {}", negated, exp),
+            (Some(negated), None) => negated,
+            (None, exp) => exp.clone().unwrap_or_default(),
+        }
+    }
+}
+
+/// Follows the `if`/`elseif`/`else`/`endif` commands of the blocks in
+/// script order, which may open in one block and close in a later one, to
+/// tell which condition a block's text is shown under.
+#[derive(Default)]
+pub struct ConditionTracker {
+    open: Vec<Branch>,
+}
+
+impl ConditionTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn feed(&mut self, command: &Value) {
+        let exp = || crate::command_attr(command, "exp").and_then(Value::as_string).cloned().unwrap_or_else(|| "?".to_string());
+        match crate::command_name(command) {
+            Some("if") => self.open.push(Branch { earlier: Vec::new(), exp: Some(exp()) }),
+            Some(name @ ("elseif" | "else")) => {
+                if let Some(branch) = self.open.last_mut() {
+                    branch.earlier.extend(branch.exp.take());
+                    branch.exp = (name == "elseif").then(exp);
+                }
+            }
+            Some("endif") => {
+                self.open.pop();
+            }
+            _ => {}
+        }
+    }
+
+    fn current(&self) -> Option<String> {
+        (!self.open.is_empty()).then(|| self.open.iter().map(Branch::describe).collect::<Vec<_>>().join(" and "))
+    }
+
+    /// Reads the commands of the next block, returning the condition in
+    /// force at its `{"text"}` command, or at its end if it has none.
+    pub fn block(&mut self, block: &LuaTable) -> Option<String> {
+        let mut condition = None;
+        for command in block.array.iter() {
+            if crate::command_name(command) == Some("text") && condition.is_none() {
+                condition = Some(self.current());
+            }
+            self.feed(command);
+        }
+        condition.unwrap_or_else(|| self.current())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conditions() {
+        let input = r#"ast = {
+            block_00000 = { {"if", exp="f.route==1"}, {"text"}, text = { ja = { { "a" } } } },
+            block_00001 = { {"if", exp="f.seen"}, {"text"}, {"endif"}, {"elseif", exp="f.route==2"}, text = { ja = { { "b" } } } },
+            block_00002 = { {"else"}, {"text"}, {"endif"}, text = { ja = { { "c" } } } },
+            block_00003 = { {"text"}, text = { ja = { { "d" } } } },
+        }"#;
+        let ast = crate::parse_tokens(&crate::tokenize(input).unwrap()).unwrap();
+        let mut tracker = ConditionTracker::new();
+        let conditions: Vec<Option<String>> = crate::iter_blocks(&ast, None).map(|(_, block)| tracker.block(block)).collect();
+        assert_eq!(conditions, vec![
+            Some("f.route==1".to_string()),
+            Some("f.route==1 and f.seen".to_string()),
+            Some("not (f.route==1 or f.route==2)".to_string()),
+            None,
+        ]);
+    }
+}
@@ -0,0 +1,101 @@
+use std::collections::BTreeSet;
+use anyhow::{Result, anyhow};
+use crate::{LuaTable, Value};
+
+/// A staff roll or translation notice to splice into the linknext chain.
+pub struct Credits {
+    /// Each page of text, shown one after the other
+    pub pages: Vec<String>,
+    /// Channels to write the pages under, every one the script has when empty
+    pub languages: Vec<String>,
+    /// Block the credits follow, the last block when `None`
+    pub after: Option<String>,
+    /// Name of the new block, the next free `block_NNNNN` when `None`
+    pub name: Option<String>,
+}
+
+/// Text channels used anywhere in the script, `vo` left out.
+fn languages(ast: &LuaTable, astver: Option<crate::compat::AstVersion>) -> BTreeSet<String> {
+    crate::iter_blocks(ast, astver)
+        .filter_map(|(_, block)| block.get("text")?.as_table())
+        .flat_map(LuaTable::fields)
+        .map(|(language, _)| language)
+        .filter(|language| *language != "vo")
+        .cloned()
+        .collect()
+}
+
+/// `block_NNNNN` numbered one past the highest block in the script.
+fn next_block_name(ast: &LuaTable, astver: Option<crate::compat::AstVersion>) -> String {
+    let last = crate::iter_blocks(ast, astver)
+        .filter_map(|(name, _)| name.strip_prefix("block_")?.parse::<u64>().ok())
+        .max();
+    format!("block_{:05}", last.map_or(0, |last| last + 1))
+}
+
+/// `{ {"text"}, text = { ja = { {"page"}, ... } }, linknext = ... }`
+fn credits_block(credits: &Credits, languages: &[String], linknext: Option<Value>) -> LuaTable {
+    let mut text = LuaTable::new();
+    for language in languages {
+        let pages = credits.pages.iter().map(|page| Value::from(vec![Value::from(page.as_str())])).collect::<Vec<_>>();
+        text.insert(language.clone(), Value::from(pages));
+    }
+    let mut block = LuaTable::new();
+    block.push(Value::from(vec![Value::from("text")]));
+    block.insert("text".to_string(), Value::from(text));
+    if let Some(linknext) = linknext {
+        block.insert("linknext".to_string(), linknext);
+    }
+    block
+}
+
+/// Adds the credits as a new block right after `credits.after`: that block
+/// now links to the credits, and the credits go on to wherever it linked
+/// before. Returns the name of the new block.
+pub fn inject(ast: &mut LuaTable, astver: Option<crate::compat::AstVersion>, credits: &Credits) -> Result<String> {
+    if credits.pages.is_empty() {
+        return Err(anyhow!("No credits text given"));
+    }
+    let name = credits.name.clone().unwrap_or_else(|| next_block_name(ast, astver));
+    let after = match &credits.after {
+        Some(after) => after.clone(),
+        None => crate::iter_blocks(ast, astver).last().map(|(name, _)| name.clone()).ok_or(anyhow!("The script has no blocks to follow"))?,
+    };
+    let languages: Vec<String> = if credits.languages.is_empty() { languages(ast, astver).into_iter().collect() } else { credits.languages.clone() };
+    let languages = if languages.is_empty() { vec!["ja".to_string()] } else { languages };
+
+    let blocks = ast.get_mut("ast").and_then(Value::as_table_mut).ok_or(anyhow!("The script has no ast table"))?;
+    if blocks.contains_key(&name) {
+        return Err(anyhow!("The script already has a block named {}", name));
+    }
+    let previous = blocks.get_mut(&after).and_then(Value::as_table_mut).ok_or(anyhow!("The script has no block named {}", after))?;
+    let linknext = previous.insert("linknext".to_string(), Value::from(name.as_str()));
+    blocks.insert_after(&after, name.clone(), Value::from(credits_block(credits, &languages, linknext)));
+    Ok(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inject_credits() {
+        let input = r#"ast = {
+            block_00000 = { text = { ja = { { "a" } }, en = { { "a" } } }, linknext = "block_00001" },
+            block_00001 = { text = { ja = { { "b" } } } },
+        }"#;
+        let mut ast = crate::parse_tokens(&crate::tokenize(input).unwrap()).unwrap();
+        let credits = Credits { pages: vec!["Translation: someone".to_string(), "Thanks".to_string()], languages: Vec::new(), after: Some("block_00000".to_string()), name: None };
+        assert_eq!(inject(&mut ast, None, &credits).unwrap(), "block_00002");
+
+        let order: Vec<&String> = crate::iter_blocks(&ast, None).map(|(name, _)| name).collect();
+        assert_eq!(order, vec!["block_00000", "block_00002", "block_00001"]);
+        assert!(crate::links::check_links(&ast, None, false).is_empty());
+        let block = crate::iter_blocks(&ast, None).find(|(name, _)| *name == "block_00002").unwrap().1;
+        assert_eq!(block.get("linknext").and_then(Value::as_string).unwrap(), "block_00001");
+        let text = block.get("text").and_then(Value::as_table).unwrap();
+        assert_eq!(text.fields().map(|(language, _)| language.as_str()).collect::<Vec<_>>(), vec!["en", "ja"]);
+
+        assert!(inject(&mut ast, None, &Credits { name: Some("block_00001".to_string()), ..credits }).is_err());
+    }
+}
@@ -0,0 +1,72 @@
+use std::fmt::Write as _;
+use std::path::Path;
+use anyhow::Result;
+use crate::{SpannedToken, Token, Tokenizer, syntax};
+
+/// The token as shown in a dump. String contents are replaced by their
+/// length so a dump can be attached to an issue without sharing the script.
+fn describe(token: &Token) -> String {
+    match token {
+        Token::StringLiteral(s) => format!("StringLiteral({} chars)", s.chars().count()),
+        token => format!("{:?}", token),
+    }
+}
+
+/// Tokenizes as far as possible, returning the tokens read before the
+/// first error along with it.
+fn tokenize_partial(input: &str) -> (Vec<SpannedToken>, Option<syntax::Error>) {
+    let mut tokens = Vec::new();
+    for token in Tokenizer::new(input) {
+        match token {
+            Ok(token) => tokens.push(token),
+            Err(e) => return (tokens, Some(e)),
+        }
+    }
+    (tokens, None)
+}
+
+/// Lists the error and every token that could be read, with its index,
+/// line:column and byte range, indented by table nesting so the shape of
+/// the parse tree is visible.
+pub fn render(input: &str, error: &anyhow::Error) -> String {
+    let (tokens, lex_error) = tokenize_partial(input);
+    let mut dump = String::new();
+    let _ = writeln!(dump, "error: {:#}", error);
+    let _ = writeln!(dump, "{} bytes, {} lines, {} tokens", input.len(), input.lines().count(), tokens.len());
+    let mut depth = 0usize;
+    for (index, (token, span)) in tokens.iter().enumerate() {
+        if *token == Token::CloseBrace {
+            depth = depth.saturating_sub(1);
+        }
+        let (line, column) = crate::line_column(input, span.start);
+        let position = format!("{}:{}", line, column);
+        let _ = writeln!(dump, "{:>7} {:>12} {:>17} {}{}", index, position, format!("{}..{}", span.start, span.end), "  ".repeat(depth), describe(token));
+        if *token == Token::OpenBrace {
+            depth += 1;
+        }
+    }
+    if let Some(e) = lex_error {
+        let _ = writeln!(dump, "tokenizing stopped: {}", e);
+    }
+    dump
+}
+
+pub fn write(path: &Path, input: &str, error: &anyhow::Error) -> Result<()> {
+    std::fs::write(path, render(input, error))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_dump() {
+        let input = "ast = {\n\tblock_00000 = { \"秘密\" ~ }\n}";
+        let error = crate::tokenize(input).unwrap_err();
+        let dump = render(input, &error.into());
+        assert!(!dump.contains("秘密"));
+        assert!(dump.contains("      6         2:18            25..33     StringLiteral(2 chars)"));
+        assert!(dump.ends_with("tokenizing stopped: Unexpected character: ~ at line 2, column 23\n"));
+    }
+}
@@ -0,0 +1,156 @@
+use std::collections::{BTreeMap, HashMap};
+use anyhow::{Result, anyhow};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// One distinct line of a deduplicated extraction, together with every
+/// position (in original extraction order) it was found at.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, PartialEq)]
+pub struct DedupeEntry {
+    pub text: String,
+    pub refs: Vec<usize>,
+}
+
+/// One line of an extraction written with `--tag-kind`.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, PartialEq)]
+pub struct TaggedEntry {
+    pub kind: crate::LineKind,
+    pub text: String,
+    /// Who is heard on a narrated line, guessed from its vo entry
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inferred_speaker: Option<String>,
+    /// The `if` the line is only shown under, e.g. on one route
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub condition: Option<String>,
+}
+
+/// One line of an extraction written with `--group-by`, with its position
+/// in a plain extraction.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, PartialEq)]
+pub struct GroupedEntry {
+    pub id: usize,
+    pub text: String,
+}
+
+/// A translated line with alternatives for some players, such as a
+/// `female` or `plural` form, merged into channels of their own.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, PartialEq)]
+pub struct VariantEntry {
+    pub text: String,
+    pub variants: BTreeMap<String, String>,
+}
+
+/// A line of a plain list, where translators may replace a string with its variants.
+#[derive(Deserialize, JsonSchema, Debug)]
+#[serde(untagged)]
+pub enum LineEntry {
+    Text(String),
+    Variants(VariantEntry),
+}
+
+/// Every layout accepted by merge: the plain list written by a normal
+/// extraction, the reference list written by `--dedupe`, the records
+/// written by `--tag-kind`, a plain list with variants on some lines, or
+/// the groups written by `--group-by`.
+#[derive(Deserialize, JsonSchema, Debug)]
+#[serde(untagged)]
+pub enum TranslationFile {
+    Plain(Vec<String>),
+    Deduped(Vec<DedupeEntry>),
+    Tagged(Vec<TaggedEntry>),
+    WithVariants(Vec<LineEntry>),
+    Grouped(BTreeMap<String, Vec<GroupedEntry>>),
+}
+
+impl TranslationFile {
+    pub fn into_strings(self) -> Result<Vec<String>> {
+        match self {
+            TranslationFile::Plain(texts) => Ok(texts),
+            TranslationFile::Deduped(entries) => expand(entries),
+            TranslationFile::Tagged(entries) => Ok(entries.into_iter().map(|e| e.text).collect()),
+            TranslationFile::WithVariants(entries) => Ok(entries.into_iter()
+                .map(|entry| match entry {
+                    LineEntry::Text(text) => text,
+                    LineEntry::Variants(entry) => entry.text,
+                })
+                .collect()),
+            TranslationFile::Grouped(groups) => expand(groups.into_values()
+                .flatten()
+                .map(|entry| DedupeEntry { text: entry.text, refs: vec![entry.id] })
+                .collect()),
+        }
+    }
+
+    /// The variants of each line, by position; empty for layouts without any.
+    pub fn variants(&self) -> Vec<BTreeMap<String, String>> {
+        match self {
+            TranslationFile::WithVariants(entries) => entries.iter()
+                .map(|entry| match entry {
+                    LineEntry::Text(_) => BTreeMap::new(),
+                    LineEntry::Variants(entry) => entry.variants.clone(),
+                })
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+pub fn dedupe(texts: Vec<String>) -> Vec<DedupeEntry> {
+    let mut entries: Vec<DedupeEntry> = Vec::new();
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    for (index, text) in texts.into_iter().enumerate() {
+        match seen.get(&text) {
+            Some(&slot) => entries[slot].refs.push(index),
+            None => {
+                seen.insert(text.clone(), entries.len());
+                entries.push(DedupeEntry { text, refs: vec![index] });
+            }
+        }
+    }
+    entries
+}
+
+/// Rebuilds the full positional list, checking that every position is
+/// referenced exactly once.
+pub fn expand(entries: Vec<DedupeEntry>) -> Result<Vec<String>> {
+    let total = entries.iter().map(|e| e.refs.len()).sum();
+    let mut texts: Vec<Option<String>> = vec![None; total];
+    for entry in entries {
+        for index in entry.refs {
+            let slot = texts.get_mut(index)
+                .ok_or(anyhow!("Reference {} is out of range (only {} lines)", index, total))?;
+            if slot.is_some() {
+                return Err(anyhow!("Reference {} is used more than once", index));
+            }
+            *slot = Some(entry.text.clone());
+        }
+    }
+    // every slot is filled: there are `total` distinct in-range references
+    Ok(texts.into_iter().map(Option::unwrap).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dedupe_roundtrip() {
+        let texts: Vec<String> = ["……", "「お兄」", "……", "……"].iter().map(|s| s.to_string()).collect();
+        let entries = dedupe(texts.clone());
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].refs, vec![0, 2, 3]);
+
+        let yaml = serde_yaml::to_string(&entries).unwrap();
+        let parsed: TranslationFile = serde_yaml::from_str(&yaml).unwrap();
+        assert_eq!(parsed.into_strings().unwrap(), texts);
+    }
+
+    #[test]
+    fn test_expand_rejects_duplicate_refs() {
+        let entries = vec![
+            DedupeEntry { text: "a".to_string(), refs: vec![0] },
+            DedupeEntry { text: "b".to_string(), refs: vec![0] },
+        ];
+        assert!(expand(entries).is_err());
+    }
+}
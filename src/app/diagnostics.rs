@@ -0,0 +1,58 @@
+use std::fmt::Write as _;
+use unicode_width::UnicodeWidthChar;
+
+/// Splits `file: message at line 3, column 5 (note)` into the message, the
+/// location and the note.
+fn split(error: &str) -> Option<(&str, usize, usize, &str)> {
+    let (message, location) = error.split_once(" at line ")?;
+    let (line, rest) = location.split_once(", column ")?;
+    let digits = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+    let note = rest[digits..].trim().trim_start_matches('(').trim_end_matches(')');
+    let message = message.split_once(": ").map_or(message, |(_, message)| message);
+    Some((message, line.parse().ok()?, rest[..digits].parse().ok()?, note))
+}
+
+/// Shows the line an error points at with a caret under the column, in the
+/// style of the Rust compiler. Returns `None` for errors without a location.
+pub fn snippet(input: &str, error: &str) -> Option<String> {
+    let mut rendered = String::new();
+    for error in error.lines() {
+        let Some((message, line, column, note)) = split(error) else {
+            continue;
+        };
+        let text = input.lines().nth(line.checked_sub(1)?).unwrap_or("");
+        let gutter = " ".repeat(line.to_string().len());
+        // tabs are copied so the caret lines up however wide the terminal draws them
+        let pad: String = text.chars().take(column.saturating_sub(1))
+            .map(|ch| if ch == '\t' { "\t".to_string() } else { " ".repeat(ch.width().unwrap_or(0)) })
+            .collect();
+        let _ = writeln!(rendered, "error: {}", message);
+        let _ = writeln!(rendered, "{}--> line {}, column {}", gutter, line, column);
+        let _ = writeln!(rendered, "{} |", gutter);
+        let _ = writeln!(rendered, "{} | {}", line, text);
+        let _ = writeln!(rendered, "{} | {}^", gutter, pad);
+        if !note.is_empty() {
+            let _ = writeln!(rendered, "{} = note: {}", gutter, note);
+        }
+    }
+    (!rendered.is_empty()).then(|| rendered.trim_end().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snippet() {
+        let input = "ast = {\n\tblock_00000 = { \"「お兄」\" ~ },\n}\n";
+        let error = crate::parse_source(input.to_string(), std::path::Path::new("a.ast"), &crate::ParseOptions::default()).unwrap_err();
+        assert_eq!(snippet(input, &error.to_string()).unwrap(), [
+            "error: Unexpected character: ~",
+            " --> line 2, column 25",
+            "  |",
+            "2 | \tblock_00000 = { \"「お兄」\" ~ },",
+            "  | \t                           ^",
+        ].join("\n"));
+        assert!(snippet(input, "a.ast: unbalanced braces").is_none());
+    }
+}
@@ -0,0 +1,96 @@
+use std::collections::HashMap;
+use anyhow::{Result, anyhow};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use crate::BlockText;
+
+/// One block of an extraction written with `--per-block`, stored as its own
+/// yaml document.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, PartialEq)]
+pub struct BlockDocument {
+    pub block: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub line: Option<i64>,
+    /// The `if` the block's text is only shown under
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub condition: Option<String>,
+    pub texts: Vec<String>,
+}
+
+pub fn to_yaml(blocks: &[BlockText]) -> Result<String> {
+    let mut output = String::new();
+    for block in blocks.iter().filter(|block| !block.texts.is_empty()) {
+        let document = BlockDocument {
+            block: block.name.clone(),
+            line: block.line,
+            condition: block.condition.clone(),
+            texts: block.texts.iter().map(|(_, text, _)| text.clone()).collect(),
+        };
+        output.push_str("---\n");
+        output.push_str(&serde_yaml::to_string(&document)?);
+    }
+    Ok(output)
+}
+
+/// Reads a per-block translation, or returns `None` when `content` uses one
+/// of the single list layouts.
+pub fn parse(content: &str) -> Result<Option<Vec<BlockDocument>>> {
+    let mut documents = Vec::new();
+    for document in serde_yaml::Deserializer::from_str(content) {
+        let value = serde_yaml::Value::deserialize(document)?;
+        if !value.is_mapping() {
+            if documents.is_empty() {
+                return Ok(None);
+            }
+            return Err(anyhow!("Document {} is not a block document", documents.len()));
+        }
+        documents.push(serde_yaml::from_value(value)?);
+    }
+    if documents.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(documents))
+}
+
+/// Pairs the current text of every block named in `documents` with its
+/// translation. Blocks without a document are left out, so a file holding
+/// only some blocks merges just those.
+pub fn pair(blocks: &[BlockText], documents: Vec<BlockDocument>) -> Result<(Vec<String>, Vec<String>)> {
+    let mut by_name: HashMap<&str, &BlockText> = blocks.iter().map(|block| (block.name.as_str(), block)).collect();
+    let mut original = Vec::new();
+    let mut translated = Vec::new();
+    for document in documents {
+        let block = by_name.remove(document.block.as_str())
+            .ok_or(anyhow!("Block {} is not in the script, or appears twice", document.block))?;
+        if block.texts.len() != document.texts.len() {
+            return Err(anyhow!("Block {} has {} lines but its document has {}", document.block, block.texts.len(), document.texts.len()));
+        }
+        original.extend(block.texts.iter().map(|(_, text, _)| text.clone()));
+        translated.extend(document.texts);
+    }
+    Ok((original, translated))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LineKind;
+
+    #[test]
+    fn test_partial_documents() {
+        let blocks = || vec![
+            BlockText { name: "block_00000".to_string(), line: Some(18), texts: vec![(LineKind::Dialogue, "「お兄」".to_string(), None)], condition: Some("f.route==1".to_string()) },
+            BlockText { name: "block_00001".to_string(), line: None, texts: vec![(LineKind::Narration, "……".to_string(), None)], condition: None },
+        ];
+        let yaml = to_yaml(&blocks()).unwrap();
+        assert!(yaml.contains("condition: f.route==1\n"));
+        assert_eq!(parse(&yaml).unwrap().unwrap().len(), 2);
+
+        let partial = "---\nblock: block_00001\ntexts:\n- '...'\n";
+        let (original, translated) = pair(&blocks(), parse(partial).unwrap().unwrap()).unwrap();
+        assert_eq!(original, vec!["……"]);
+        assert_eq!(translated, vec!["..."]);
+
+        assert_eq!(parse("- a\n- b\n").unwrap(), None);
+    }
+}
@@ -0,0 +1,126 @@
+use std::{collections::HashMap, fmt};
+use crate::{LuaTable, Value};
+
+/// Where two scripts first differ, as a path such as `ast.block_00000[0].time`.
+#[derive(Debug, PartialEq)]
+pub struct Difference {
+    pub path: String,
+    description: String,
+}
+
+impl fmt::Display for Difference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.description)
+    }
+}
+
+fn difference(path: &str, description: String) -> Option<Difference> {
+    Some(Difference { path: path.to_string(), description })
+}
+
+fn summary(value: &Value) -> String {
+    match value {
+        Value::Integer(i, _) => i.to_string(),
+        Value::Float(f, _) => format!("{:?}", f),
+        Value::String(s) => format!("{:?}", s),
+        Value::BareWord(word) => word.clone(),
+        Value::Table(table) => {
+            let mut keys: Vec<&String> = table.fields().map(|(key, _)| key).collect();
+            keys.sort();
+            match (table.array.len(), keys.is_empty()) {
+                (entries, true) => format!("a table of {} entries", entries),
+                (0, false) => format!("a table with keys {:?}", keys),
+                (entries, false) => format!("a table of {} entries with keys {:?}", entries, keys),
+            }
+        }
+        Value::SpContent(Some(sp)) => format!("[{}]", sp),
+        Value::SpContent(None) => "[]".to_string(),
+    }
+}
+
+fn compare_dicts(path: &str, a: &HashMap<&String, &Value>, b: &HashMap<&String, &Value>) -> Option<Difference> {
+    let mut keys: Vec<&String> = a.keys().chain(b.keys()).copied().collect();
+    keys.sort();
+    keys.dedup();
+    for key in keys {
+        let child = if path.is_empty() { key.clone() } else { format!("{}.{}", path, key) };
+        match (a.get(key), b.get(key)) {
+            (Some(x), Some(y)) => {
+                if let Some(difference) = compare(&child, x, y) {
+                    return Some(difference);
+                }
+            }
+            (Some(x), None) => return difference(&child, format!("only in the first script ({})", summary(x))),
+            (None, Some(y)) => return difference(&child, format!("only in the second script ({})", summary(y))),
+            (None, None) => unreachable!(),
+        }
+    }
+    None
+}
+
+fn fields(table: &LuaTable) -> HashMap<&String, &Value> {
+    table.fields().collect()
+}
+
+/// Explains the first difference between two values, `None` when they are
+/// the same. Table key order is ignored and numbers compare like Lua does,
+/// so `2` equals `2.0`.
+fn compare(path: &str, a: &Value, b: &Value) -> Option<Difference> {
+    let same = match (a, b) {
+        (Value::Integer(x, _), Value::Integer(y, _)) => x == y,
+        (Value::Float(x, _), Value::Float(y, _)) => x == y,
+        (Value::Integer(x, _), Value::Float(y, _)) | (Value::Float(y, _), Value::Integer(x, _)) => *x as f64 == *y,
+        (Value::String(x), Value::String(y)) | (Value::BareWord(x), Value::BareWord(y)) => x == y,
+        (Value::SpContent(x), Value::SpContent(y)) => x == y,
+        (Value::Table(x), Value::Table(y)) => {
+            for (index, (x, y)) in x.array.iter().zip(&y.array).enumerate() {
+                if let Some(difference) = compare(&format!("{}[{}]", path, index), x, y) {
+                    return Some(difference);
+                }
+            }
+            if x.array.len() == y.array.len() {
+                return compare_dicts(path, &fields(x), &fields(y));
+            }
+            false
+        }
+        _ => false,
+    };
+    if same {
+        None
+    } else {
+        difference(path, format!("{} vs {}", summary(a), summary(b)))
+    }
+}
+
+/// `None` when both scripts are semantically identical, otherwise the path
+/// of the first difference with both sides.
+pub fn first_difference(a: &LuaTable, b: &LuaTable) -> Option<Difference> {
+    compare_dicts("", &a.fields().collect(), &b.fields().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(input: &str) -> LuaTable {
+        crate::parse_tokens(&crate::tokenize(input).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_equivalent() {
+        let a = parse("astver = 2.0\nast = {\n\tblock_00000 = {\n\t\t{\"bg\", time=2000, file=\"bg001a\"},\n\t},\n}\n");
+        let b = parse("ast = { block_00000 = { {\"bg\", time = 2000, file = \"bg001a\"} } }\nastver = 2\n");
+        assert_eq!(first_difference(&a, &b), None);
+
+        let c = parse("ast = { block_00000 = { {\"bg\", time = 1000, file = \"bg001a\"} } }\nastver = 2\n");
+        assert_eq!(first_difference(&a, &c).unwrap().to_string(), "ast.block_00000[0].time: 2000 vs 1000");
+
+        // fields match by key, wherever they were written
+        let d = parse("ast = { block_00000 = { {file = \"bg001a\", \"bg\", time = 2000} } }\nastver = 2\n");
+        assert_eq!(first_difference(&a, &d), None);
+
+        let spans = crate::parse_spans("ast = {\n\tblock_00000 = {\n\t\t{\"bg\", time=1000},\n\t},\n}\n").unwrap();
+        assert_eq!(spans["ast.block_00000[0].time"], 39..43);
+        assert_eq!(spans["ast.block_00000[0]"], 27..44);
+    }
+}
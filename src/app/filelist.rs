@@ -0,0 +1,45 @@
+use std::path::{Path, PathBuf};
+use anyhow::{Result, anyhow};
+
+/// Reads a list of files, one per line. Blank lines and lines starting with
+/// `#` are skipped, and Windows line endings are accepted.
+pub fn read(list: &Path) -> Result<Vec<PathBuf>> {
+    let content = std::fs::read_to_string(list)
+        .map_err(|e| anyhow!("Failed to read file list {}: {}", list.display(), e))?;
+    Ok(content.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(PathBuf::from)
+        .collect())
+}
+
+/// Replaces every `@list.txt` among the scripts given on the command line
+/// with the lines of that file, so long lists of scripts do not hit command
+/// line length limits. Only these are expanded, never option values such as
+/// `--text @handle`; a script whose name starts with `@` is given as `./@name`.
+pub fn expand(inputs: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    let mut expanded = Vec::new();
+    for input in inputs {
+        let list = input.to_str().and_then(|input| input.strip_prefix('@')).filter(|list| !list.is_empty());
+        match list {
+            Some(list) => expanded.extend(read(Path::new(list))?),
+            None => expanded.push(input.clone()),
+        }
+    }
+    Ok(expanded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand() {
+        let list = std::env::temp_dir().join("artemis_ast_filelist_test.txt");
+        std::fs::write(&list, "# common route\r\ncommon/01.ast\r\n\r\ncommon/02.ast\r\n").unwrap();
+        let inputs = [PathBuf::from("prologue.ast"), PathBuf::from(format!("@{}", list.display())), PathBuf::from("./@epilogue.ast")];
+        let expanded = expand(&inputs).unwrap();
+        assert_eq!(expanded, ["prologue.ast", "common/01.ast", "common/02.ast", "./@epilogue.ast"].map(PathBuf::from));
+        std::fs::remove_file(list).unwrap();
+    }
+}
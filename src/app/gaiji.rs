@@ -0,0 +1,100 @@
+use std::{collections::HashMap, path::Path};
+use anyhow::{Result, anyhow};
+
+/// Converts private-use glyphs (hearts, notes, ...) to readable `<name>`
+/// tokens for translators and back. PUA characters missing from the mapping
+/// file become `<U+XXXX>` so they are never shown as tofu.
+#[derive(Debug, Default)]
+pub struct GaijiMap {
+    names: HashMap<char, String>,
+    chars: HashMap<String, char>,
+}
+
+pub fn is_private_use(ch: char) -> bool {
+    matches!(ch as u32, 0xE000..=0xF8FF | 0xF0000..=0xFFFFD | 0x100000..=0x10FFFD)
+}
+
+impl GaijiMap {
+    /// Reads a yaml mapping of token names to glyphs, e.g. `heart: "\uE000"`.
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+        let entries: HashMap<String, String> = serde_yaml::from_str(&content)?;
+        let mut map = GaijiMap::default();
+        for (name, glyph) in entries {
+            let mut chars = glyph.chars();
+            let (Some(ch), None) = (chars.next(), chars.next()) else {
+                return Err(anyhow!("{}: gaiji {} must map to a single character, got {:?}", path.display(), name, glyph));
+            };
+            if name.is_empty() || name.contains(['<', '>']) {
+                return Err(anyhow!("{}: invalid gaiji name {:?}", path.display(), name));
+            }
+            if let Some(other) = map.names.insert(ch, name.clone()) {
+                return Err(anyhow!("{}: U+{:04X} is mapped to both {} and {}", path.display(), ch as u32, other, name));
+            }
+            map.chars.insert(name, ch);
+        }
+        Ok(map)
+    }
+
+    pub fn encode(&self, text: &str) -> String {
+        let mut encoded = String::new();
+        for ch in text.chars() {
+            match self.names.get(&ch) {
+                Some(name) => encoded.push_str(&format!("<{}>", name)),
+                None if is_private_use(ch) => encoded.push_str(&format!("<U+{:04X}>", ch as u32)),
+                None => encoded.push(ch),
+            }
+        }
+        encoded
+    }
+
+    fn lookup(&self, token: &str) -> Option<char> {
+        if let Some(&ch) = self.chars.get(token) {
+            return Some(ch);
+        }
+        let code = u32::from_str_radix(token.strip_prefix("U+")?, 16).ok()?;
+        char::from_u32(code).filter(|&ch| is_private_use(ch))
+    }
+
+    /// Turns tokens back into glyphs. Angle brackets that are not a known
+    /// token are left alone.
+    pub fn decode(&self, text: &str) -> String {
+        let mut decoded = String::new();
+        let mut rest = text;
+        while let Some(start) = rest.find('<') {
+            decoded.push_str(&rest[..start]);
+            rest = &rest[start..];
+            match rest.find('>').and_then(|end| Some((end, self.lookup(&rest[1..end])?))) {
+                Some((end, ch)) => {
+                    decoded.push(ch);
+                    rest = &rest[end + 1..];
+                }
+                None => {
+                    decoded.push('<');
+                    rest = &rest[1..];
+                }
+            }
+        }
+        decoded.push_str(rest);
+        decoded
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gaiji_roundtrip() {
+        let path = std::env::temp_dir().join("artemis_ast_gaiji_test.yaml");
+        std::fs::write(&path, "heart: \"\\uE000\"\nnote: \"\\uE001\"\n").unwrap();
+        let map = GaijiMap::load(&path).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        let text = "お兄\u{E000}\u{E001}\u{E0FF}";
+        let encoded = map.encode(text);
+        assert_eq!(encoded, "お兄<heart><note><U+E0FF>");
+        assert_eq!(map.decode(&encoded), text);
+        assert_eq!(map.decode("a < b <heart> <unknown> <U+0041>"), "a < b \u{E000} <unknown> <U+0041>");
+    }
+}
@@ -0,0 +1,72 @@
+use std::collections::HashMap;
+use clap::ValueEnum;
+use crate::{BlockText, LineKind, LuaTable, dedupe::GroupedEntry};
+
+/// How `extract --group-by` arranges the lines.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum GroupBy {
+    /// The `name` of the speaker; narration goes under `(narration)`
+    Speaker,
+    /// The block the line is in
+    Block,
+    /// The in-game chapter set by the last `savetitle`; lines before the first go under `(untitled)`
+    Chapter,
+}
+
+/// Sorts the lines of `blocks` into groups, kept in the order each group is
+/// first seen. Every line carries its position in a plain extraction as
+/// `id`, so merge can put it back wherever the groups are edited.
+pub fn group(ast: &LuaTable, astver: Option<crate::compat::AstVersion>, blocks: &[BlockText], by: GroupBy) -> serde_yaml::Mapping {
+    let tables: HashMap<&String, &crate::LuaTable> = crate::iter_blocks(ast, astver).collect();
+    let mut groups = serde_yaml::Mapping::new();
+    let mut chapter = "(untitled)";
+    let mut id = 0;
+    for block in blocks {
+        let table = tables.get(&block.name);
+        if let Some(title) = table.and_then(|table| crate::sections::savetitle(table)) {
+            chapter = title;
+        }
+        let speaker = table
+            .and_then(|table| table.get("text")?.as_table())
+            .and_then(crate::voice::speaker)
+            .map_or("(narration)", String::as_str);
+        for (kind, text, _) in block.texts.iter() {
+            let name = match by {
+                GroupBy::Speaker if *kind == LineKind::Dialogue => speaker,
+                GroupBy::Speaker => "(narration)",
+                GroupBy::Block => &block.name,
+                GroupBy::Chapter => chapter,
+            };
+            let entry = serde_yaml::to_value(GroupedEntry { id, text: text.clone() }).unwrap();
+            match groups.get_mut(name).and_then(serde_yaml::Value::as_sequence_mut) {
+                Some(entries) => entries.push(entry),
+                None => {
+                    groups.insert(name.into(), serde_yaml::Value::Sequence(vec![entry]));
+                }
+            }
+            id += 1;
+        }
+    }
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ScenarioOptions;
+
+    #[test]
+    fn test_group_by_speaker() {
+        let input = r#"ast = {
+            block_00000 = { text = { ja = { { name = {"妃愛"}, "「お兄」" } } } },
+            block_00001 = { text = { ja = { { "朝だ。" } } } },
+            block_00002 = { text = { ja = { { name = {"妃愛"}, "「起きて」" } } } },
+        }"#;
+        let ast = crate::parse_tokens(&crate::tokenize(input).unwrap()).unwrap();
+        let blocks = crate::extract_block_texts(&ast, None, &ScenarioOptions::default()).unwrap();
+        let yaml = serde_yaml::to_string(&group(&ast, None, &blocks, GroupBy::Speaker)).unwrap();
+        assert_eq!(yaml, "妃愛:\n- id: 0\n  text: 「お兄」\n- id: 2\n  text: 「起きて」\n(narration):\n- id: 1\n  text: 朝だ。\n");
+        let parsed: crate::dedupe::TranslationFile = serde_yaml::from_str(&yaml).unwrap();
+        assert_eq!(parsed.into_strings().unwrap(), vec!["「お兄」", "朝だ。", "「起きて」"]);
+    }
+}
@@ -0,0 +1,107 @@
+use std::path::Path;
+use anyhow::{Result, anyhow};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use crate::preview::escape_html;
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, PartialEq)]
+pub struct ExportEntry {
+    pub index: usize,
+    pub source: String,
+    pub translation: String,
+}
+
+/// The JSON a reviewer downloads from the page written by `html-export`.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, PartialEq)]
+pub struct HtmlExport {
+    /// Name of the script the page was made from
+    pub script: String,
+    pub entries: Vec<ExportEntry>,
+}
+
+const SCRIPT: &str = r#"
+function download() {
+  const data = JSON.parse(document.getElementById('data').textContent);
+  document.querySelectorAll('textarea').forEach((area, i) => { data.entries[i].translation = area.value; });
+  const blob = new Blob([JSON.stringify(data, null, 2)], { type: 'application/json' });
+  const link = document.createElement('a');
+  link.href = URL.createObjectURL(blob);
+  link.download = data.script + '.json';
+  link.click();
+}
+"#;
+
+/// Renders a self-contained page showing every source line next to an
+/// editable translation, with a button saving the edits as JSON.
+pub fn render(script: &str, source: &[String], translation: &[String]) -> Result<String> {
+    let export = HtmlExport {
+        script: script.to_string(),
+        entries: source.iter()
+            .zip(translation)
+            .enumerate()
+            .map(|(index, (source, translation))| ExportEntry { index, source: source.clone(), translation: translation.clone() })
+            .collect(),
+    };
+    // keep the embedded json from closing its own <script> tag
+    let data = serde_json::to_string(&export)?.replace("</", "<\\/");
+
+    let mut html = String::from("<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><style>\n");
+    html.push_str("table { border-collapse: collapse; width: 100%; }\ntd { border: 1px solid #ccc; padding: 4px; vertical-align: top; }\n");
+    html.push_str("td.source { width: 45%; white-space: pre-wrap; }\ntextarea { width: 100%; box-sizing: border-box; }\n");
+    html.push_str("</style></head><body>\n");
+    html.push_str(&format!("<h1>{}</h1>\n<button onclick=\"download()\">Download JSON</button>\n<table>\n", escape_html(script)));
+    for entry in export.entries.iter() {
+        html.push_str(&format!(
+            "<tr><td>{}</td><td class=\"source\">{}</td><td><textarea rows=\"3\">{}</textarea></td></tr>\n",
+            entry.index,
+            escape_html(&entry.source),
+            escape_html(&entry.translation),
+        ));
+    }
+    html.push_str("</table>\n");
+    html.push_str(&format!("<script id=\"data\" type=\"application/json\">{}</script>\n", data));
+    html.push_str(&format!("<script>{}</script>\n</body></html>\n", SCRIPT));
+    Ok(html)
+}
+
+/// Reads the reviewed translations back, checking they were made from the
+/// same lines the script holds now.
+pub fn load_translations(path: &Path, source: &[String]) -> Result<Vec<String>> {
+    let export: HtmlExport = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+    if export.entries.len() != source.len() {
+        return Err(anyhow!("{} has {} entries but the script has {} lines", path.display(), export.entries.len(), source.len()));
+    }
+    let mut translations = Vec::new();
+    for (expected, (index, entry)) in source.iter().zip(export.entries.into_iter().enumerate()) {
+        if entry.index != index || entry.source != *expected {
+            return Err(anyhow!("{}: entry {} was exported from a different line: {:?}", path.display(), index, entry.source));
+        }
+        translations.push(entry.translation);
+    }
+    Ok(translations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_export_roundtrip() {
+        let source = vec!["「お兄」".to_string(), "</script>".to_string()];
+        let html = render("a.ast", &source, &source).unwrap();
+        assert_eq!(html.matches("</script>").count(), 2);
+
+        let path = std::env::temp_dir().join("artemis_ast_html_export_test.json");
+        let reviewed = HtmlExport {
+            script: "a.ast".to_string(),
+            entries: vec![
+                ExportEntry { index: 0, source: source[0].clone(), translation: "\"Bro\"".to_string() },
+                ExportEntry { index: 1, source: source[1].clone(), translation: "x".to_string() },
+            ],
+        };
+        std::fs::write(&path, serde_json::to_string(&reviewed).unwrap()).unwrap();
+        assert_eq!(load_translations(&path, &source).unwrap(), vec!["\"Bro\"", "x"]);
+        assert!(load_translations(&path, &source[..1]).is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,146 @@
+use std::{collections::HashMap, path::PathBuf};
+use anyhow::{Result, anyhow};
+use clap::{Args, ValueEnum};
+
+/// Vowels for the syllable rules, with the accented ones of French, German and Spanish.
+const VOWELS: &str = "aeiouyàáâäæèéêëìíîïòóôöœùúûü";
+
+/// Invisible character marking where a word may break.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum SoftBreak {
+    /// U+00AD, shown as a hyphen only when the line breaks there
+    Hyphen,
+    /// U+200B, a break with no hyphen
+    Zwsp,
+}
+
+impl SoftBreak {
+    pub fn char(self) -> char {
+        match self {
+            SoftBreak::Hyphen => '\u{AD}',
+            SoftBreak::Zwsp => '\u{200B}',
+        }
+    }
+}
+
+#[derive(Args, Debug)]
+pub struct HyphenateOptions {
+    /// Insert break points into long words of the translation so the engine's wrapper can split them
+    #[arg(long, value_enum)]
+    pub soft_breaks: Option<SoftBreak>,
+    /// Only break words with at least this many letters
+    #[arg(long, default_value_t = 8)]
+    pub min_word: usize,
+    /// Words hyphenated by hand, one per line with their break points marked (hy-phen-ation); other words are broken by rule
+    #[arg(long, requires = "soft_breaks")]
+    pub hyphenation_dict: Option<PathBuf>,
+}
+
+impl Default for HyphenateOptions {
+    /// The defaults of the command line.
+    fn default() -> Self {
+        HyphenateOptions { soft_breaks: None, min_word: 8, hyphenation_dict: None }
+    }
+}
+
+pub struct Hyphenator {
+    mark: char,
+    min_word: usize,
+    /// Lowercased word -> letters before each break point
+    dictionary: HashMap<String, Vec<usize>>,
+}
+
+fn is_vowel(ch: char) -> bool {
+    VOWELS.contains(ch.to_lowercase().next().unwrap_or(ch))
+}
+
+/// Break points of a word by the usual syllable rules: a single consonant
+/// goes with the next vowel (ba-con), two consonants are split (let-ter).
+/// At least two letters stay on each side.
+fn rule_points(word: &[char]) -> Vec<usize> {
+    (2..word.len().saturating_sub(1))
+        .filter(|&i| {
+            let vowel_before = is_vowel(word[i - 1]) || is_vowel(word[i - 2]);
+            vowel_before && !is_vowel(word[i]) && is_vowel(word[i + 1])
+        })
+        .collect()
+}
+
+impl Hyphenator {
+    /// `None` unless `--soft-breaks` is given.
+    pub fn load(options: &HyphenateOptions) -> Result<Option<Self>> {
+        let Some(soft_break) = options.soft_breaks else {
+            return Ok(None);
+        };
+        let mut dictionary = HashMap::new();
+        if let Some(path) = &options.hyphenation_dict {
+            let content = std::fs::read_to_string(path)
+                .map_err(|e| anyhow!("Failed to read hyphenation dictionary {}: {}", path.display(), e))?;
+            for entry in content.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+                let mut points = Vec::new();
+                let mut word = String::new();
+                for ch in entry.chars() {
+                    if ch == '-' {
+                        points.push(word.chars().count());
+                    } else {
+                        word.extend(ch.to_lowercase());
+                    }
+                }
+                dictionary.insert(word, points);
+            }
+        }
+        Ok(Some(Hyphenator { mark: soft_break.char(), min_word: options.min_word, dictionary }))
+    }
+
+    fn break_word(&self, word: &[char], output: &mut String) {
+        let points = if word.len() < self.min_word || !word.iter().all(|ch| ch.is_alphabetic()) {
+            Vec::new()
+        } else {
+            let lower: String = word.iter().flat_map(|ch| ch.to_lowercase()).collect();
+            self.dictionary.get(&lower).cloned().unwrap_or_else(|| rule_points(word))
+        };
+        for (index, ch) in word.iter().enumerate() {
+            if points.contains(&index) {
+                output.push(self.mark);
+            }
+            output.push(*ch);
+        }
+    }
+
+    /// Adds break points to every long Latin word of `text`. Text already
+    /// holding the mark is left as it is, so merging twice changes nothing.
+    pub fn apply(&self, text: &str) -> String {
+        if text.contains(self.mark) {
+            return text.to_string();
+        }
+        let mut output = String::with_capacity(text.len());
+        let mut word = Vec::new();
+        for ch in text.chars() {
+            if ch.is_alphabetic() && (ch.is_ascii() || ('\u{C0}'..='\u{24F}').contains(&ch)) {
+                word.push(ch);
+                continue;
+            }
+            self.break_word(&word, &mut output);
+            word.clear();
+            output.push(ch);
+        }
+        self.break_word(&word, &mut output);
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_soft_breaks() {
+        let hyphenator = Hyphenator {
+            mark: '-',
+            min_word: 7,
+            dictionary: HashMap::from([("breakfast".to_string(), vec![5])]),
+        };
+        assert_eq!(hyphenator.apply("Unbelievable, breakfast is ready!"), "Un-be-lie-vab-le, break-fast is ready!");
+        assert_eq!(hyphenator.apply("お兄ちゃん、letter"), "お兄ちゃん、letter");
+    }
+}
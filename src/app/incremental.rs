@@ -0,0 +1,126 @@
+use std::ops::Range;
+use std::path::Path;
+use anyhow::Result;
+use crate::{LuaTable, ParseOptions, SpanTable, TokenStream, Tokenizer, Value};
+
+/// A script kept with the byte range of each of its values, so that an edit
+/// inside one block, as an editor sends them on every keystroke, reads only
+/// that block again.
+pub struct ParsedScript {
+    pub input: String,
+    pub ast: LuaTable,
+    /// Empty after a failed edit, so the next one reads the whole script
+    spans: SpanTable,
+}
+
+impl ParsedScript {
+    pub fn parse(input: String, filename: &Path, options: &ParseOptions) -> Result<Self> {
+        let mut stream = TokenStream::new(Tokenizer::new(&input).keeping_comments().options(&options.read()), &options.read());
+        stream.spans = Some(SpanTable::new());
+        let ast = crate::parse_top_level(&mut stream).map_err(|e| crate::locate(e.into(), &input, filename))?;
+        let spans = stream.spans.unwrap_or_default();
+        Ok(ParsedScript { input, ast, spans })
+    }
+
+    /// The block holding all of `range`, with the range of its table.
+    fn block_around(&self, range: &Range<usize>, astver: Option<crate::compat::AstVersion>) -> Option<(String, Range<usize>)> {
+        let version = crate::compat::version(&self.ast, astver);
+        let blocks = self.ast.get("ast").and_then(Value::as_table)?;
+        blocks.fields()
+            .filter(|(name, _)| crate::compat::is_block_name(version, name))
+            .find_map(|(name, _)| {
+                let span = self.spans.get(&format!("ast.{}", name))?;
+                (span.start < range.start && range.end < span.end).then(|| (name.clone(), span.clone()))
+            })
+    }
+
+    /// Replaces `range` of the script with `text` and reads it again: only
+    /// the block around the edit when it stays inside one, the whole script
+    /// otherwise. On error the text is still replaced, and the tree is the
+    /// one from before the edit.
+    pub fn edit(&mut self, range: Range<usize>, text: &str, filename: &Path, options: &ParseOptions) -> Result<()> {
+        let block = self.block_around(&range, options.astver);
+        self.input.replace_range(range.clone(), text);
+        let Some((name, span)) = block else {
+            *self = Self::parse(std::mem::take(&mut self.input), filename, options)?;
+            return Ok(());
+        };
+        let end = span.end + text.len() - range.len();
+        match self.reparse_block(&name, span.start..end, options) {
+            std::result::Result::Ok((value, spans)) => {
+                if let Some(blocks) = self.ast.get_mut("ast").and_then(Value::as_table_mut) {
+                    blocks.insert(name.clone(), value);
+                }
+                self.move_spans(&name, span.end, end, spans);
+                Ok(())
+            }
+            Err(e) => {
+                self.spans.clear();
+                Err(crate::locate(e, &self.input, filename))
+            }
+        }
+    }
+
+    /// Reads the table of block `name`, now at `range`, with the range of
+    /// each of its values.
+    fn reparse_block(&self, name: &str, range: Range<usize>, options: &ParseOptions) -> Result<(Value, SpanTable)> {
+        let tokens = Tokenizer::starting_at(&self.input, range.start)
+            .keeping_comments()
+            .options(&options.read())
+            .take_while(|token| token.as_ref().map_or(true, |(_, span)| span.start < range.end));
+        let mut stream = TokenStream::new(tokens, &options.read());
+        // the ast table around the block is not read again
+        stream.max_depth = stream.max_depth.saturating_sub(1);
+        stream.spans = Some(SpanTable::new());
+        stream.path = format!("ast.{}", name);
+        let value = crate::parse_value(&mut stream)?;
+        if let Some((token, span)) = stream.peek()? {
+            let message = format!("Unexpected token after the block: {:?}", token);
+            return Err(crate::parse_error(span.start, message).into());
+        }
+        if !matches!(value, Value::Table(_)) {
+            return Err(crate::parse_error(range.start, "Expected a table for the block").into());
+        }
+        Ok((value, stream.spans.unwrap_or_default()))
+    }
+
+    /// Swaps the spans of block `name` for `block_spans`, and shifts those
+    /// after it by how much the block grew or shrank: its end moved from
+    /// `old_end` to `new_end`.
+    fn move_spans(&mut self, name: &str, old_end: usize, new_end: usize, block_spans: SpanTable) {
+        let prefix = format!("ast.{}", name);
+        let shift = |offset: usize| if offset >= old_end { offset + new_end - old_end } else { offset };
+        self.spans.retain(|path, _| !(path == &prefix || path.starts_with(&format!("{}.", prefix)) || path.starts_with(&format!("{}[", prefix))));
+        for span in self.spans.values_mut() {
+            *span = shift(span.start)..shift(span.end);
+        }
+        self.spans.extend(block_spans);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edit_block() {
+        let input = "astver = 2.0\nast = {\n\tblock_00000 = { text = { ja = { { \"a\" } } } },\n\tblock_00001 = { {\"bg\", time=1000} },\n}\n";
+        let path = Path::new("a.ast");
+        let options = ParseOptions::default();
+        let mut script = ParsedScript::parse(input.to_string(), path, &options).unwrap();
+        let at = input.find("\"a\"").unwrap();
+        script.edit(at..at + 3, "\"longer\"", path, &options).unwrap();
+        assert_eq!(crate::extract_secnario(&script.ast, None, &Default::default()).unwrap(), vec!["longer"]);
+        let time = &script.spans["ast.block_00001[0].time"];
+        assert_eq!(&script.input[time.clone()], "1000");
+        assert_eq!(script.spans["ast"].end, script.input.trim_end().len());
+
+        let at = script.input.find("1000").unwrap();
+        let error = script.edit(at..at + 4, "1000 ~", path, &options).unwrap_err();
+        assert_eq!(error.to_string(), "a.ast: Unexpected character: ~ at line 4, column 35");
+        assert!(script.spans.is_empty());
+        let at = script.input.find(" ~").unwrap();
+        script.edit(at..at + 2, "", path, &options).unwrap();
+        assert_eq!(&script.input[script.spans["ast.block_00001[0].time"].clone()], "1000");
+    }
+}
@@ -0,0 +1,107 @@
+/// Indentation of a line: tabs, spaces, or both.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Style {
+    Tabs,
+    Spaces,
+    Mixed,
+}
+
+fn leading(line: &str) -> &str {
+    &line[..line.len() - line.trim_start_matches([' ', '\t']).len()]
+}
+
+fn style(line: &str) -> Option<Style> {
+    let indent = leading(line);
+    match (indent.contains('\t'), indent.contains(' ')) {
+        _ if line.trim().is_empty() => None,
+        (true, true) => Some(Style::Mixed),
+        (true, false) => Some(Style::Tabs),
+        (false, true) => Some(Style::Spaces),
+        (false, false) => None,
+    }
+}
+
+/// Whether each line starts inside a string literal, continued from the line
+/// before with a `\` line break. Its leading whitespace is text, not indentation.
+fn continues_string(input: &str) -> Vec<bool> {
+    let mut starts = vec![false];
+    let mut quote: Option<char> = None;
+    let mut chars = input.chars();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\\' if quote.is_some() => starts.extend((chars.next() == Some('\n')).then_some(true)),
+            '"' | '\'' if quote.is_none() => quote = Some(ch),
+            _ if quote == Some(ch) => quote = None,
+            '\n' => starts.push(quote.is_some()),
+            _ => {}
+        }
+    }
+    starts
+}
+
+/// Lists lines indented with both tabs and spaces, and, when the file
+/// indents some lines with tabs and others with spaces, the lines using the
+/// less common one. Line numbers are 1-based.
+pub fn check(input: &str) -> Vec<(usize, String)> {
+    let in_string = continues_string(input);
+    let styles: Vec<(usize, Style)> = input.lines().enumerate()
+        .filter(|(index, _)| !in_string.get(*index).copied().unwrap_or(false))
+        .filter_map(|(index, line)| Some((index + 1, style(line)?)))
+        .collect();
+    let tabs = styles.iter().filter(|(_, style)| *style == Style::Tabs).count();
+    let spaces = styles.iter().filter(|(_, style)| *style == Style::Spaces).count();
+    let minority = if tabs >= spaces { Style::Spaces } else { Style::Tabs };
+
+    let mut problems = Vec::new();
+    for (line, style) in styles {
+        match style {
+            Style::Mixed => problems.push((line, "indented with both tabs and spaces".to_string())),
+            _ if tabs > 0 && spaces > 0 && style == minority => {
+                let (used, usual) = if style == Style::Tabs { ("tabs", "spaces") } else { ("spaces", "tabs") };
+                problems.push((line, format!("indented with {} where the file mostly uses {}", used, usual)));
+            }
+            _ => {}
+        }
+    }
+    problems
+}
+
+/// Rewrites every indentation as tabs, counting a tab as `tab_width`
+/// columns and rounding to the nearest tab. Lines continuing a string
+/// literal are left alone.
+pub fn normalize(input: &str, tab_width: usize) -> String {
+    let tab_width = tab_width.max(1);
+    let in_string = continues_string(input);
+    let mut output = String::with_capacity(input.len());
+    for (index, line) in input.split_inclusive('\n').enumerate() {
+        let indent = leading(line);
+        if in_string.get(index).copied().unwrap_or(false) || line.trim().is_empty() {
+            output.push_str(line);
+            continue;
+        }
+        let columns = indent.chars().fold(0, |column, ch| match ch {
+            '\t' => (column / tab_width + 1) * tab_width,
+            _ => column + 1,
+        });
+        output.push_str(&"\t".repeat((columns + tab_width / 2) / tab_width));
+        output.push_str(&line[indent.len()..]);
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_indentation() {
+        let input = "ast = {\n\tblock_00000 = {\n\t  line = 18,\n\t\ttext = \"a\\\n    b\",\n    },\n\tblock_00001 = {},\n}\n";
+        assert_eq!(check(input), vec![
+            (3, "indented with both tabs and spaces".to_string()),
+            (6, "indented with spaces where the file mostly uses tabs".to_string()),
+        ]);
+        let normalized = normalize(input, 4);
+        assert_eq!(normalized, "ast = {\n\tblock_00000 = {\n\t\tline = 18,\n\t\ttext = \"a\\\n    b\",\n\t},\n\tblock_00001 = {},\n}\n");
+        assert!(check(&normalized).is_empty());
+    }
+}
@@ -0,0 +1,69 @@
+use std::collections::BTreeMap;
+use anyhow::Result;
+use serde::Serialize;
+use crate::{LuaTable, Value};
+
+/// One line with its text in every language channel that has it.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct LangEntry {
+    pub block: String,
+    #[serde(flatten)]
+    pub texts: BTreeMap<String, String>,
+}
+
+/// Every language channel under `text` (ja, en, zht, ...), in the order
+/// they first appear. `vo` holds voices, not text.
+pub fn channels(ast: &LuaTable, astver: Option<crate::compat::AstVersion>) -> Vec<String> {
+    let mut found: Vec<String> = Vec::new();
+    for (_, block) in crate::iter_blocks(ast, astver) {
+        let channels = block.get("text").and_then(Value::as_table).into_iter().flat_map(LuaTable::fields);
+        for (channel, _) in channels.filter(|(channel, value)| *channel != "vo" && value.is_table()) {
+            if !found.contains(channel) {
+                found.push(channel.clone());
+            }
+        }
+    }
+    found
+}
+
+/// The lines of every channel side by side: the n-th line of a block in
+/// one channel goes with the n-th line of that block in the others. A
+/// channel with fewer lines in a block is left out of the extra entries.
+pub fn side_by_side(ast: &LuaTable, astver: Option<crate::compat::AstVersion>) -> Result<Vec<LangEntry>> {
+    let mut by_channel = Vec::new();
+    for channel in channels(ast, astver) {
+        let blocks = crate::extract_blocks(ast, astver, &channel)?;
+        by_channel.push((channel, blocks));
+    }
+    let Some((_, first)) = by_channel.first() else {
+        return Ok(Vec::new());
+    };
+    let mut entries = Vec::new();
+    for (index, block) in first.iter().enumerate() {
+        let rows = by_channel.iter().map(|(_, blocks)| blocks[index].texts.len()).max().unwrap_or(0);
+        for row in 0..rows {
+            let texts = by_channel.iter()
+                .filter_map(|(channel, blocks)| Some((channel.clone(), blocks[index].texts.get(row)?.1.clone())))
+                .collect();
+            entries.push(LangEntry { block: block.name.clone(), texts });
+        }
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_side_by_side() {
+        let input = r#"ast = {
+            block_00000 = { text = { vo = { {"vo", file="a"} }, ja = { { "「お兄」" }, { "朝だ。" } }, en = { { "\"Bro\"" } }, zht = { { "「哥」" }, { "早上了。" } } } },
+            block_00001 = { {"bg"} },
+        }"#;
+        let ast = crate::parse_tokens(&crate::tokenize(input).unwrap()).unwrap();
+        assert_eq!(channels(&ast, None), vec!["ja", "en", "zht"]);
+        let yaml = serde_yaml::to_string(&side_by_side(&ast, None).unwrap()).unwrap();
+        assert_eq!(yaml, "- block: block_00000\n  en: '\"Bro\"'\n  ja: 「お兄」\n  zht: 「哥」\n- block: block_00000\n  ja: 朝だ。\n  zht: 早上了。\n");
+    }
+}
@@ -0,0 +1,144 @@
+use clap::{Args, ValueEnum};
+use unicode_width::UnicodeWidthStr;
+
+/// How the length of a line is measured against `--max-length`.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq)]
+pub enum WidthMode {
+    /// Count unicode scalar values
+    Chars,
+    /// Count display cells, full-width characters take 2
+    #[default]
+    Cells,
+    /// Like `cells`, but ambiguous-width characters (…, ―, ○) also take 2, as in CJK fonts
+    Cjk,
+}
+
+#[derive(Args, Debug)]
+pub struct LengthOptions {
+    /// Warn about translated lines longer than this
+    #[arg(long)]
+    pub max_length: Option<usize>,
+    /// How line length is measured
+    #[arg(long, value_enum, default_value_t)]
+    pub width_mode: WidthMode,
+    /// Textbox width in display cells, used to wrap lines for --max-rows
+    #[arg(long, default_value_t = 48)]
+    pub columns: usize,
+    /// Warn about translated lines that wrap to more rows than one page holds
+    #[arg(long)]
+    pub max_rows: Option<usize>,
+}
+
+impl Default for LengthOptions {
+    /// The defaults of the command line.
+    fn default() -> Self {
+        LengthOptions { max_length: None, width_mode: WidthMode::default(), columns: 48, max_rows: None }
+    }
+}
+
+pub fn text_width(text: &str, mode: WidthMode) -> usize {
+    match mode {
+        WidthMode::Chars => text.chars().count(),
+        WidthMode::Cells => text.width(),
+        WidthMode::Cjk => text.width_cjk(),
+    }
+}
+
+/// Breaks `text` into rows of at most `columns` cells the way the engine's
+/// naive wrapper does: at any character, and at explicit newlines.
+pub fn wrap(text: &str, columns: usize, mode: WidthMode) -> Vec<String> {
+    let mut rows = Vec::new();
+    for line in text.split('\n') {
+        let mut row = String::new();
+        let mut width = 0;
+        for ch in line.chars() {
+            let ch_width = text_width(ch.encode_utf8(&mut [0; 4]), mode);
+            if width + ch_width > columns && !row.is_empty() {
+                rows.push(std::mem::take(&mut row));
+                width = 0;
+            }
+            row.push(ch);
+            width += ch_width;
+        }
+        rows.push(row);
+    }
+    rows
+}
+
+/// Returns `(index, width)` of every line exceeding the configured limit.
+pub fn check_lengths(texts: &[String], options: &LengthOptions) -> Vec<(usize, usize)> {
+    let Some(max) = options.max_length else {
+        return Vec::new();
+    };
+    texts.iter()
+        .enumerate()
+        .map(|(i, text)| (i, text_width(text, options.width_mode)))
+        .filter(|(_, width)| *width > max)
+        .collect()
+}
+
+/// Returns `(index, rows)` of every line that does not fit in one page
+/// once wrapped to the textbox width.
+pub fn check_rows(texts: &[String], options: &LengthOptions) -> Vec<(usize, usize)> {
+    let Some(max_rows) = options.max_rows else {
+        return Vec::new();
+    };
+    texts.iter()
+        .enumerate()
+        .map(|(i, text)| (i, wrap(text, options.columns, options.width_mode).len()))
+        .filter(|(_, rows)| *rows > max_rows)
+        .collect()
+}
+
+/// Cuts `text` into pages of at most `max_rows` rows once wrapped to the
+/// textbox width. A newline or space falling on a page break is dropped.
+pub fn split_pages(text: &str, max_rows: usize, options: &LengthOptions) -> Vec<String> {
+    let max_rows = max_rows.max(1);
+    let mut pages = Vec::new();
+    let mut page = String::new();
+    let mut rows = 0;
+    for (index, line) in text.split('\n').enumerate() {
+        for (part, row) in wrap(line, options.columns, options.width_mode).into_iter().enumerate() {
+            if rows == max_rows {
+                pages.push(std::mem::take(&mut page).trim_end().to_string());
+                rows = 0;
+            } else if part == 0 && index > 0 {
+                page.push('\n');
+            }
+            page.push_str(if rows == 0 { row.trim_start() } else { &row });
+            rows += 1;
+        }
+    }
+    pages.push(page);
+    pages
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_width() {
+        assert_eq!(text_width("abc", WidthMode::Cells), 3);
+        assert_eq!(text_width("お兄", WidthMode::Chars), 2);
+        assert_eq!(text_width("お兄", WidthMode::Cells), 4);
+        assert_eq!(text_width("……", WidthMode::Cells), 2);
+        assert_eq!(text_width("……", WidthMode::Cjk), 4);
+    }
+
+    #[test]
+    fn test_wrap() {
+        assert_eq!(wrap("お兄、あさー", 6, WidthMode::Cells), vec!["お兄、", "あさー"]);
+        assert_eq!(wrap("ab\ncd", 6, WidthMode::Cells), vec!["ab", "cd"]);
+        assert_eq!(wrap("", 6, WidthMode::Cells), vec![""]);
+    }
+
+    #[test]
+    fn test_check_rows() {
+        let options = LengthOptions { max_length: None, width_mode: WidthMode::Cells, columns: 4, max_rows: Some(2) };
+        let texts = vec!["お兄".to_string(), "お兄、あさー".to_string(), "ab\ncd\nef".to_string()];
+        assert_eq!(check_rows(&texts, &options), vec![(1, 3), (2, 3)]);
+        assert_eq!(split_pages("お兄、あさー", 2, &options), vec!["お兄、あ", "さー"]);
+        assert_eq!(split_pages("ab\ncd\nef", 2, &options), vec!["ab\ncd", "ef"]);
+    }
+}
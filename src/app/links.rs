@@ -0,0 +1,74 @@
+use std::collections::HashMap;
+use crate::{LuaTable, Value};
+
+/// `(block, linknext target)` of every block, in script order.
+fn linknexts(ast: &LuaTable, astver: Option<crate::compat::AstVersion>) -> Vec<(&String, Option<&String>)> {
+    crate::iter_blocks(ast, astver)
+        .map(|(block, table)| (block, table.get("linknext").and_then(Value::as_string)))
+        .collect()
+}
+
+/// Checks that `linknext` chains run forward through the script: every
+/// target exists, no block links to itself, and unless `allow_cycles` no
+/// block links back to an earlier one, which is how loops are made.
+pub fn check_links(ast: &LuaTable, astver: Option<crate::compat::AstVersion>, allow_cycles: bool) -> Vec<String> {
+    let links = linknexts(ast, astver);
+    let position: HashMap<&String, usize> = links.iter().enumerate().map(|(i, (block, _))| (*block, i)).collect();
+    let next: HashMap<&String, &String> = links.iter().filter_map(|(block, target)| Some((*block, (*target)?))).collect();
+    let mut problems = Vec::new();
+
+    for (index, (block, target)) in links.iter().enumerate() {
+        let Some(target) = target else {
+            continue;
+        };
+        match position.get(target) {
+            None => problems.push(format!("{}: linknext {} does not exist", block, target)),
+            Some(_) if target == block => problems.push(format!("{}: links to itself", block)),
+            Some(&at) if at < index && !allow_cycles => {
+                // walk the chain from the target to see whether it comes back here
+                let mut chain = vec![target.as_str()];
+                let mut current = *target;
+                while current != *block && chain.len() <= links.len() {
+                    let Some(following) = next.get(current) else {
+                        break;
+                    };
+                    current = following;
+                    chain.push(current);
+                }
+                if current == *block {
+                    problems.push(format!("{}: linknext {} closes a loop: {} -> {}", block, target, block, chain.join(" -> ")));
+                } else {
+                    problems.push(format!("{}: linknext {} points back to an earlier block", block, target));
+                }
+            }
+            Some(_) => {}
+        }
+    }
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_links() {
+        let input = r#"ast = {
+            block_00000 = { linknext = "block_00001" },
+            block_00001 = { linknext = "block_00002" },
+            block_00002 = { linknext = "block_00000" },
+            block_00003 = { linknext = "block_00003" },
+            block_00004 = { linknext = "block_00099" },
+            block_00005 = { linknext = "block_00004" },
+        }
+        "#;
+        let ast = crate::parse_tokens(&crate::tokenize(input).unwrap()).unwrap();
+        assert_eq!(check_links(&ast, None, false), vec![
+            "block_00002: linknext block_00000 closes a loop: block_00002 -> block_00000 -> block_00001 -> block_00002",
+            "block_00003: links to itself",
+            "block_00004: linknext block_00099 does not exist",
+            "block_00005: linknext block_00004 points back to an earlier block",
+        ]);
+        assert_eq!(check_links(&ast, None, true).len(), 2);
+    }
+}
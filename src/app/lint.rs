@@ -0,0 +1,147 @@
+use std::collections::BTreeSet;
+use clap::{Args, ValueEnum};
+
+/// Words meaning a number or an ordinal in English translations.
+const NUMBER_WORDS: &[&str] = &[
+    "zero", "one", "two", "three", "four", "five", "six", "seven", "eight", "nine", "ten",
+    "eleven", "twelve", "thirteen", "fourteen", "fifteen", "sixteen", "seventeen", "eighteen", "nineteen",
+    "twenty", "thirty", "forty", "fifty", "sixty", "seventy", "eighty", "ninety", "hundred", "thousand", "million",
+    "first", "second", "third", "fourth", "fifth", "sixth", "seventh", "eighth", "ninth", "tenth",
+];
+
+/// Typography conventions of the translation's language.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum Typography {
+    /// English: no space before any punctuation
+    En,
+    /// French: a space before ! ? : ; is expected
+    Fr,
+}
+
+/// Optional checks run on the translation before it is merged.
+#[derive(Args, Debug, Default)]
+pub struct LintOptions {
+    /// Warn when numbers are written as numerals on one side and spelled out on the other
+    #[arg(long)]
+    pub lint_numbers: bool,
+    /// Warn about double spaces and spacing around punctuation, following this language's rules
+    #[arg(long, value_enum)]
+    pub lint_typography: Option<Typography>,
+    /// Warn about lines mixing right-to-left and left-to-right text without direction marks (see --direction-marks)
+    #[arg(long)]
+    pub lint_bidi: bool,
+}
+
+/// Runs of digits, full-width digits folded to ASCII and thousands separators dropped.
+fn numerals(text: &str) -> BTreeSet<String> {
+    let mut found = BTreeSet::new();
+    let mut current = String::new();
+    let mut chars = text.chars().peekable();
+    while let Some(ch) = chars.next() {
+        let digit = match ch {
+            '0'..='9' => Some(ch),
+            '０'..='９' => char::from_u32(ch as u32 - '０' as u32 + '0' as u32),
+            _ => None,
+        };
+        match digit {
+            Some(digit) => current.push(digit),
+            // 1,000 is the same number as 1000
+            None if (ch == ',' || ch == '，') && !current.is_empty() && chars.peek().is_some_and(|c| c.is_ascii_digit()) => {}
+            None if !current.is_empty() => {
+                found.insert(std::mem::take(&mut current));
+            }
+            None => {}
+        }
+    }
+    if !current.is_empty() {
+        found.insert(current);
+    }
+    found
+}
+
+fn has_number_word(text: &str) -> bool {
+    text.split(|c: char| !c.is_alphabetic())
+        .any(|word| NUMBER_WORDS.contains(&word.to_lowercase().as_str()))
+}
+
+/// Finds entries whose translation renders numbers differently from the
+/// source: numerals spelled out, numerals dropped, or numerals introduced
+/// where the source has none.
+pub fn check_numbers(source: &[String], translation: &[String]) -> Vec<(usize, String)> {
+    let mut problems = Vec::new();
+    for (index, (source, translated)) in source.iter().zip(translation).enumerate() {
+        let source_numbers = numerals(source);
+        let translated_numbers = numerals(translated);
+        for number in source_numbers.difference(&translated_numbers) {
+            if has_number_word(translated) {
+                problems.push((index, format!("{} is a numeral in the source but spelled out in the translation", number)));
+            } else {
+                problems.push((index, format!("{} from the source is missing in the translation", number)));
+            }
+        }
+        if source_numbers.is_empty() {
+            for number in translated_numbers {
+                problems.push((index, format!("{} is a numeral in the translation but not in the source", number)));
+            }
+        }
+    }
+    problems
+}
+
+/// Finds spacing mistakes in one translated line: double spaces, a space
+/// before punctuation and a missing space after the end of a sentence.
+pub fn check_typography(text: &str, language: Typography) -> Vec<String> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut problems = Vec::new();
+    if text.contains("  ") {
+        problems.push("double space".to_string());
+    }
+    for (i, &ch) in chars.iter().enumerate() {
+        let before = i.checked_sub(1).map(|j| chars[j]);
+        let after = chars.get(i + 1).copied();
+        // "..." is an ellipsis, not a full stop
+        let ellipsis = ch == '.' && (before == Some('.') || after == Some('.'));
+        if !",.!?:;".contains(ch) || ellipsis {
+            continue;
+        }
+        let spaced_in_language = language == Typography::Fr && "!?:;".contains(ch);
+        if before == Some(' ') && !spaced_in_language {
+            problems.push(format!("space before '{}'", ch));
+        }
+        // lowercase after a full stop is usually a trailing "...and", and a
+        // lone capital before it is an initial as in U.S.A
+        let initial = i >= 1 && chars[i - 1].is_uppercase() && (i < 2 || !chars[i - 2].is_alphabetic());
+        if ".!?".contains(ch) && after.is_some_and(char::is_uppercase) && !initial {
+            problems.push(format!("missing space after '{}'", ch));
+        }
+    }
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_typography() {
+        assert!(check_typography("Good morning, big brother... Mmh.", Typography::En).is_empty());
+        assert_eq!(check_typography("Wait  here .Then go!Now", Typography::En), vec![
+            "double space".to_string(),
+            "space before '.'".to_string(),
+            "missing space after '.'".to_string(),
+            "missing space after '!'".to_string(),
+        ]);
+        assert!(check_typography("Quoi ? Le U.S.A. Well...and", Typography::Fr).is_empty());
+    }
+
+    #[test]
+    fn test_check_numbers() {
+        let source: Vec<String> = ["３時に", "1,000円", "三人", "一緒に"].iter().map(|s| s.to_string()).collect();
+        let translation: Vec<String> = ["At three.", "1000 yen", "3 people", "Together"].iter().map(|s| s.to_string()).collect();
+        let problems = check_numbers(&source, &translation);
+        assert_eq!(problems, vec![
+            (0, "3 is a numeral in the source but spelled out in the translation".to_string()),
+            (2, "3 is a numeral in the translation but not in the source".to_string()),
+        ]);
+    }
+}
@@ -0,0 +1,139 @@
+use std::{
+    cell::RefCell,
+    fmt::Display,
+    fs::File,
+    io::Write,
+    path::Path,
+    sync::{Mutex, OnceLock},
+};
+use anyhow::Result;
+
+/// Serializes output so groups of lines from different workers never interleave.
+static OUTPUT: Mutex<()> = Mutex::new(());
+static LOG_FILE: OnceLock<Mutex<File>> = OnceLock::new();
+
+thread_local! {
+    /// Pending messages of the file the current thread is working on.
+    static SCOPE: RefCell<Option<Vec<String>>> = const { RefCell::new(None) };
+}
+
+/// Mirrors every message into `path` in addition to stderr.
+pub fn init(log_file: Option<&Path>) -> Result<()> {
+    if let Some(path) = log_file {
+        let file = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+        let _ = LOG_FILE.set(Mutex::new(file));
+    }
+    Ok(())
+}
+
+fn write_lines(lines: &[String]) {
+    let _guard = OUTPUT.lock().unwrap();
+    let mut stderr = std::io::stderr().lock();
+    for line in lines {
+        let _ = writeln!(stderr, "{}", line);
+    }
+    if let Some(file) = LOG_FILE.get() {
+        let mut file = file.lock().unwrap();
+        for line in lines {
+            let _ = writeln!(file, "{}", line);
+        }
+    }
+}
+
+/// Reports a diagnostic. Inside [`scoped`] it is held back and printed with
+/// the file's other messages once the file is done. Messages name their file
+/// themselves.
+pub fn warn(message: impl Display) {
+    let message = message.to_string();
+    let pending = SCOPE.with(|scope| match scope.borrow_mut().as_mut() {
+        Some(buffer) => {
+            buffer.push(message);
+            None
+        }
+        None => Some(message),
+    });
+    if let Some(message) = pending {
+        write_lines(&[message]);
+    }
+}
+
+/// Puts back the scope that was active before, also when the work inside
+/// panics, printing what was held back unless it is being dropped.
+struct ScopeGuard {
+    outer: Option<Vec<String>>,
+    print: bool,
+}
+
+impl ScopeGuard {
+    fn enter(print: bool) -> Self {
+        let outer = SCOPE.with(|scope| scope.replace(Some(Vec::new())));
+        ScopeGuard { outer, print }
+    }
+}
+
+impl Drop for ScopeGuard {
+    fn drop(&mut self) {
+        let buffer = SCOPE.with(|scope| scope.replace(self.outer.take()));
+        if let (true, Some(buffer)) = (self.print, buffer) {
+            write_lines(&buffer);
+        }
+    }
+}
+
+/// Runs `f` with the messages it reports grouped together, so those of
+/// workers on other files do not interleave with them.
+pub fn scoped<T>(f: impl FnOnce() -> T) -> T {
+    let _guard = ScopeGuard::enter(true);
+    f()
+}
+
+/// Runs `f` with every message it reports dropped, for trial runs whose
+/// diagnostics would only repeat the real ones.
+pub fn silenced<T>(f: impl FnOnce() -> T) -> T {
+    let _guard = ScopeGuard::enter(false);
+    f()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scopes() {
+        let dir = crate::test_dir("logging");
+        let log = dir.join("log.txt");
+        init(Some(&log)).unwrap();
+        let ours = || {
+            let text = std::fs::read_to_string(&log).unwrap();
+            text.lines().filter(|line| line.starts_with("logging-test")).map(str::to_string).collect::<Vec<_>>()
+        };
+
+        std::thread::scope(|s| {
+            for file in 0..4 {
+                s.spawn(move || scoped(|| {
+                    for line in 0..20 {
+                        warn(format!("logging-test-{}.ast: warning {}", file, line));
+                        std::thread::yield_now();
+                    }
+                }));
+            }
+        });
+        let lines = ours();
+        assert_eq!(lines.len(), 80);
+        for group in lines.chunks(20) {
+            let file = group[0].split(':').next().unwrap();
+            let expected: Vec<_> = (0..20).map(|line| format!("{}: warning {}", file, line)).collect();
+            assert_eq!(group, expected);
+        }
+
+        scoped(|| {
+            warn("logging-test-held.ast: held back");
+            assert_eq!(ours().len(), 80);
+            silenced(|| warn("logging-test-silenced.ast: dropped"));
+        });
+        let lines = ours();
+        assert_eq!(lines.len(), 81);
+        assert_eq!(lines[80], "logging-test-held.ast: held back");
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,77 @@
+use std::collections::HashMap;
+use std::path::Path;
+use anyhow::Result;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+pub struct Location {
+    pub block: String,
+    /// 1-based line of the literal in the merged script
+    pub line: usize,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, PartialEq)]
+pub struct MappingEntry {
+    /// Position of the entry in the translation file
+    pub index: usize,
+    pub source: String,
+    pub translation: String,
+    /// Every literal the entry was written over. Merge replaces by text, so
+    /// entries with the same source share locations and the last one wins.
+    pub locations: Vec<Location>,
+}
+
+/// Written by `merge --emit-mapping`: where each translation entry ended up.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, PartialEq)]
+pub struct Mapping {
+    pub script: String,
+    pub translation: String,
+    pub entries: Vec<MappingEntry>,
+}
+
+/// Locates the literals of `source` in `script`, the source text before
+/// merging. `line_offset` counts the lines merge put in front of it.
+pub fn build(script: &str, line_offset: usize, lang: &str, parse: &crate::ParseOptions, source: &[String], translation: &[String]) -> Result<Vec<MappingEntry>> {
+    let mut by_text: HashMap<String, Vec<Location>> = HashMap::new();
+    let mut line = 1;
+    let mut counted = 0;
+    for (block, text, span) in crate::text_scan::literals(script, lang, parse)? {
+        line += script[counted..span.start].matches('\n').count();
+        counted = span.start;
+        by_text.entry(text).or_default().push(Location { block, line: line + line_offset });
+    }
+    let entries = source.iter().zip(translation).enumerate()
+        .map(|(index, (source, translation))| MappingEntry {
+            index,
+            source: source.clone(),
+            translation: translation.clone(),
+            locations: by_text.get(source).cloned().unwrap_or_default(),
+        })
+        .collect();
+    Ok(entries)
+}
+
+pub fn write(path: &Path, mapping: &Mapping) -> Result<()> {
+    std::fs::write(path, serde_json::to_string_pretty(mapping)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_mapping() {
+        let script = "ast = {\n\tblock_00000 = {\n\t\ttext = { ja = { { \"「お兄」\" } } },\n\t},\n\tblock_00001 = {\n\t\ttext = { ja = { { \"……\" }, { \"「お兄」\" } } },\n\t},\n}\n";
+        let source: Vec<String> = ["「お兄」", "……", "「お兄」"].iter().map(|s| s.to_string()).collect();
+        let translation: Vec<String> = ["\"Bro\"", "...", "\"Brother\""].iter().map(|s| s.to_string()).collect();
+        let entries = build(script, 1, "ja", &crate::ParseOptions::default(), &source, &translation).unwrap();
+        assert_eq!(entries[0].locations, vec![
+            Location { block: "block_00000".to_string(), line: 4 },
+            Location { block: "block_00001".to_string(), line: 7 },
+        ]);
+        assert_eq!(entries[1].locations, vec![Location { block: "block_00001".to_string(), line: 7 }]);
+        assert_eq!(entries[2].locations, entries[0].locations);
+    }
+}
@@ -0,0 +1,106 @@
+use crate::length::{self, LengthOptions};
+use crate::LuaTable;
+use crate::Value;
+
+/// A translated line moved partly onto pages of its own.
+#[derive(Debug, PartialEq)]
+pub struct Split {
+    pub block: String,
+    /// The line as translated
+    pub text: String,
+    /// How many pages it now takes
+    pub pages: usize,
+}
+
+/// A page holding `text` in place of the page's lines, with its speaker and
+/// commands (`{"rt2"}`) cloned so it shows the same way.
+fn continuation(page: &LuaTable, text: String) -> LuaTable {
+    let mut clone = LuaTable::new();
+    for entry in &page.array {
+        match entry {
+            Value::String(_) => {}
+            entry => clone.push(entry.clone()),
+        }
+    }
+    // the text goes where the page's first line stood
+    let at = page.array.iter().position(|entry| matches!(entry, Value::String(_))).unwrap_or(0);
+    clone.array.insert(at.min(clone.array.len()), Value::String(text));
+    for (key, value) in page.fields() {
+        clone.insert(key.clone(), value.clone());
+    }
+    clone
+}
+
+/// Moves what does not fit of each translated line that wraps to more than
+/// `--max-rows` rows onto new pages right after its own, and returns every
+/// split so they can be reviewed.
+pub fn split_long_lines(ast: &mut LuaTable, astver: Option<crate::compat::AstVersion>, options: &LengthOptions) -> Vec<Split> {
+    let Some(max_rows) = options.max_rows else {
+        return Vec::new();
+    };
+    let version = crate::compat::version(ast, astver);
+    let mut splits = Vec::new();
+    let Some(blocks) = ast.get_mut("ast").and_then(Value::as_table_mut) else {
+        return splits;
+    };
+    for (name, block) in blocks.fields_mut() {
+        if !crate::compat::is_block_name(version, name) {
+            continue;
+        }
+        let ja = block.as_table_mut()
+            .and_then(|block| block.get_mut("text"))
+            .and_then(Value::as_table_mut)
+            .and_then(|text| text.get_mut("ja"))
+            .and_then(Value::as_table_mut);
+        let Some(ja) = ja else {
+            continue;
+        };
+        let mut index = 0;
+        while index < ja.array.len() {
+            let mut continued = Vec::new();
+            let mut rest = Vec::new();
+            if let Some(page) = ja.array[index].as_table_mut() {
+                for entry in page.array.iter_mut() {
+                    let Some(text) = entry.as_string_mut() else {
+                        continue;
+                    };
+                    let mut pages = length::split_pages(text, max_rows, options);
+                    if pages.len() < 2 {
+                        continue;
+                    }
+                    splits.push(Split { block: name.clone(), text: text.clone(), pages: pages.len() });
+                    *text = pages.remove(0);
+                    rest.extend(pages);
+                }
+                let page = &*page;
+                continued = rest.into_iter().map(|text| Value::Table(continuation(page, text))).collect();
+            }
+            index += 1;
+            let added = continued.len();
+            ja.array.splice(index..index, continued);
+            index += added;
+        }
+    }
+    splits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_long_lines() {
+        let input = "astver = 2.0\nast = {\n\tblock_00000 = {\n\t\ttext = { ja = { { name = { \"妃愛\" }, \"abcd efgh ijkl\", {\"rt2\"} }, { \"ok\" } } },\n\t},\n}\n";
+        let mut ast = crate::parse_tokens(&crate::tokenize(input).unwrap()).unwrap();
+        let options = LengthOptions { max_length: None, width_mode: length::WidthMode::Cells, columns: 5, max_rows: Some(2) };
+        let splits = split_long_lines(&mut ast, None, &options);
+        assert_eq!(splits, vec![Split { block: "block_00000".to_string(), text: "abcd efgh ijkl".to_string(), pages: 2 }]);
+        let blocks = crate::extract_blocks(&ast, None, "ja").unwrap();
+        let texts: Vec<&str> = blocks[0].texts.iter().map(|(_, text, _)| text.as_str()).collect();
+        assert_eq!(texts, vec!["abcd efgh", "ijkl", "ok"]);
+        let ja = ast["ast"].as_table().unwrap().get("block_00000").unwrap().as_table().unwrap()["text"].as_table().unwrap()["ja"].as_table().unwrap();
+        let continued = ja.array[1].as_table().unwrap();
+        assert!(continued.contains_key("name"));
+        assert_eq!(crate::command_name(&continued.array[1]), Some("rt2"));
+    }
+}
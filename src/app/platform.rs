@@ -0,0 +1,57 @@
+use std::path::{Path, PathBuf};
+use anyhow::Result;
+
+/// Switches the Windows console to UTF-8 so Japanese file names and
+/// messages are not mangled by the legacy code page. Does nothing elsewhere.
+pub fn init_console() {
+    #[cfg(windows)]
+    {
+        const CP_UTF8: u32 = 65001;
+        #[link(name = "kernel32")]
+        extern "system" {
+            fn SetConsoleOutputCP(code_page: u32) -> i32;
+        }
+        // fails harmlessly when output is redirected to a file
+        unsafe {
+            SetConsoleOutputCP(CP_UTF8);
+        }
+    }
+}
+
+/// `C:\games\x` -> `\\?\C:\games\x` and `\\server\share` -> `\\?\UNC\server\share`,
+/// the verbatim forms that are not limited to MAX_PATH.
+fn verbatim(absolute: &str) -> String {
+    if absolute.starts_with(r"\\?\") {
+        absolute.to_string()
+    } else if let Some(unc) = absolute.strip_prefix(r"\\") {
+        format!(r"\\?\UNC\{}", unc)
+    } else {
+        format!(r"\\?\{}", absolute)
+    }
+}
+
+/// On Windows, makes `path` absolute and verbatim so files deeper than
+/// MAX_PATH under game installs can still be opened. Other platforms get the
+/// path back unchanged.
+pub fn long_path(path: &Path) -> Result<PathBuf> {
+    if cfg!(windows) {
+        // absolute() also resolves `.` and `..`, which verbatim paths do not allow
+        let absolute = std::path::absolute(path)?;
+        // a name that is not valid unicode is left as it is rather than mangled
+        Ok(absolute.to_str().map_or(absolute.clone(), |absolute| PathBuf::from(verbatim(absolute))))
+    } else {
+        Ok(path.to_path_buf())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verbatim() {
+        assert_eq!(verbatim(r"C:\games\x"), r"\\?\C:\games\x");
+        assert_eq!(verbatim(r"\\nas\games\x"), r"\\?\UNC\nas\games\x");
+        assert_eq!(verbatim(r"\\?\C:\x"), r"\\?\C:\x");
+    }
+}
@@ -0,0 +1,96 @@
+use std::path::PathBuf;
+use anyhow::Result;
+use clap::Args;
+use crate::length::{self, WidthMode};
+
+#[derive(Args, Debug)]
+pub struct PreviewOptions {
+    /// Textbox width in display cells
+    #[arg(long, default_value_t = 48)]
+    columns: usize,
+    /// Rows visible in one page of the textbox
+    #[arg(long, default_value_t = 3)]
+    rows: usize,
+    /// How character widths are measured
+    #[arg(long, value_enum, default_value_t)]
+    width_mode: WidthMode,
+    /// Write an HTML page instead of printing to the terminal
+    #[arg(long)]
+    html: Option<PathBuf>,
+}
+
+struct Layout<'a> {
+    text: &'a str,
+    rows: Vec<String>,
+}
+
+impl Layout<'_> {
+    fn overflows(&self, options: &PreviewOptions) -> bool {
+        self.rows.len() > options.rows
+    }
+}
+
+fn pad(row: &str, options: &PreviewOptions) -> String {
+    let width = length::text_width(row, options.width_mode);
+    format!("{}{}", row, " ".repeat(options.columns.saturating_sub(width)))
+}
+
+fn render_terminal(layouts: &[Layout], options: &PreviewOptions) {
+    let border = format!("+{}+", "-".repeat(options.columns));
+    for (index, layout) in layouts.iter().enumerate() {
+        if layout.overflows(options) {
+            println!("#{} OVERFLOW ({} rows > {})", index, layout.rows.len(), options.rows);
+        } else {
+            println!("#{}", index);
+        }
+        println!("{}", border);
+        for (row_index, row) in layout.rows.iter().enumerate() {
+            // rows past the page are drawn outside the box
+            let edge = if row_index < options.rows { '|' } else { '!' };
+            println!("{}{}{}", edge, pad(row, options), edge);
+        }
+        println!("{}", border);
+    }
+}
+
+pub fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn render_html(layouts: &[Layout], options: &PreviewOptions) -> String {
+    let mut html = String::from("<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><style>\n");
+    html.push_str(&format!(
+        ".box {{ font-family: monospace; width: {}ch; border: 1px solid #444; margin: 8px 0; white-space: pre; }}\n",
+        options.columns,
+    ));
+    html.push_str(".overflow { border-color: #c00; background: #fee; }\n.hidden { color: #c00; }\n");
+    html.push_str("</style></head><body>\n");
+    for (index, layout) in layouts.iter().enumerate() {
+        let class = if layout.overflows(options) { "box overflow" } else { "box" };
+        html.push_str(&format!("<div class=\"{}\" title=\"#{} {}\">", class, index, escape_html(layout.text)));
+        for (row_index, row) in layout.rows.iter().enumerate() {
+            let row = escape_html(row);
+            if row_index < options.rows {
+                html.push_str(&format!("<div>{}</div>", row));
+            } else {
+                html.push_str(&format!("<div class=\"hidden\">{}</div>", row));
+            }
+        }
+        html.push_str("</div>\n");
+    }
+    html.push_str("</body></html>\n");
+    html
+}
+
+pub fn preview(texts: &[String], options: &PreviewOptions) -> Result<()> {
+    let layouts: Vec<Layout> = texts.iter()
+        .map(|text| Layout { text, rows: length::wrap(text, options.columns, options.width_mode) })
+        .collect();
+    match &options.html {
+        Some(path) => std::fs::write(path, render_html(&layouts, options))?,
+        None => render_terminal(&layouts, options),
+    }
+    let overflowing = layouts.iter().filter(|layout| layout.overflows(options)).count();
+    println!("{} of {} lines overflow the textbox", overflowing, layouts.len());
+    Ok(())
+}
@@ -0,0 +1,198 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+};
+use anyhow::{Result, anyhow};
+use crate::{
+    EmptyScript, ExtractOptions, LuaTable, MergeOptions, ParseOptions, ScenarioOptions, WriteOptions,
+    batch::Outcome, length::LengthOptions, routes::BlockGraph, stats::{FileStats, ProjectStats},
+};
+
+/// A directory of scripts handled as a whole, as `batch` and `stats` do,
+/// for frontends that would otherwise walk it and collect the errors
+/// themselves. Parsed scripts are kept, so asking for the stats and then
+/// the graph reads each script once.
+pub struct Project {
+    root: PathBuf,
+    pub parse: ParseOptions,
+    pub write: WriteOptions,
+    pub scenario: ScenarioOptions,
+    asts: HashMap<PathBuf, LuaTable>,
+}
+
+/// Runs `f` over every script the way `batch` does, and sorts the scripts
+/// by how it went.
+fn each(scripts: Vec<PathBuf>, mut f: impl FnMut(&Path) -> Result<()>) -> Outcome {
+    let mut outcome = Outcome::default();
+    for script in scripts {
+        let result = crate::batch::handle(|| f(&script));
+        outcome.record(script, result);
+    }
+    outcome
+}
+
+/// The parsed script at `path`, parsed on first use.
+fn cached<'a>(asts: &'a mut HashMap<PathBuf, LuaTable>, parse: &ParseOptions, path: &Path) -> Result<&'a LuaTable> {
+    if !asts.contains_key(path) {
+        let ast = crate::parse_ast(path, parse)?;
+        asts.insert(path.to_path_buf(), ast);
+    }
+    Ok(&asts[path])
+}
+
+/// `path` under `dir` instead of `root`.
+fn mirrored(root: &Path, dir: &Path, path: &Path) -> Result<PathBuf> {
+    Ok(dir.join(path.strip_prefix(root)?))
+}
+
+impl Project {
+    /// The scripts under `root`, read and written with the default options.
+    pub fn open(root: impl AsRef<Path>) -> Result<Self> {
+        let root = crate::platform::long_path(root.as_ref())?;
+        if !root.is_dir() {
+            return Err(anyhow!("{} is not a directory", root.display()));
+        }
+        Ok(Project {
+            root,
+            parse: ParseOptions::default(),
+            write: WriteOptions::default(),
+            scenario: ScenarioOptions::default(),
+            asts: HashMap::new(),
+        })
+    }
+
+    /// Writes scripts in the style described by the yaml file at `path`, as
+    /// `--style` does.
+    pub fn with_style(mut self, path: &Path) -> Result<Self> {
+        crate::style::Style::load(path)?.apply(&mut self.write);
+        Ok(self)
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Every .ast file under the root, sorted.
+    pub fn scripts(&self) -> Result<Vec<PathBuf>> {
+        let mut files = Vec::new();
+        crate::batch::collect_ast_files(&self.root, &mut files)?;
+        files.sort();
+        Ok(files)
+    }
+
+    /// The parsed script at `path`, which is read only the first time.
+    pub fn ast(&mut self, path: &Path) -> Result<&LuaTable> {
+        cached(&mut self.asts, &self.parse, path)
+    }
+
+    /// Forgets the parsed scripts, for when they changed on disk or the
+    /// parse options did.
+    pub fn reload(&mut self) {
+        self.asts.clear();
+    }
+
+    /// Extracts every script to a yaml file of the same name under
+    /// `output_dir`, as `batch extract` does.
+    pub fn extract_all(&mut self, output_dir: &Path) -> Result<Outcome> {
+        let scripts = self.scripts()?;
+        let Project { root, parse, scenario, asts, .. } = self;
+        Ok(each(scripts, |script| {
+            let output = mirrored(root, output_dir, script)?.with_extension("yaml");
+            if let Some(parent) = output.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            let ast = cached(asts, parse, script)?;
+            if ast.is_empty() {
+                return Ok(());
+            }
+            crate::extract_secnario_toyaml(ast, parse.astver, output, scenario, &ExtractOptions::default())
+        }))
+    }
+
+    /// Merges the yaml file of the same name under `yaml_dir` into every
+    /// script, writing them under `output_dir`, as `batch merge` does.
+    pub fn merge_all(&mut self, yaml_dir: &Path, output_dir: &Path) -> Result<Outcome> {
+        let scripts = self.scripts()?;
+        let merge = MergeOptions::default();
+        Ok(each(scripts, |script| {
+            let output = mirrored(&self.root, output_dir, script)?;
+            if let Some(parent) = output.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            let yaml_input = mirrored(&self.root, yaml_dir, script)?.with_extension("yaml");
+            crate::merge_file(script, &yaml_input, &output, &self.parse, &self.write, &self.scenario, &merge)
+        }))
+    }
+
+    /// The counts `stats` writes for every script, with the state of each
+    /// translation found under `yaml_dir`. Empty scripts are listed apart.
+    pub fn stats(&mut self, yaml_dir: Option<&Path>) -> Result<ProjectStats> {
+        let length = LengthOptions::default();
+        let mut files = Vec::new();
+        let mut empty = Vec::new();
+        for script in self.scripts()? {
+            let ast = match cached(&mut self.asts, &self.parse, &script) {
+                Err(e) if e.downcast_ref::<EmptyScript>().is_some() => {
+                    empty.push(script.display().to_string());
+                    continue;
+                }
+                result => result?,
+            };
+            let translation = match yaml_dir {
+                Some(dir) => Some(mirrored(&self.root, dir, &script)?.with_extension("yaml")).filter(|path| path.exists()),
+                None => None,
+            };
+            let translation = translation.map(crate::read_yaml_as_strings).transpose()?;
+            files.push(FileStats::of(&script, ast, self.parse.astver, translation.as_deref(), &self.scenario, &length)?);
+        }
+        Ok(ProjectStats { empty, ..ProjectStats::new(files) })
+    }
+
+    /// How the blocks of every script link together, by script. Empty
+    /// scripts are left out.
+    pub fn graph(&mut self) -> Result<BTreeMap<PathBuf, BlockGraph>> {
+        let mut graphs = BTreeMap::new();
+        for script in self.scripts()? {
+            let ast = match cached(&mut self.asts, &self.parse, &script) {
+                Err(e) if e.downcast_ref::<EmptyScript>().is_some() => continue,
+                result => result?,
+            };
+            graphs.insert(script, crate::routes::build(ast, self.parse.astver)?);
+        }
+        Ok(graphs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_project() {
+        let dir = crate::test_dir("project");
+        let scripts = dir.join("scripts");
+        std::fs::create_dir_all(scripts.join("sub")).unwrap();
+        let script = "ast = {\n\tblock_00000 = { text = { ja = { { \"こんにちは\" } } }, linknext = \"block_00001\" },\n\tblock_00001 = { text = { ja = { { \"さようなら\" } } } },\n}\n";
+        std::fs::write(scripts.join("a.ast"), script).unwrap();
+        std::fs::write(scripts.join("sub/empty.ast"), "").unwrap();
+
+        let mut project = Project::open(&scripts).unwrap();
+        let extracted = project.extract_all(&dir.join("yaml")).unwrap();
+        assert_eq!(extracted.done, [scripts.join("a.ast")]);
+        assert_eq!(extracted.empty, [scripts.join("sub/empty.ast")]);
+        let yaml = std::fs::read_to_string(dir.join("yaml/a.yaml")).unwrap();
+        std::fs::write(dir.join("yaml/a.yaml"), yaml.replace("さようなら", "goodbye")).unwrap();
+
+        let stats = project.stats(Some(&dir.join("yaml"))).unwrap();
+        assert_eq!(stats.files.len(), 1);
+        assert_eq!(stats.empty.len(), 1);
+        let graph = &project.graph().unwrap()[&scripts.join("a.ast")];
+        assert_eq!(graph.successors("block_00000"), ["block_00001"]);
+
+        let merged = project.merge_all(&dir.join("yaml"), &dir.join("out")).unwrap();
+        assert!(merged.is_ok());
+        let output = std::fs::read_to_string(dir.join("out/a.ast")).unwrap();
+        assert!(output.contains("\"goodbye\"") && output.contains("こんにちは"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,88 @@
+use std::collections::HashMap;
+
+const PAIRS: [(char, char); 3] = [('「', '」'), ('『', '』'), ('“', '”')];
+
+fn describe(style: Option<char>) -> String {
+    match style {
+        Some(open) => format!("{}…{}", open, PAIRS.iter().find(|p| p.0 == open).map_or(open, |p| p.1)),
+        None => "no quotes".to_string(),
+    }
+}
+
+/// Describes the first bracket of `text` that is not closed or closed by the
+/// wrong kind, if any.
+pub fn unbalanced(text: &str) -> Option<String> {
+    let mut stack = Vec::new();
+    for ch in text.chars() {
+        if let Some(&(open, close)) = PAIRS.iter().find(|p| p.0 == ch) {
+            stack.push((open, close));
+        } else if let Some(&(open, close)) = PAIRS.iter().find(|p| p.1 == ch) {
+            match stack.pop() {
+                Some((_, expected)) if expected == close => {}
+                Some((other, expected)) => return Some(format!("'{}' closes '{}', expected '{}'", ch, other, expected)),
+                None => return Some(format!("'{}' has no matching '{}'", ch, open)),
+            }
+        }
+    }
+    stack.first().map(|(open, close)| format!("'{}' is never closed by '{}'", open, close))
+}
+
+/// The outermost quotation style of a line: the first opening bracket.
+fn style(text: &str) -> Option<char> {
+    text.chars().find(|ch| PAIRS.iter().any(|p| p.0 == *ch))
+}
+
+/// Finds translated lines that quote differently from how the rest of the
+/// translation renders the same source style, e.g. one 「」 line turned into
+/// “” while every other one was kept as 「」.
+pub fn inconsistent_styles(source: &[String], translation: &[String]) -> Vec<(usize, String)> {
+    let pairs: Vec<(Option<char>, Option<char>)> = source.iter()
+        .zip(translation)
+        .map(|(s, t)| (style(s), style(t)))
+        .collect();
+
+    let mut counts: HashMap<(Option<char>, Option<char>), usize> = HashMap::new();
+    for pair in pairs.iter().filter(|(s, _)| s.is_some()) {
+        *counts.entry(*pair).or_default() += 1;
+    }
+    // the most common rendering of each source style, ties going to the kept style
+    let mut usual: HashMap<Option<char>, (usize, bool, Option<char>)> = HashMap::new();
+    for (&(from, to), &count) in counts.iter() {
+        let candidate = (count, from == to, to);
+        let best = usual.entry(from).or_insert(candidate);
+        if candidate > *best {
+            *best = candidate;
+        }
+    }
+
+    pairs.iter()
+        .enumerate()
+        .filter_map(|(index, (from, to))| {
+            let expected = usual.get(from)?.2;
+            (expected != *to).then(|| (index, format!(
+                "source {} is translated as {}, elsewhere as {}", describe(*from), describe(*to), describe(expected))))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unbalanced() {
+        assert_eq!(unbalanced("「お兄『あさ』」"), None);
+        assert!(unbalanced("「お兄").is_some());
+        assert!(unbalanced("お兄」").is_some());
+        assert!(unbalanced("「お兄』").is_some());
+    }
+
+    #[test]
+    fn test_inconsistent_styles() {
+        let source: Vec<String> = ["「a」", "「b」", "「c」", "d"].iter().map(|s| s.to_string()).collect();
+        let translation: Vec<String> = ["“a”", "“b”", "「c」", "d"].iter().map(|s| s.to_string()).collect();
+        let found = inconsistent_styles(&source, &translation);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].0, 2);
+    }
+}
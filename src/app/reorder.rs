@@ -0,0 +1,158 @@
+use std::path::Path;
+use anyhow::{Result, anyhow};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use crate::{LuaTable, Value};
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Operation {
+    /// Take the scene out of the chain and link it in after `after`
+    Move,
+    /// Link a scene that is not in the chain in after `after`
+    Insert,
+    /// Take the scene out of the chain, linking around it
+    Skip,
+}
+
+/// One entry of the edit list read by `reorder --edits`, e.g.
+/// `{ op: move, scene: block_00010, until: block_00012, after: block_00003 }`.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Edit {
+    pub op: Operation,
+    /// First block of the scene
+    pub scene: String,
+    /// Last block of the scene, following linknext from `scene`; the scene is one block when left out
+    #[serde(default)]
+    pub until: Option<String>,
+    /// Block the scene follows, for `move` and `insert`
+    #[serde(default)]
+    pub after: Option<String>,
+}
+
+pub fn load(path: &Path) -> Result<Vec<Edit>> {
+    let content = std::fs::read_to_string(path).map_err(|e| anyhow!("Failed to read edit list {}: {}", path.display(), e))?;
+    serde_yaml::from_str(&content).map_err(|e| anyhow!("{}: {}", path.display(), e))
+}
+
+fn block<'a>(ast: &'a LuaTable, name: &str) -> Result<&'a LuaTable> {
+    ast.get("ast")
+        .and_then(Value::as_table)
+        .and_then(|blocks| blocks.get(name))
+        .and_then(Value::as_table)
+        .ok_or(anyhow!("The script has no block named {}", name))
+}
+
+fn block_mut<'a>(ast: &'a mut LuaTable, name: &str) -> Result<&'a mut LuaTable> {
+    ast.get_mut("ast")
+        .and_then(Value::as_table_mut)
+        .and_then(|blocks| blocks.get_mut(name))
+        .and_then(Value::as_table_mut)
+        .ok_or(anyhow!("The script has no block named {}", name))
+}
+
+fn linknext(ast: &LuaTable, name: &str) -> Result<Option<String>> {
+    Ok(block(ast, name)?.get("linknext").and_then(Value::as_string).cloned())
+}
+
+/// Points `name` at `target`, or ends the chain there when `target` is `None`.
+fn set_linknext(ast: &mut LuaTable, name: &str, target: Option<String>) -> Result<()> {
+    let block = block_mut(ast, name)?;
+    match target {
+        Some(target) => {
+            block.insert("linknext".to_string(), Value::from(target));
+        }
+        None => {
+            block.remove("linknext");
+        }
+    }
+    Ok(())
+}
+
+/// The blocks of a scene, from `first` along linknext to `last`.
+fn scene(ast: &LuaTable, first: &str, last: &str) -> Result<Vec<String>> {
+    let mut blocks = vec![first.to_string()];
+    while blocks.last().is_some_and(|block| block != last) {
+        let current = blocks.last().unwrap();
+        let next = linknext(ast, current)?.ok_or(anyhow!("The chain from {} ends at {} without reaching {}", first, current, last))?;
+        if blocks.contains(&next) {
+            return Err(anyhow!("The chain from {} loops back to {} without reaching {}", first, next, last));
+        }
+        blocks.push(next);
+    }
+    Ok(blocks)
+}
+
+/// Links every block leading into the scene to the block after it.
+fn detach(ast: &mut LuaTable, astver: Option<crate::compat::AstVersion>, first: &str, last: &str) -> Result<()> {
+    let next = linknext(ast, last)?;
+    let previous: Vec<String> = crate::iter_blocks(ast, astver)
+        .filter(|(_, block)| block.get("linknext").and_then(Value::as_string).is_some_and(|target| target == first))
+        .map(|(name, _)| name.clone())
+        .collect();
+    for name in previous {
+        set_linknext(ast, &name, next.clone())?;
+    }
+    Ok(())
+}
+
+/// Links the scene in between `after` and the block `after` led to.
+fn splice(ast: &mut LuaTable, first: &str, last: &str, after: &str) -> Result<()> {
+    let next = linknext(ast, after)?;
+    set_linknext(ast, after, Some(first.to_string()))?;
+    set_linknext(ast, last, next)
+}
+
+/// Applies the edits in order, each to the chain the ones before it left.
+pub fn apply(ast: &mut LuaTable, astver: Option<crate::compat::AstVersion>, edits: &[Edit]) -> Result<()> {
+    for (index, edit) in edits.iter().enumerate() {
+        let last = edit.until.as_deref().unwrap_or(&edit.scene);
+        let blocks = scene(ast, &edit.scene, last).map_err(|e| anyhow!("edit {}: {}", index, e))?;
+        let after = match (edit.op, &edit.after) {
+            (Operation::Skip, _) => None,
+            (_, Some(after)) if blocks.contains(after) => return Err(anyhow!("edit {}: {} is part of the scene it should follow", index, after)),
+            (_, Some(after)) => Some(after),
+            (_, None) => return Err(anyhow!("edit {}: {:?} needs the block to follow in after", index, edit.op)),
+        };
+        let result = match (edit.op, after) {
+            (Operation::Move, Some(after)) => detach(ast, astver, &edit.scene, last).and_then(|_| splice(ast, &edit.scene, last, after)),
+            (Operation::Insert, Some(after)) => splice(ast, &edit.scene, last, after),
+            _ => detach(ast, astver, &edit.scene, last),
+        };
+        result.map_err(|e| anyhow!("edit {}: {}", index, e))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reorder() {
+        let input = r#"ast = {
+            block_00000 = { linknext = "block_00001" },
+            block_00001 = { linknext = "block_00002" },
+            block_00002 = { linknext = "block_00003" },
+            block_00003 = { linknext = "block_00004" },
+            block_00004 = {},
+            block_00010 = { linknext = "block_00011" },
+            block_00011 = {},
+        }"#;
+        let mut ast = crate::parse_tokens(&crate::tokenize(input).unwrap()).unwrap();
+        let edits: Vec<Edit> = serde_yaml::from_str("
+- { op: move, scene: block_00001, until: block_00002, after: block_00003 }
+- { op: skip, scene: block_00004 }
+- { op: insert, scene: block_00010, until: block_00011, after: block_00000 }
+").unwrap();
+        apply(&mut ast, None, &edits).unwrap();
+
+        let chain = scene(&ast, "block_00000", "block_00002").unwrap();
+        assert_eq!(chain, vec!["block_00000", "block_00010", "block_00011", "block_00003", "block_00001", "block_00002"]);
+        assert_eq!(linknext(&ast, "block_00002").unwrap(), None);
+
+        let bad: Vec<Edit> = serde_yaml::from_str("- { op: move, scene: block_00001, after: block_00001 }").unwrap();
+        assert_eq!(apply(&mut ast, None, &bad).unwrap_err().to_string(), "edit 0: block_00001 is part of the scene it should follow");
+    }
+}
@@ -0,0 +1,136 @@
+use std::path::Path;
+use anyhow::{Result, anyhow};
+use crate::ParseOptions;
+
+/// Lines kept on each side of the failure before shrinking.
+const WINDOW_LINES: usize = 50;
+
+/// Replaces the letters and non-ASCII characters of string literals and
+/// comments with `x`, so no script text is shared. Quotes, brackets, digits
+/// and escape sequences stay as written, since they are what usually breaks
+/// the parser.
+pub fn redact(input: &str) -> String {
+    let mut redacted = String::with_capacity(input.len());
+    let mut quote: Option<char> = None;
+    let mut in_comment = false;
+    let mut chars = input.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\n' => in_comment = false,
+            '-' if quote.is_none() && chars.peek() == Some(&'-') => in_comment = true,
+            '\\' if quote.is_some() => {
+                redacted.push(ch);
+                // keep the escape itself: \x41, \u{3044}, \123
+                let Some(escaped) = chars.next() else {
+                    break;
+                };
+                redacted.push(escaped);
+                let keep = match escaped {
+                    'x' => 2,
+                    'u' => 1 + chars.clone().take_while(|&c| c != '}').count(),
+                    _ => 0,
+                };
+                redacted.extend(chars.by_ref().take(keep));
+                continue;
+            }
+            '"' | '\'' if !in_comment && quote.is_none() => quote = Some(ch),
+            _ if quote == Some(ch) => quote = None,
+            _ if (quote.is_some() || in_comment) && (ch.is_alphabetic() || !ch.is_ascii()) => {
+                redacted.push('x');
+                continue;
+            }
+            _ => {}
+        }
+        redacted.push(ch);
+    }
+    redacted
+}
+
+/// `file: message at line 3, column 5` -> (`message`, line 3).
+fn split_location(error: &str) -> (String, Option<usize>) {
+    let message = error.strip_prefix(": ").unwrap_or(error);
+    match message.rsplit_once(" at line ") {
+        Some((kind, location)) => {
+            let line = location.split(',').next().and_then(|line| line.parse().ok());
+            (kind.to_string(), line)
+        }
+        None => (message.to_string(), None),
+    }
+}
+
+/// The error `text` fails with, location left out, or `None` if it parses.
+fn failure(text: &str) -> Option<(String, Option<usize>)> {
+    let result = crate::logging::silenced(|| crate::parse_checked(text, Path::new(""), &ParseOptions::default()));
+    result.err().map(|e| split_location(&e.to_string()))
+}
+
+/// Closes the tables a cut left open, the way `--repair` would.
+fn balance(lines: &[&str]) -> String {
+    let text = lines.join("\n") + "\n";
+    let report = crate::braces::check(&text);
+    if report.is_balanced() {
+        text
+    } else {
+        crate::braces::repair(&text, &report)
+    }
+}
+
+/// Cuts `input` down to a redacted snippet that fails with the same error:
+/// first the lines around the failure, then as many chunks of those as can
+/// go while the error stays the same.
+pub fn minimize(input: &str) -> Result<String> {
+    let redacted = redact(input);
+    let (kind, line) = failure(&redacted).ok_or(anyhow!("The script parses once its text is redacted, so no snippet can be made"))?;
+    let line = line.ok_or(anyhow!("{} has no single location to cut a snippet around", kind))?;
+    let reproduces = |lines: &[&str]| failure(&balance(lines)).is_some_and(|(found, _)| found == kind);
+
+    let all: Vec<&str> = redacted.lines().collect();
+    let start = line.saturating_sub(WINDOW_LINES + 1);
+    let end = (line + WINDOW_LINES).min(all.len());
+    let mut lines = if reproduces(&all[start..end]) { all[start..end].to_vec() } else { all };
+
+    let mut chunk = lines.len() / 2;
+    while chunk > 0 {
+        let mut at = 0;
+        while at < lines.len() {
+            let mut candidate = lines.clone();
+            candidate.drain(at..(at + chunk).min(lines.len()));
+            if reproduces(&candidate) {
+                lines = candidate;
+            } else {
+                at += chunk;
+            }
+        }
+        chunk /= 2;
+    }
+    Ok(balance(&lines))
+}
+
+pub fn write(path: &Path, input: &str) -> Result<()> {
+    std::fs::write(path, minimize(input)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact() {
+        let input = "ast = { -- 秘密 note\n\tblock_00000 = { text = \"「お兄」\\x41\\u{3044}\\n\", 0x1F },\n}\n";
+        assert_eq!(redact(input), "ast = { -- xx xxxx\n\tblock_00000 = { text = \"xxxx\\x41\\u{3044}\\n\", 0x1F },\n}\n");
+    }
+
+    #[test]
+    fn test_minimize() {
+        let mut input = String::from("astver = 2.0\nast = {\n");
+        for i in 0..200 {
+            input.push_str(&format!("\tblock_{:05} = {{\n\t\ttext = {{ ja = {{ {{ \"「お兄」\" }} }} }},\n\t}},\n", i));
+        }
+        input.push_str("\tblock_00200 = {\n\t\ttext = { ja = { { \"秘密\" ~ } } },\n\t},\n}\n");
+        let snippet = minimize(&input).unwrap();
+        assert!(snippet.lines().count() <= 3, "{}", snippet);
+        assert!(!snippet.contains("秘密"));
+        assert_eq!(failure(&snippet).unwrap().0, "Unexpected character: ~");
+    }
+}
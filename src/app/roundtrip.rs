@@ -0,0 +1,125 @@
+use std::{fmt, path::Path};
+use anyhow::Result;
+use crate::{ParseOptions, ScenarioOptions, WriteOptions};
+
+#[derive(Debug, Default)]
+pub struct RoundtripReport {
+    pub lines: usize,
+    /// First line that did not survive the yaml round trip, with what came back
+    pub yaml_mismatch: Option<(usize, String, String)>,
+    /// First line that differs after merging and extracting again
+    pub merge_mismatch: Option<(usize, String, String)>,
+    /// Line counts of the first and second extraction when they differ
+    pub count_mismatch: Option<(usize, usize)>,
+    /// Source lines changed by merging the unmodified text back
+    pub changed_source_lines: usize,
+    /// Merge or re-parse failed outright
+    pub merge_error: Option<String>,
+}
+
+impl RoundtripReport {
+    pub fn is_clean(&self) -> bool {
+        self.yaml_mismatch.is_none()
+            && self.merge_mismatch.is_none()
+            && self.count_mismatch.is_none()
+            && self.changed_source_lines == 0
+            && self.merge_error.is_none()
+    }
+}
+
+impl fmt::Display for RoundtripReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_clean() {
+            return write!(f, "round trip ok: {} lines", self.lines);
+        }
+        write!(f, "round trip failed ({} lines)", self.lines)?;
+        if let Some((index, before, after)) = &self.yaml_mismatch {
+            write!(f, "\n  yaml changed line {}: {:?} -> {:?}", index, before, after)?;
+        }
+        if let Some(e) = &self.merge_error {
+            write!(f, "\n  merge failed: {}", e)?;
+        }
+        if let Some((before, after)) = self.count_mismatch {
+            write!(f, "\n  extracted {} lines, re-extracted {}", before, after)?;
+        }
+        if let Some((index, before, after)) = &self.merge_mismatch {
+            write!(f, "\n  merge changed line {}: {:?} -> {:?}", index, before, after)?;
+        }
+        if self.changed_source_lines > 0 {
+            write!(f, "\n  merging the unmodified text changed {} source lines", self.changed_source_lines)?;
+        }
+        std::result::Result::Ok(())
+    }
+}
+
+fn first_difference(a: &[String], b: &[String]) -> Option<(usize, String, String)> {
+    a.iter()
+        .zip(b)
+        .enumerate()
+        .find(|(_, (x, y))| x != y)
+        .map(|(i, (x, y))| (i, x.clone(), y.clone()))
+}
+
+/// Runs extract -> yaml -> merge -> extract on `input` without touching disk.
+pub fn check(input: &Path, parse: &ParseOptions, write: &WriteOptions, scenario: &ScenarioOptions) -> Result<RoundtripReport> {
+    let script = crate::read_script(input, parse)?;
+    let ast = crate::parse_source(script.clone(), input, parse)?;
+    let mut report = RoundtripReport::default();
+    if ast.is_empty() {
+        return Ok(report);
+    }
+
+    let extracted = crate::extract_secnario(&ast, parse.astver, scenario)?;
+    report.lines = extracted.len();
+
+    let gaiji = crate::load_gaiji(scenario)?;
+    let encoded: Vec<String> = match &gaiji {
+        Some(gaiji) => extracted.iter().map(|text| gaiji.encode(text)).collect(),
+        None => extracted.clone(),
+    };
+    let yaml = serde_yaml::to_string(&encoded)?;
+    let mut translated: Vec<String> = serde_yaml::from_str(&yaml)?;
+    if let Some(gaiji) = &gaiji {
+        translated = translated.iter().map(|text| gaiji.decode(text)).collect();
+    }
+    report.yaml_mismatch = first_difference(&extracted, &translated);
+
+    let blocks = crate::extract_block_texts(&ast, parse.astver, scenario)?;
+    let merged = match crate::splice::splice_texts(&script, &blocks, &translated, scenario.lang(), parse, write, false) {
+        std::result::Result::Ok(merged) => merged,
+        Err(e) => {
+            report.merge_error = Some(format!("{:#}", e));
+            return Ok(report);
+        }
+    };
+    report.changed_source_lines = script.lines().zip(merged.lines()).filter(|(a, b)| a != b).count();
+
+    let reparsed = match crate::parse_source(merged, input, parse) {
+        std::result::Result::Ok(reparsed) => reparsed,
+        Err(e) => {
+            report.merge_error = Some(format!("{:#}", e));
+            return Ok(report);
+        }
+    };
+    let reextracted = crate::extract_secnario(&reparsed, parse.astver, scenario)?;
+    if reextracted.len() != extracted.len() {
+        report.count_mismatch = Some((extracted.len(), reextracted.len()));
+    }
+    report.merge_mismatch = first_difference(&extracted, &reextracted);
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip_clean() {
+        let path = std::env::temp_dir().join("artemis_ast_roundtrip_test.ast");
+        std::fs::write(&path, "ast = {\n\tblock_00000 = {\n\t\ttext = {\n\t\t\tja = {\n\t\t\t\t{\n\t\t\t\t\t\"plain\",\n\t\t\t\t},\n\t\t\t},\n\t\t},\n\t},\n}\n").unwrap();
+        let report = check(&path, &ParseOptions::default(), &WriteOptions::default(), &ScenarioOptions::default()).unwrap();
+        assert!(report.is_clean(), "{}", report);
+        assert_eq!(report.lines, 1);
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,133 @@
+use std::collections::{HashMap, HashSet};
+use anyhow::Result;
+use crate::{LuaTable, Value};
+
+/// Blocks of a script and where each one can continue to.
+pub struct BlockGraph {
+    /// Block names in script order, the first one being the entry point
+    order: Vec<String>,
+    edges: HashMap<String, Vec<String>>,
+    /// `(lines, characters)` of text in each block
+    counts: HashMap<String, (usize, usize)>,
+}
+
+impl BlockGraph {
+    /// Block names in script order, the entry point first.
+    pub fn blocks(&self) -> &[String] {
+        &self.order
+    }
+
+    /// The blocks `block` can continue to.
+    pub fn successors(&self, block: &str) -> &[String] {
+        self.edges.get(block).map_or(&[], Vec::as_slice)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Route {
+    pub blocks: Vec<String>,
+    pub lines: usize,
+    pub chars: usize,
+}
+
+/// Collects every string in `value` naming another block: `linknext`, and
+/// the targets of jumps and choices.
+fn targets<'a>(value: &'a Value, blocks: &HashSet<&str>, found: &mut Vec<&'a str>) {
+    match value {
+        Value::String(s) if blocks.contains(s.as_str()) && !found.contains(&s.as_str()) => found.push(s),
+        Value::Table(table) => table.values().for_each(|item| targets(item, blocks, found)),
+        _ => {}
+    }
+}
+
+pub fn build(ast: &LuaTable, astver: Option<crate::compat::AstVersion>) -> Result<BlockGraph> {
+    let texts = crate::extract_blocks(ast, astver, crate::DEFAULT_LANG)?;
+    let order: Vec<String> = texts.iter().map(|block| block.name.clone()).collect();
+    let counts = texts.into_iter()
+        .map(|block| {
+            let chars = block.texts.iter().map(|(_, text, _)| text.chars().filter(|c| !c.is_whitespace()).count()).sum();
+            (block.name, (block.texts.len(), chars))
+        })
+        .collect();
+
+    let names: HashSet<&str> = order.iter().map(String::as_str).collect();
+    let mut edges = HashMap::new();
+    for (block_key, block) in crate::iter_blocks(ast, astver) {
+        let mut found = Vec::new();
+        block.values().for_each(|item| targets(item, &names, &mut found));
+        found.retain(|target| *target != block_key);
+        edges.insert(block_key.clone(), found.into_iter().map(str::to_string).collect());
+    }
+    Ok(BlockGraph { order, edges, counts })
+}
+
+/// Every path from the first block to a block with no successor, stopping a
+/// path when it loops back on itself. At most `max_routes` are returned.
+pub fn routes(graph: &BlockGraph, max_routes: usize) -> Vec<Route> {
+    let mut routes = Vec::new();
+    let Some(entry) = graph.order.first() else {
+        return routes;
+    };
+    let mut stack = vec![vec![entry.clone()]];
+    while let Some(path) = stack.pop() {
+        if routes.len() >= max_routes {
+            break;
+        }
+        let last = path.last().unwrap();
+        let next: Vec<&String> = graph.edges.get(last)
+            .into_iter()
+            .flatten()
+            .filter(|target| !path.contains(target))
+            .collect();
+        if next.is_empty() {
+            let (lines, chars) = path.iter()
+                .filter_map(|block| graph.counts.get(block))
+                .fold((0, 0), |(l, c), (bl, bc)| (l + bl, c + bc));
+            routes.push(Route { blocks: path, lines, chars });
+            continue;
+        }
+        // pushed in reverse so the first choice is explored first
+        for target in next.into_iter().rev() {
+            let mut longer = path.clone();
+            longer.push(target.clone());
+            stack.push(longer);
+        }
+    }
+    routes
+}
+
+pub fn print_report(graph: &BlockGraph, routes: &[Route]) {
+    for (index, route) in routes.iter().enumerate() {
+        println!("route {} (ends at {}): {} lines, {} characters", index + 1, route.blocks.last().unwrap(), route.lines, route.chars);
+        // only the choices are interesting, straight runs of blocks are implied
+        for pair in route.blocks.windows(2) {
+            if graph.edges.get(&pair[0]).is_some_and(|next| next.len() > 1) {
+                println!("  {} -> {}", pair[0], pair[1]);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_routes() {
+        let input = r#"ast = {
+            block_00000 = { text = { ja = { { "ab" } } }, {"select", a="block_00001", b="block_00002"} },
+            block_00001 = { text = { ja = { { "cde" } } }, linknext = "block_00003" },
+            block_00002 = { text = { ja = { { "f" } } }, linknext = "block_00000" },
+            block_00003 = { text = { ja = { { "gh" } } } },
+        }
+        "#;
+        let ast = crate::parse_tokens(&crate::tokenize(input).unwrap()).unwrap();
+        let graph = build(&ast, None).unwrap();
+        let found = routes(&graph, 10);
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].blocks, vec!["block_00000", "block_00001", "block_00003"]);
+        assert_eq!((found[0].lines, found[0].chars), (3, 7));
+        assert_eq!(found[1].blocks, vec!["block_00000", "block_00002"]);
+        assert_eq!((found[1].lines, found[1].chars), (2, 3));
+    }
+}
@@ -0,0 +1,138 @@
+use clap::ValueEnum;
+use schemars::{schema::RootSchema, schema_for};
+use crate::{chapters, dedupe, documents, mapping, reorder, shards, sidecar, stats, style};
+#[cfg(feature = "html-export")]
+use crate::html_export;
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum SchemaFormat {
+    /// Extracted yaml read back by merge: a plain list, `--dedupe` entries, `--tag-kind` records or lines with variants
+    Translation,
+    /// One document of a `--per-block` extraction
+    Block,
+    /// The manifest listing the shards of an `extract --max-entries` run
+    Manifest,
+    /// The JSON saved from an `html-export` page
+    #[cfg(feature = "html-export")]
+    HtmlExport,
+    /// The chapters.yaml read by `batch merge --chapter`
+    Chapters,
+    /// The `.ast.meta` sidecar written by `extract --meta`
+    Sidecar,
+    /// The JSON written by `merge --emit-mapping`
+    Mapping,
+    /// The JSON written by `stats`
+    Stats,
+    /// The edit list read by `reorder --edits`
+    Edits,
+    /// The formatting read by `--style`
+    Style,
+}
+
+pub fn schema(format: SchemaFormat) -> RootSchema {
+    match format {
+        SchemaFormat::Translation => schema_for!(dedupe::TranslationFile),
+        SchemaFormat::Block => schema_for!(documents::BlockDocument),
+        SchemaFormat::Manifest => schema_for!(shards::Manifest),
+        #[cfg(feature = "html-export")]
+        SchemaFormat::HtmlExport => schema_for!(html_export::HtmlExport),
+        SchemaFormat::Chapters => schema_for!(chapters::ChapterManifest),
+        SchemaFormat::Sidecar => schema_for!(sidecar::Sidecar),
+        SchemaFormat::Mapping => schema_for!(mapping::Mapping),
+        SchemaFormat::Stats => schema_for!(stats::ProjectStats),
+        SchemaFormat::Edits => schema_for!(Vec<reorder::Edit>),
+        SchemaFormat::Style => schema_for!(style::Style),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    /// Checks `value` against the parts of JSON Schema that schemars emits.
+    fn accepts(schema: &Value, root: &Value, value: &Value) -> bool {
+        if let Some(path) = schema["$ref"].as_str() {
+            let name = path.strip_prefix("#/definitions/").unwrap();
+            return accepts(&root["definitions"][name], root, value);
+        }
+        if let Some(options) = schema["anyOf"].as_array() {
+            return options.iter().any(|option| accepts(option, root, value));
+        }
+        let type_ok = |name: &str| match name {
+            "string" => value.is_string(),
+            "integer" => value.is_i64() || value.is_u64(),
+            "number" => value.is_number(),
+            "boolean" => value.is_boolean(),
+            "null" => value.is_null(),
+            "array" => value.is_array(),
+            "object" => value.is_object(),
+            other => panic!("unexpected type {}", other),
+        };
+        let types_ok = match &schema["type"] {
+            Value::String(name) => type_ok(name),
+            Value::Array(names) => names.iter().any(|name| type_ok(name.as_str().unwrap())),
+            _ => true,
+        };
+        if !types_ok {
+            return false;
+        }
+        if let Some(allowed) = schema["enum"].as_array() {
+            if !allowed.contains(value) {
+                return false;
+            }
+        }
+        if let (Some(minimum), Some(number)) = (schema["minimum"].as_f64(), value.as_f64()) {
+            if number < minimum {
+                return false;
+            }
+        }
+        if let Some(items) = value.as_array() {
+            if !schema["items"].is_null() && !items.iter().all(|item| accepts(&schema["items"], root, item)) {
+                return false;
+            }
+        }
+        if let Some(fields) = value.as_object() {
+            let required = schema["required"].as_array().into_iter().flatten();
+            if !required.into_iter().all(|name| fields.contains_key(name.as_str().unwrap())) {
+                return false;
+            }
+            for (name, field) in fields {
+                let ok = match (&schema["properties"][name], &schema["additionalProperties"]) {
+                    (Value::Null, Value::Bool(allowed)) => *allowed,
+                    (Value::Null, Value::Null) => true,
+                    (Value::Null, extra) => accepts(extra, root, field),
+                    (property, _) => accepts(property, root, field),
+                };
+                if !ok {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    #[test]
+    fn test_translation_schema() {
+        let root = serde_json::to_value(schema(SchemaFormat::Translation)).unwrap();
+        let layouts = [
+            "- first\n- second\n",
+            "- text: first\n  refs: [0, 2]\n- text: second\n  refs: [1]\n",
+            "- kind: dialogue\n  text: first\n  inferred_speaker: Alice\n- kind: narration\n  text: second\n  condition: route == 1\n",
+            "- first\n- text: second\n  variants:\n    female: second, she said\n",
+            "chapter1:\n- id: 0\n  text: first\n- id: 1\n  text: second\n",
+        ];
+        for yaml in layouts {
+            let parsed: dedupe::TranslationFile = serde_yaml::from_str(yaml).unwrap();
+            assert!(parsed.into_strings().is_ok(), "{}", yaml);
+            let value: Value = serde_yaml::from_str(yaml).unwrap();
+            assert!(accepts(&root, &root, &value), "the schema rejects\n{}", yaml);
+        }
+
+        for yaml in ["- kind: spoken\n  text: first\n", "chapter1: first\n", "- text: first\n"] {
+            assert!(serde_yaml::from_str::<dedupe::TranslationFile>(yaml).is_err(), "{}", yaml);
+            let value: Value = serde_yaml::from_str(yaml).unwrap();
+            assert!(!accepts(&root, &root, &value), "the schema accepts\n{}", yaml);
+        }
+    }
+}
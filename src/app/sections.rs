@@ -0,0 +1,82 @@
+use std::{collections::HashMap, path::Path};
+use anyhow::Result;
+use crate::{LuaTable, ScenarioOptions};
+
+/// A run of blocks under one `savetitle`, the chapter name the game shows
+/// on its save screen. Text before the first `savetitle` forms a section
+/// without a title.
+#[derive(Debug, Default, PartialEq)]
+pub struct Section {
+    pub title: Option<String>,
+    /// `file:block` where the section starts
+    pub start: String,
+    pub blocks: usize,
+    pub lines: usize,
+    pub characters: usize,
+}
+
+/// The title set by a block's `{"savetitle", text="..."}` command, if any.
+pub fn savetitle(block: &LuaTable) -> Option<&str> {
+    block.array.iter()
+        .filter(|item| crate::command_name(item) == Some("savetitle"))
+        .find_map(|item| crate::command_attr(item, "text")?.as_string())
+        .map(String::as_str)
+}
+
+/// Adds the blocks of one script to `sections`, in reading order. A script
+/// that does not open with a `savetitle` continues the last section, and a
+/// `savetitle` repeating the current title does not start a new one.
+pub fn add_script(sections: &mut Vec<Section>, file: &Path, ast: &LuaTable, astver: Option<crate::compat::AstVersion>, options: &ScenarioOptions) -> Result<()> {
+    let titles: HashMap<&String, &str> = crate::iter_blocks(ast, astver)
+        .filter_map(|(name, block)| Some((name, savetitle(block)?)))
+        .collect();
+    for block in crate::extract_block_texts(ast, astver, options)? {
+        let title = titles.get(&block.name).copied();
+        let current = sections.last().and_then(|section| section.title.as_deref());
+        if sections.is_empty() || title.is_some_and(|title| Some(title) != current) {
+            sections.push(Section {
+                title: title.map(str::to_string),
+                start: format!("{}:{}", file.display(), block.name),
+                ..Default::default()
+            });
+        }
+        let section = sections.last_mut().unwrap();
+        section.blocks += 1;
+        section.lines += block.texts.len();
+        section.characters += block.texts.iter().map(|(_, text, _)| text.chars().count()).sum::<usize>();
+    }
+    Ok(())
+}
+
+pub fn print_report(sections: &[Section]) {
+    for section in sections {
+        println!("{} ({}): {} blocks, {} lines, {} characters",
+            section.title.as_deref().unwrap_or("(untitled)"), section.start, section.blocks, section.lines, section.characters);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sections() {
+        let block = |title: &str, text: &str| {
+            let savetitle = if title.is_empty() { String::new() } else { format!("{{\"savetitle\", text=\"{}\"}},", title) };
+            format!("{{ {} text = {{ ja = {{ {{ \"{}\" }} }} }} }}", savetitle, text)
+        };
+        let input = format!("ast = {{ block_00000 = {}, block_00001 = {}, block_00002 = {}, block_00003 = {} }}",
+            block("", "「あ」"), block("第一章", "「いい」"), block("第一章", "う"), block("第二章", "「え」"));
+        let ast = crate::parse_tokens(&crate::tokenize(&input).unwrap()).unwrap();
+        let mut sections = Vec::new();
+        add_script(&mut sections, Path::new("a.ast"), &ast, None, &ScenarioOptions::default()).unwrap();
+        let titles: Vec<(Option<&str>, &str, usize, usize)> = sections.iter()
+            .map(|section| (section.title.as_deref(), section.start.as_str(), section.blocks, section.characters))
+            .collect();
+        assert_eq!(titles, vec![
+            (None, "a.ast:block_00000", 1, 3),
+            (Some("第一章"), "a.ast:block_00001", 2, 5),
+            (Some("第二章"), "a.ast:block_00003", 1, 3),
+        ]);
+    }
+}
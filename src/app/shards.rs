@@ -0,0 +1,89 @@
+use std::path::{Path, PathBuf};
+use anyhow::{Result, anyhow};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, PartialEq)]
+pub struct Shard {
+    /// File name, relative to the manifest
+    pub file: String,
+    pub entries: usize,
+}
+
+/// Written in place of the extraction when `--max-entries` splits it, listing
+/// the shards in order. Merge reads the whole set back through it.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, PartialEq)]
+pub struct Manifest {
+    pub total: usize,
+    pub shards: Vec<Shard>,
+}
+
+/// `out/a.yaml` -> `out/a.003.yaml`
+fn shard_path(output: &Path, number: usize) -> PathBuf {
+    let stem = output.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match output.extension() {
+        Some(ext) => format!("{}.{:03}.{}", stem, number, ext.to_string_lossy()),
+        None => format!("{}.{:03}", stem, number),
+    };
+    output.with_file_name(name)
+}
+
+pub fn write(output: &Path, entries: Vec<serde_yaml::Value>, max_entries: usize) -> Result<()> {
+    if max_entries == 0 {
+        return Err(anyhow!("--max-entries must be at least 1"));
+    }
+    let mut manifest = Manifest { total: entries.len(), shards: Vec::new() };
+    for (index, chunk) in entries.chunks(max_entries).enumerate() {
+        let path = shard_path(output, index + 1);
+        std::fs::write(&path, serde_yaml::to_string(chunk)?)?;
+        let file = path.file_name().unwrap().to_string_lossy().into_owned();
+        manifest.shards.push(Shard { file, entries: chunk.len() });
+    }
+    std::fs::write(output, serde_yaml::to_string(&manifest)?)?;
+    Ok(())
+}
+
+/// Joins the shards listed by a manifest back into one list. Returns `None`
+/// when `content` is not a manifest.
+pub fn load(manifest_path: &Path, content: &str) -> Result<Option<serde_yaml::Value>> {
+    let Ok(manifest) = serde_yaml::from_str::<Manifest>(content) else {
+        return Ok(None);
+    };
+    let mut entries = Vec::new();
+    for shard in manifest.shards.iter() {
+        let path = manifest_path.with_file_name(&shard.file);
+        let content = std::fs::read_to_string(&path)
+            .map_err(|e| anyhow!("Failed to read shard {}: {}", path.display(), e))?;
+        let serde_yaml::Value::Sequence(items) = serde_yaml::from_str(&content)? else {
+            return Err(anyhow!("Shard {} is not a list", path.display()));
+        };
+        if items.len() != shard.entries {
+            return Err(anyhow!("Shard {} has {} entries, the manifest expects {}", path.display(), items.len(), shard.entries));
+        }
+        entries.extend(items);
+    }
+    if entries.len() != manifest.total {
+        return Err(anyhow!("Shards hold {} entries, the manifest expects {}", entries.len(), manifest.total));
+    }
+    Ok(Some(serde_yaml::Value::Sequence(entries)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shards_roundtrip() {
+        let dir = std::env::temp_dir().join("artemis_ast_shards_test");
+        std::fs::create_dir_all(&dir).unwrap();
+        let output = dir.join("a.yaml");
+        let entries: Vec<serde_yaml::Value> = (0..5).map(|i| serde_yaml::Value::String(i.to_string())).collect();
+        write(&output, entries.clone(), 2).unwrap();
+        assert!(dir.join("a.003.yaml").exists());
+
+        let content = std::fs::read_to_string(&output).unwrap();
+        assert_eq!(load(&output, &content).unwrap(), Some(serde_yaml::Value::Sequence(entries)));
+        assert_eq!(load(&output, "- a\n").unwrap(), None);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
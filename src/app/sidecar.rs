@@ -0,0 +1,87 @@
+use std::path::{Path, PathBuf};
+use anyhow::Result;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use crate::text_scan;
+
+/// Parse facts about one extracted line, recorded so later merges do not
+/// have to re-derive them.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, PartialEq)]
+pub struct SidecarEntry {
+    pub block: String,
+    /// Byte range of the literal, quotes included
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    /// The literal exactly as written in the script
+    pub literal: String,
+    /// sha256 of the decoded text
+    pub sha256: String,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, PartialEq)]
+pub struct Sidecar {
+    pub source_sha256: String,
+    pub entries: Vec<SidecarEntry>,
+}
+
+/// `scripts/a.ast` -> `scripts/a.ast.meta`
+pub fn sidecar_path(ast: &Path) -> PathBuf {
+    let mut name = ast.as_os_str().to_owned();
+    name.push(".meta");
+    PathBuf::from(name)
+}
+
+pub fn build(input: &str, lang: &str, parse: &crate::ParseOptions) -> Result<Sidecar> {
+    let mut entries = Vec::new();
+    let mut line = 1;
+    let mut counted = 0;
+    for (block, text, span) in text_scan::literals(input, lang, parse)? {
+        line += input[counted..span.start].matches('\n').count();
+        counted = span.start;
+        entries.push(SidecarEntry {
+            block,
+            start: span.start,
+            end: span.end,
+            line,
+            literal: input[span].to_string(),
+            sha256: crate::sha256_hex(text.as_bytes()),
+        });
+    }
+    Ok(Sidecar { source_sha256: crate::sha256_hex(input.as_bytes()), entries })
+}
+
+pub fn write(ast: &Path, input: &str, lang: &str, parse: &crate::ParseOptions) -> Result<()> {
+    let sidecar = build(input, lang, parse)?;
+    std::fs::write(sidecar_path(ast), serde_yaml::to_string(&sidecar)?)?;
+    Ok(())
+}
+
+/// Loads the sidecar of `ast` if one was written.
+pub fn load(ast: &Path) -> Result<Option<Sidecar>> {
+    let path = sidecar_path(ast);
+    if !path.exists() {
+        return Ok(None);
+    }
+    Ok(Some(serde_yaml::from_str(&std::fs::read_to_string(path)?)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_sidecar() {
+        let input = "ast = {\n\tblock_00000 = {\n\t\t{\"savetitle\", text=\"x\"},\n\t\ttext = {\n\t\t\tja = {\n\t\t\t\t{\n\t\t\t\t\tname = {\"妃愛\"},\n\t\t\t\t\t\"「お兄」\",\n\t\t\t\t\t{\"rt2\"},\n\t\t\t\t},\n\t\t\t},\n\t\t},\n\t},\n}\n";
+        let sidecar = build(input, "ja", &crate::ParseOptions::default()).unwrap();
+        assert_eq!(sidecar.entries.len(), 1);
+        let entry = &sidecar.entries[0];
+        assert_eq!(entry.block, "block_00000");
+        assert_eq!(entry.line, 8);
+        assert_eq!(entry.literal, "\"「お兄」\"");
+        assert_eq!(&input[entry.start..entry.end], entry.literal);
+
+        let ast = crate::parse_tokens(&crate::tokenize(input).unwrap()).unwrap();
+        assert_eq!(crate::extract_secnario(&ast, None, &Default::default()).unwrap(), vec!["「お兄」".to_string()]);
+    }
+}
@@ -0,0 +1,90 @@
+use std::collections::{HashMap, VecDeque};
+use anyhow::{Result, anyhow};
+use crate::{BlockText, ParseOptions, QuoteStyle, UnusedReplacement, WriteOptions, text_scan};
+
+/// Writes each changed line over the literal it was extracted from and
+/// copies every other byte of the script as it is. Literals are found by
+/// their tokens, so escapes and long brackets in the script match the
+/// decoded lines, and every line goes back to its own literal even when
+/// another line has the same text.
+///
+/// `merge` writes the changed lines with the quoting of the write options.
+/// With `surgical`, for `merge --surgical`, each keeps the quote it was
+/// written with unless `--long-strings` puts it in long brackets.
+///
+/// `blocks` are the extracted lines of channel `lang`, paired in order with `texts`. Lines are
+/// matched within their block, so any scenario options that reorder blocks
+/// or leave lines out still land each line in its place.
+pub fn splice_texts(script: &str, blocks: &[BlockText], texts: &[String], lang: &str, parse: &ParseOptions, options: &WriteOptions, surgical: bool) -> Result<String> {
+    let extracted = blocks.iter().flat_map(|block| block.texts.iter().map(move |(_, text, _)| (&block.name, text)));
+    if extracted.clone().count() != texts.len() {
+        return Err(anyhow!("The translation has {} lines, the script {}", texts.len(), extracted.count()));
+    }
+    let mut pending: HashMap<&String, VecDeque<(&String, &String)>> = HashMap::new();
+    for ((block, old), new) in extracted.zip(texts) {
+        pending.entry(block).or_default().push_back((old, new));
+    }
+
+    let mut output = String::with_capacity(script.len());
+    let mut copied = 0;
+    for (block, text, span) in text_scan::literals(script, lang, parse)? {
+        let Some(lines) = pending.get_mut(&block) else {
+            continue;
+        };
+        if lines.front().is_none_or(|(old, _)| **old != text) {
+            continue;
+        }
+        let (_, new) = lines.pop_front().unwrap();
+        if *new == text {
+            continue;
+        }
+        let quote_style = if !surgical {
+            options.quote_style
+        } else if script[span.start..].starts_with('\'') {
+            QuoteStyle::Single
+        } else {
+            QuoteStyle::Double
+        };
+        output.push_str(&script[copied..span.start]);
+        output.push_str(&crate::string_to_script(new, &WriteOptions { quote_style, ..*options }));
+        copied = span.end;
+    }
+    output.push_str(&script[copied..]);
+
+    if let Some((old, _)) = pending.into_values().find_map(|mut lines| lines.pop_front()) {
+        return Err(UnusedReplacement(old.clone()).into());
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_splice_texts() {
+        let script = "-- keep me\r\nast = {\r\n  block_00000 = { text = { ja = { { name = {'妃愛'}, '「お兄\\u{3001}」', \"朝だ。\" } } } },\r\n  block_00001 = { text = { ja = { { \"同じ\" } } } },\r\n}";
+        let ast = crate::parse_tokens(&crate::tokenize(script).unwrap()).unwrap();
+        let blocks = crate::extract_block_texts(&ast, None, &crate::ScenarioOptions::default()).unwrap();
+        let texts = vec!["\"Big bro\"".to_string(), "朝だ。".to_string(), "Same".to_string()];
+        let merged = splice_texts(script, &blocks, &texts, "ja", &crate::ParseOptions::default(), &WriteOptions::default(), true).unwrap();
+        assert_eq!(merged, "-- keep me\r\nast = {\r\n  block_00000 = { text = { ja = { { name = {'妃愛'}, '\"Big bro\"', \"朝だ。\" } } } },\r\n  block_00001 = { text = { ja = { { \"Same\" } } } },\r\n}");
+
+        let unchanged: Vec<String> = blocks.iter().flat_map(|block| block.texts.iter().map(|(_, text, _)| text.clone())).collect();
+        assert_eq!(splice_texts(script, &blocks, &unchanged, "ja", &crate::ParseOptions::default(), &WriteOptions::default(), true).unwrap(), script);
+        assert!(splice_texts(script, &blocks, &texts[..2], "ja", &crate::ParseOptions::default(), &WriteOptions::default(), true).is_err());
+    }
+
+    #[test]
+    fn test_merge_unmodified() {
+        let script = "ast = {\n\tblock_00000 = { text = { ja = { { \"say \\\"hi\\\"\\n\", 'it\\'s', [[plain]], [==[long ]] one]==], \"plain\" } } } },\n}\n";
+        let ast = crate::parse_tokens(&crate::tokenize(script).unwrap()).unwrap();
+        let blocks = crate::extract_block_texts(&ast, None, &crate::ScenarioOptions::default()).unwrap();
+        let texts: Vec<String> = blocks.iter().flat_map(|block| block.texts.iter().map(|(_, text, _)| text.clone())).collect();
+        assert_eq!(texts, ["say \"hi\"\n", "it's", "plain", "long ]] one", "plain"]);
+        assert_eq!(splice_texts(script, &blocks, &texts, "ja", &crate::ParseOptions::default(), &WriteOptions::default(), false).unwrap(), script);
+        let texts = ["say \"hi\"\n", "it's", "plain", "long ]] one", "done"].map(String::from);
+        let merged = splice_texts(script, &blocks, &texts, "ja", &crate::ParseOptions::default(), &WriteOptions::default(), false).unwrap();
+        assert_eq!(merged, script.replace("\"plain\" }", "\"done\" }"));
+    }
+}
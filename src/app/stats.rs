@@ -0,0 +1,111 @@
+use std::path::Path;
+use anyhow::Result;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use crate::{LineKind, LuaTable, ScenarioOptions, completeness::Completeness, length::LengthOptions};
+
+/// Counts for one script. No text is recorded, so the file can be shared
+/// with a dashboard without sharing the script.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default, PartialEq)]
+pub struct FileStats {
+    pub file: String,
+    pub blocks: usize,
+    pub dialogue: usize,
+    pub narration: usize,
+    /// Characters of the source lines
+    pub characters: usize,
+    /// Chapters started by a `savetitle` in this file
+    pub sections: usize,
+    /// Filled-in entries of the translation, when one was found
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub translated: Option<usize>,
+    /// Translated lines over `--max-length`
+    pub too_long: usize,
+    /// Translated lines wrapping past `--max-rows`
+    pub too_many_rows: usize,
+}
+
+/// Written by `stats`: the counts of every script and their sum.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default, PartialEq)]
+pub struct ProjectStats {
+    /// Version of artemis_ast that wrote the file
+    pub version: String,
+    pub total: FileStats,
+    pub files: Vec<FileStats>,
+    /// Empty placeholder scripts, left out of the counts
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub empty: Vec<String>,
+}
+
+impl FileStats {
+    /// Counts the lines of a script and, if given, the state of its translation.
+    pub fn of(file: &Path, ast: &LuaTable, astver: Option<crate::compat::AstVersion>, translation: Option<&[String]>, scenario: &ScenarioOptions, length: &LengthOptions) -> Result<Self> {
+        let blocks = crate::extract_block_texts(ast, astver, scenario)?;
+        let mut sections = Vec::new();
+        crate::sections::add_script(&mut sections, file, ast, astver, scenario)?;
+        let lines = blocks.iter().flat_map(|block| block.texts.iter());
+        let mut stats = FileStats {
+            file: file.display().to_string(),
+            blocks: blocks.len(),
+            sections: sections.iter().filter(|section| section.title.is_some()).count(),
+            ..Default::default()
+        };
+        for (kind, text, _) in lines {
+            match kind {
+                LineKind::Dialogue => stats.dialogue += 1,
+                LineKind::Narration => stats.narration += 1,
+            }
+            stats.characters += text.chars().count();
+        }
+        if let Some(translation) = translation {
+            stats.translated = Some(Completeness::of(translation).translated);
+            stats.too_long = crate::length::check_lengths(translation, length).len();
+            stats.too_many_rows = crate::length::check_rows(translation, length).len();
+        }
+        Ok(stats)
+    }
+
+    fn add(&mut self, other: &FileStats) {
+        self.blocks += other.blocks;
+        self.dialogue += other.dialogue;
+        self.narration += other.narration;
+        self.characters += other.characters;
+        self.sections += other.sections;
+        if let Some(translated) = other.translated {
+            self.translated = Some(self.translated.unwrap_or(0) + translated);
+        }
+        self.too_long += other.too_long;
+        self.too_many_rows += other.too_many_rows;
+    }
+}
+
+impl ProjectStats {
+    pub fn new(files: Vec<FileStats>) -> Self {
+        let mut total = FileStats { file: "total".to_string(), ..Default::default() };
+        files.iter().for_each(|file| total.add(file));
+        ProjectStats { version: env!("CARGO_PKG_VERSION").to_string(), total, files, empty: Vec::new() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_stats() {
+        let input = r#"ast = {
+            block_00000 = { {"savetitle", text="第一章"}, text = { ja = { { name = {"妃愛"}, "「お兄」" } } } },
+            block_00001 = { text = { ja = { { "朝だ。" } } } },
+        }"#;
+        let ast = crate::parse_tokens(&crate::tokenize(input).unwrap()).unwrap();
+        let length = LengthOptions { max_length: Some(10), width_mode: Default::default(), columns: 48, max_rows: None };
+        let translation = vec!["\"Big brother\"".to_string(), String::new()];
+        let file = FileStats::of(Path::new("a.ast"), &ast, None, Some(&translation), &ScenarioOptions::default(), &length).unwrap();
+        let stats = ProjectStats::new(vec![file]);
+        assert_eq!(stats.total, FileStats {
+            file: "total".to_string(), blocks: 2, dialogue: 1, narration: 1, characters: 7, sections: 1,
+            translated: Some(1), too_long: 1, too_many_rows: 0,
+        });
+        assert!(!serde_json::to_string(&stats).unwrap().contains("お兄"));
+    }
+}
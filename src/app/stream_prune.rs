@@ -0,0 +1,280 @@
+use std::io::{BufRead, Write};
+use anyhow::{Result, anyhow};
+
+/// Entries of a block that survive pruning, as in `prune_ast`.
+const KEPT_KEYS: &[&[u8]] = &[b"linknext", b"line"];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    Open,
+    Close,
+    Comma,
+    Equal,
+    /// Whitespace and comments
+    Space,
+    /// Identifiers and numbers
+    Word,
+    /// String literals and `[..]` sp tags, kept exactly as written
+    Literal,
+}
+
+struct RawToken {
+    kind: Kind,
+    text: Vec<u8>,
+}
+
+/// Splits a script into tokens without decoding them, reading one byte at a
+/// time so memory use does not grow with the file. Every structural
+/// character is ASCII, so multi-byte UTF-8 passes through untouched.
+struct Lexer<R> {
+    input: R,
+    peeked: Option<u8>,
+}
+
+impl<R: BufRead> Lexer<R> {
+    fn new(input: R) -> Self {
+        Lexer { input, peeked: None }
+    }
+
+    fn byte(&mut self) -> Result<Option<u8>> {
+        if let Some(b) = self.peeked.take() {
+            return Ok(Some(b));
+        }
+        let buffer = self.input.fill_buf()?;
+        let Some(&b) = buffer.first() else {
+            return Ok(None);
+        };
+        self.input.consume(1);
+        Ok(Some(b))
+    }
+
+    fn peek(&mut self) -> Result<Option<u8>> {
+        if self.peeked.is_none() {
+            self.peeked = self.byte()?;
+        }
+        Ok(self.peeked)
+    }
+
+    /// Reads up to and including `end`, honouring backslash escapes.
+    fn until(&mut self, text: &mut Vec<u8>, end: u8, escapes: bool) -> Result<()> {
+        loop {
+            let b = self.byte()?.ok_or_else(|| anyhow!("Unexpected end of input inside a literal"))?;
+            text.push(b);
+            if escapes && b == b'\\' {
+                text.push(self.byte()?.ok_or_else(|| anyhow!("Incomplete escape sequence"))?);
+            } else if b == end {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Reads a comment whose first `-` is in `text`: to the end of the line,
+    /// or to the `]]` closing a long `--[[` / `--[==[` comment.
+    fn comment(&mut self, text: &mut Vec<u8>) -> Result<()> {
+        text.push(self.byte()?.unwrap());
+        let mut level = None;
+        if self.peek()? == Some(b'[') {
+            text.push(self.byte()?.unwrap());
+            let mut equals = 0;
+            while self.peek()? == Some(b'=') {
+                text.push(self.byte()?.unwrap());
+                equals += 1;
+            }
+            if self.peek()? == Some(b'[') {
+                level = Some(equals);
+            }
+        }
+        let Some(level) = level else {
+            while let Some(b) = self.peek()?.filter(|&b| b != b'\n') {
+                text.push(b);
+                self.peeked = None;
+            }
+            return Ok(());
+        };
+        let mut close = vec![b']'];
+        close.extend(std::iter::repeat_n(b'=', level));
+        close.push(b']');
+        // skip the second `[` so `--[[]]` does not close on itself
+        text.push(self.byte()?.unwrap());
+        while !text.ends_with(&close) {
+            text.push(self.byte()?.ok_or_else(|| anyhow!("Unterminated block comment"))?);
+        }
+        Ok(())
+    }
+
+    /// Reads the rest of a long-bracket string whose first `[` is in `text`,
+    /// up to its `]]` / `]==]`.
+    fn long_string(&mut self, text: &mut Vec<u8>) -> Result<()> {
+        let mut close = vec![b']'];
+        while self.peek()? == Some(b'=') {
+            text.push(self.byte()?.unwrap());
+            close.push(b'=');
+        }
+        close.push(b']');
+        text.push(self.byte()?.filter(|&b| b == b'[').ok_or_else(|| anyhow!("Malformed long bracket"))?);
+        while !text.ends_with(&close) {
+            text.push(self.byte()?.ok_or_else(|| anyhow!("Unexpected end of input inside a long string"))?);
+        }
+        Ok(())
+    }
+
+    fn next(&mut self) -> Result<Option<RawToken>> {
+        let Some(b) = self.byte()? else {
+            return Ok(None);
+        };
+        let mut text = vec![b];
+        let kind = match b {
+            b'{' => Kind::Open,
+            b'}' => Kind::Close,
+            b',' | b';' => Kind::Comma,
+            b'=' => Kind::Equal,
+            b'"' | b'\'' => {
+                self.until(&mut text, b, true)?;
+                Kind::Literal
+            }
+            b'[' if matches!(self.peek()?, Some(b'[' | b'=')) => {
+                self.long_string(&mut text)?;
+                Kind::Literal
+            }
+            b'[' => {
+                // a quoted key may itself contain `]`
+                if let Some(quote @ (b'"' | b'\'')) = self.peek()? {
+                    text.push(self.byte()?.unwrap());
+                    self.until(&mut text, quote, true)?;
+                }
+                self.until(&mut text, b']', false)?;
+                Kind::Literal
+            }
+            b'-' if self.peek()? == Some(b'-') => {
+                self.comment(&mut text)?;
+                Kind::Space
+            }
+            _ if b.is_ascii_whitespace() => {
+                while let Some(b) = self.peek()?.filter(u8::is_ascii_whitespace) {
+                    text.push(b);
+                    self.peeked = None;
+                }
+                Kind::Space
+            }
+            _ => {
+                while let Some(b) = self.peek()?.filter(|b| !b"{},;=\"'[".contains(b) && !b.is_ascii_whitespace()) {
+                    text.push(b);
+                    self.peeked = None;
+                }
+                Kind::Word
+            }
+        };
+        Ok(Some(RawToken { kind, text }))
+    }
+
+    fn next_significant(&mut self) -> Result<RawToken> {
+        loop {
+            match self.next()? {
+                Some(token) if token.kind == Kind::Space => continue,
+                Some(token) => return Ok(token),
+                None => return Err(anyhow!("Unexpected end of input inside a block")),
+            }
+        }
+    }
+}
+
+/// Passes one block item through to `output`, or drops it when `output` is
+/// `None`, starting with the already-read `first` token. Returns the comma or
+/// closing brace that ended the item.
+fn item<R: BufRead>(lexer: &mut Lexer<R>, first: RawToken, mut output: Option<&mut dyn Write>) -> Result<Kind> {
+    let mut depth = 0usize;
+    let mut token = first;
+    loop {
+        match token.kind {
+            Kind::Comma | Kind::Close if depth == 0 => return Ok(token.kind),
+            Kind::Open => depth += 1,
+            Kind::Close => depth -= 1,
+            _ => {}
+        }
+        if let Some(output) = output.as_mut() {
+            if depth > 0 || token.kind != Kind::Space {
+                output.write_all(&token.text)?;
+            }
+        }
+        token = lexer.next()?.ok_or_else(|| anyhow!("Unexpected end of input inside a block"))?;
+    }
+}
+
+/// Filters the items of one block whose `{` has been written, through its `}`.
+fn prune_block<R: BufRead>(lexer: &mut Lexer<R>, output: &mut dyn Write) -> Result<()> {
+    loop {
+        let token = lexer.next_significant()?;
+        let end = match token.kind {
+            Kind::Close => Kind::Close,
+            Kind::Comma => continue,
+            Kind::Word if KEPT_KEYS.contains(&token.text.as_slice()) => {
+                let next = lexer.next_significant()?;
+                if next.kind == Kind::Equal {
+                    output.write_all(b"\n\t\t")?;
+                    output.write_all(&token.text)?;
+                    output.write_all(b" = ")?;
+                    let value = lexer.next_significant()?;
+                    let end = item(lexer, value, Some(&mut *output))?;
+                    if end == Kind::Comma {
+                        output.write_all(b",")?;
+                    }
+                    end
+                } else {
+                    item(lexer, next, None)?
+                }
+            }
+            _ => item(lexer, token, None)?,
+        };
+        if end == Kind::Close {
+            output.write_all(b"\n\t}")?;
+            return Ok(());
+        }
+    }
+}
+
+/// Same result as `prune_ast`, computed as a token filter over `input` so
+/// the script is never held in memory. Everything outside the blocks of `ast`
+/// is copied verbatim.
+pub fn prune(input: impl BufRead, mut output: impl Write) -> Result<()> {
+    let mut lexer = Lexer::new(input);
+    let mut depth = 0usize;
+    let mut statement_key: Vec<u8> = Vec::new();
+    let mut in_ast = false;
+    while let Some(token) = lexer.next()? {
+        output.write_all(&token.text)?;
+        match token.kind {
+            Kind::Word if depth == 0 => statement_key = token.text,
+            Kind::Open => {
+                if depth == 0 {
+                    in_ast = statement_key == b"ast";
+                }
+                depth += 1;
+                if in_ast && depth == 2 {
+                    prune_block(&mut lexer, &mut output)?;
+                    depth -= 1;
+                }
+            }
+            Kind::Close => depth = depth.checked_sub(1).ok_or_else(|| anyhow!("Unmatched '}}'"))?,
+            _ => {}
+        }
+    }
+    output.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stream_prune() {
+        let input = "-- generated {\nastver = 2.0\nast = {\n\tblock_00000 = {\n\t\t{\"bg\", file=\"{bg}\"},\n\t\ttext = {\n\t\t\tja = { { \"「お兄\\\"」\" } }, --[[ } ]]\n\t\t},\n\t\tlinknext = \"block_00001\",\n\t\tline = 18,\n\t},\n\tblock_00001 = {\n\t\tline = 20\n\t},\n}\n";
+        let mut output = Vec::new();
+        prune(input.as_bytes(), &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert_eq!(output, "-- generated {\nastver = 2.0\nast = {\n\tblock_00000 = {\n\t\tlinknext = \"block_00001\",\n\t\tline = 18,\n\t},\n\tblock_00001 = {\n\t\tline = 20\n\t},\n}\n");
+
+        let pruned = crate::parse_tokens(&crate::tokenize(&output).unwrap()).unwrap();
+        assert!(crate::extract_secnario(&pruned, None, &Default::default()).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,62 @@
+use std::path::Path;
+use anyhow::{Result, anyhow};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use crate::{IndentStyle, Newlines, WriteOptions};
+
+/// How a game formats its scripts, read from `--style` so each project can
+/// keep one file instead of repeating the flags.
+#[derive(Deserialize, JsonSchema, Debug, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Style {
+    /// `tabs` or `spaces`
+    #[serde(default)]
+    pub indent: Option<IndentStyle>,
+    /// Spaces per level when indenting with spaces
+    #[serde(default)]
+    pub indent_width: Option<usize>,
+    /// Write a separator after the last entry of every table too
+    #[serde(default)]
+    pub trailing_comma: Option<bool>,
+    /// Write fields as `key = value` instead of `key=value`
+    #[serde(default)]
+    pub spaced_equals: Option<bool>,
+    /// `escape` line breaks in strings as `\n`, or write them `raw` in long brackets
+    #[serde(default)]
+    pub newlines: Option<Newlines>,
+}
+
+impl Style {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path).map_err(|e| anyhow!("Failed to read style {}: {}", path.display(), e))?;
+        serde_yaml::from_str(&content).map_err(|e| anyhow!("{}: {}", path.display(), e))
+    }
+
+    /// Fills in what the command line left unset.
+    pub fn apply(&self, options: &mut WriteOptions) {
+        options.indent = options.indent.or(self.indent);
+        options.indent_width = options.indent_width.or(self.indent_width);
+        options.trailing_comma |= self.trailing_comma.unwrap_or(false);
+        options.spaced_equals |= self.spaced_equals.unwrap_or(false);
+        options.newlines = options.newlines.or(self.newlines);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_style() {
+        let style: Style = serde_yaml::from_str("indent: spaces\nindent_width: 2\ntrailing_comma: true\nspaced_equals: true\n").unwrap();
+        let mut options = WriteOptions::default();
+        style.apply(&mut options);
+        let ast = crate::parse_tokens(&crate::tokenize(r#"t = { "a", b = { 1 } }"#).unwrap()).unwrap();
+        assert_eq!(crate::reconstruct_script(&ast, &options).unwrap(), "t = {\n  \"a\",\n  b = {\n    1,\n  },\n}\n");
+
+        let mut options = WriteOptions { indent: Some(IndentStyle::Tabs), ..Default::default() };
+        style.apply(&mut options);
+        assert_eq!(options.indent_unit(), "\t");
+        assert!(serde_yaml::from_str::<Style>("indent: tab\n").is_err());
+    }
+}
@@ -0,0 +1,113 @@
+use anyhow::Result;
+use crate::{ParseOptions, Span, Token, compat::{self, AstVersion}};
+
+/// The tokens just read, as far as naming the next table goes.
+enum Previous {
+    Identifier(String),
+    /// `name =`
+    Key(String),
+    Equal,
+    Other,
+}
+
+/// Follows the table nesting of a token stream to pick out the string
+/// literals extraction reads, `ast = { block_* = { text = { ja = { { "..." } } } } }`,
+/// or those of another language channel, without building any values.
+pub struct TextScanner {
+    version: AstVersion,
+    lang: String,
+    /// The key each open table was assigned to, `None` for positional ones
+    labels: Vec<Option<String>>,
+    previous: Previous,
+}
+
+impl TextScanner {
+    pub fn new(version: AstVersion, lang: &str) -> Self {
+        TextScanner { version, lang: lang.to_string(), labels: Vec::new(), previous: Previous::Other }
+    }
+
+    /// Takes the next token, returning the block it is in when it is a line of text.
+    pub fn feed(&mut self, token: &Token) -> Option<&str> {
+        let previous = std::mem::replace(&mut self.previous, Previous::Other);
+        match token {
+            Token::Identifier(name) => self.previous = Previous::Identifier(name.clone()),
+            Token::Equal => {
+                self.previous = match previous {
+                    Previous::Identifier(name) => Previous::Key(name),
+                    _ => Previous::Equal,
+                }
+            }
+            Token::OpenBrace => self.labels.push(match previous {
+                Previous::Key(name) => Some(name),
+                _ => None,
+            }),
+            Token::CloseBrace => {
+                self.labels.pop();
+            }
+            Token::StringLiteral(_) if !matches!(previous, Previous::Key(_) | Previous::Equal) => {
+                if let [Some(ast), Some(block), Some(text), Some(lang), None] = self.labels.as_slice() {
+                    if ast == "ast" && compat::is_block_name(self.version, block) && text == "text" && *lang == self.lang {
+                        return Some(block);
+                    }
+                }
+            }
+            _ => {}
+        }
+        None
+    }
+}
+
+/// The lines of a script in script order, read straight from its tokens.
+/// The astver layout is taken from the lines before the `ast` table.
+pub fn texts(input: &str, lang: &str, parse: &ParseOptions) -> Result<Vec<String>> {
+    Ok(literals(input, lang, parse)?.into_iter().map(|(_, text, _)| text).collect())
+}
+
+/// `(block, text, span of the literal)` of every line of a script, in script order.
+pub fn literals(input: &str, lang: &str, parse: &ParseOptions) -> Result<Vec<(String, String, Span)>> {
+    let mut tokens = crate::Tokenizer::new(input).options(&parse.read());
+    let mut header = Vec::new();
+    for token in tokens.by_ref() {
+        let token = token?;
+        let open = token.0 == Token::OpenBrace;
+        header.push(token);
+        if open {
+            break;
+        }
+    }
+    let mut scanner = TextScanner::new(compat::version_of_tokens(&header, parse.astver), lang);
+    let mut literals = Vec::new();
+    for token in header.into_iter().map(Ok).chain(tokens) {
+        let (token, span) = token?;
+        if let Some(block) = scanner.feed(&token) {
+            let block = block.to_string();
+            if let Token::StringLiteral(text) = token {
+                literals.push((block, text, span));
+            }
+        }
+    }
+    Ok(literals)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scanned_texts_match_extraction() {
+        let input = r#"astver = 2.0
+            ast = {
+                block_00000 = {
+                    {"bg", file="bg001a"},
+                    text = { ja = { { name = {"妃愛"}, "「お兄」", {"rt2"}, "「朝」" } }, en = { { "Hey" } } },
+                    linknext = "block_00001",
+                },
+                block_00001 = { text = { ja = { { "朝だ。", voice = "x" } } } },
+                system = { text = { ja = { { "menu" } } } },
+            }"#;
+        let ast = crate::parse_tokens(&crate::tokenize(input).unwrap()).unwrap();
+        let extracted = crate::extract_secnario(&ast, None, &crate::ScenarioOptions::default()).unwrap();
+        assert_eq!(texts(input, "ja", &ParseOptions::default()).unwrap(), extracted);
+        assert_eq!(extracted, vec!["「お兄」", "「朝」", "朝だ。"]);
+    }
+}
@@ -0,0 +1,69 @@
+use crate::{LuaTable, Value};
+
+/// Commands whose `time` is a pause rather than the duration of an effect.
+const WAIT_COMMANDS: &[&str] = &["wait", "wt"];
+
+#[derive(Debug, Default, PartialEq)]
+pub struct BlockTiming {
+    pub block: String,
+    /// Sum of every `time=` attribute in the block, in milliseconds
+    pub total: f64,
+    /// Part of `total` coming from wait commands
+    pub wait: f64,
+}
+
+fn as_millis(value: &Value) -> Option<f64> {
+    value.as_integer().map(|i| i as f64).or_else(|| value.as_float())
+}
+
+pub fn block_timings(ast: &LuaTable, astver: Option<crate::compat::AstVersion>) -> Vec<BlockTiming> {
+    let mut timings = Vec::new();
+    for (block_key, block) in crate::iter_blocks(ast, astver) {
+        let mut timing = BlockTiming { block: block_key.clone(), ..Default::default() };
+        for item in block.array.iter() {
+            let Some(time) = crate::command_attr(item, "time").and_then(as_millis) else {
+                continue;
+            };
+            timing.total += time;
+            if crate::command_name(item).is_some_and(|name| WAIT_COMMANDS.contains(&name)) {
+                timing.wait += time;
+            }
+        }
+        timings.push(timing);
+    }
+    timings
+}
+
+pub fn print_report(timings: &[BlockTiming]) {
+    println!("{:<16}{:>12}{:>12}", "block", "total(s)", "wait(s)");
+    for t in timings {
+        println!("{:<16}{:>12.1}{:>12.1}", t.block, t.total / 1000.0, t.wait / 1000.0);
+    }
+    let total: f64 = timings.iter().map(|t| t.total).sum();
+    let wait: f64 = timings.iter().map(|t| t.wait).sum();
+    println!("{:<16}{:>12.1}{:>12.1}", "scene", total / 1000.0, wait / 1000.0);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_block_timings() {
+        let input = r#"ast = {
+            block_00000 = {
+                {"bg", time=2000, file="bg001a", path=":bg/"},
+                {"wait", time=500},
+                {"se", file="seアラーム", loop=1, id=1},
+                {"fg", ch="妃愛", time=250.5},
+                linknext = "block_00001",
+            },
+        }
+        "#;
+
+        let tokens = crate::tokenize(input).unwrap();
+        let ast = crate::parse_tokens(&tokens).unwrap();
+        let timings = block_timings(&ast, None);
+        assert_eq!(timings, vec![BlockTiming { block: "block_00000".to_string(), total: 2750.5, wait: 500.0 }]);
+    }
+}
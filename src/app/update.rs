@@ -0,0 +1,67 @@
+use std::process::Command;
+use anyhow::{Result, anyhow};
+use serde::Deserialize;
+
+const LATEST_RELEASE_URL: &str = "https://api.github.com/repos/xmoezzz/artemis_ast/releases/latest";
+
+#[derive(Deserialize, Debug)]
+struct Release {
+    tag_name: String,
+    html_url: String,
+}
+
+/// `v1.2.3` or `1.2` -> `[1, 2, 3]` / `[1, 2]`; anything after a `-` (pre-release) is ignored.
+fn parse_version(version: &str) -> Option<Vec<u64>> {
+    let version = version.trim().trim_start_matches('v');
+    let version = version.split('-').next()?;
+    version.split('.').map(|part| part.parse().ok()).collect()
+}
+
+/// Whether `latest` is a newer release than `current`. Tags that are not
+/// version numbers never count as newer.
+fn is_newer(latest: &str, current: &str) -> bool {
+    match (parse_version(latest), parse_version(current)) {
+        (Some(latest), Some(current)) => latest > current,
+        _ => false,
+    }
+}
+
+/// Asks the GitHub releases API for the latest build. Uses curl, which ships
+/// with Windows 10 and every Unix the tool runs on, rather than bundling an
+/// HTTP client for one request.
+fn latest_release() -> Result<Release> {
+    let output = Command::new("curl")
+        .args(["-sSfL", "-H", "Accept: application/vnd.github+json", LATEST_RELEASE_URL])
+        .output()
+        .map_err(|e| anyhow!("Failed to run curl: {}", e))?;
+    if !output.status.success() {
+        return Err(anyhow!("Could not reach {}: {}", LATEST_RELEASE_URL, String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(serde_json::from_slice(&output.stdout)?)
+}
+
+pub fn check() -> Result<()> {
+    let current = env!("CARGO_PKG_VERSION");
+    let release = latest_release()?;
+    if is_newer(&release.tag_name, current) {
+        println!("A newer version is available: {} (this is {})", release.tag_name, current);
+        println!("Download it from {}", release.html_url);
+    } else {
+        println!("artemis_ast {} is up to date", current);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_newer() {
+        assert!(is_newer("v0.2.0", "0.1.0"));
+        assert!(is_newer("0.1.10", "0.1.9"));
+        assert!(!is_newer("v0.1.0", "0.1.0"));
+        assert!(!is_newer("v0.1.0-rc1", "0.1.0"));
+        assert!(!is_newer("nightly", "0.1.0"));
+    }
+}
@@ -0,0 +1,138 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use anyhow::Result;
+use crate::{Span, Token, WriteOptions};
+
+/// Alternative texts of one translated line by variant name, e.g. `female`
+/// for a female player or `plural` for a plural form.
+pub type Variants = BTreeMap<String, String>;
+
+/// `ja` -> `ja_female`, the channel a variant is written to.
+pub fn channel_name(channel: &str, variant: &str) -> String {
+    format!("{}_{}", channel, variant)
+}
+
+/// `(entry, variant)` of every variant the engine has no channel for.
+pub fn unsupported<'a>(variants: &'a [Variants], supported: &[String]) -> Vec<(usize, &'a str)> {
+    variants.iter()
+        .enumerate()
+        .flat_map(|(index, entry)| entry.keys().map(move |name| (index, name.as_str())))
+        .filter(|(_, name)| !supported.iter().any(|s| s == name))
+        .collect()
+}
+
+/// A text channel such as `ja = { {...}, ... }` with the lines in it.
+struct Channel {
+    name: String,
+    /// Where the channel's name starts, for its indentation
+    name_start: usize,
+    /// The table from `{` to `}`
+    table: Span,
+    lines: Vec<(Span, String)>,
+}
+
+/// The channels extraction reads, `ast = { block_* = { text = { ja = { { "..." } } } } }`.
+fn channels(tokens: &[(Token, Span)]) -> Vec<Channel> {
+    let mut labels: Vec<(Option<String>, usize)> = Vec::new();
+    let mut found = Vec::new();
+    let mut lines = Vec::new();
+    for (index, (token, span)) in tokens.iter().enumerate() {
+        let previous = index.checked_sub(1).map(|i| &tokens[i].0);
+        let path: Vec<Option<&str>> = labels.iter().map(|(label, _)| label.as_deref()).collect();
+        let in_text = matches!(path.as_slice(), [Some("ast"), Some(block), Some("text"), Some("ja"), ..] if block.starts_with("block_"));
+        let depth = path.len();
+        match token {
+            Token::OpenBrace => {
+                let label = match (index.checked_sub(2).map(|i| &tokens[i].0), previous) {
+                    (Some(Token::Identifier(name)), Some(Token::Equal)) => Some(name.clone()),
+                    _ => None,
+                };
+                labels.push((label, index));
+            }
+            Token::CloseBrace => {
+                let Some((label, open)) = labels.pop() else {
+                    continue;
+                };
+                if in_text && depth == 4 {
+                    found.push(Channel {
+                        name: label.unwrap_or_default(),
+                        name_start: tokens[open - 2].1.start,
+                        table: tokens[open].1.start..span.end,
+                        lines: std::mem::take(&mut lines),
+                    });
+                }
+            }
+            // the bare strings of a line table, `{ name = {...}, "...", {"rt2"} }`
+            Token::StringLiteral(text) if in_text && depth == 5 && path[4].is_none() && previous != Some(&Token::Equal) => {
+                lines.push((span.clone(), text.clone()));
+            }
+            _ => {}
+        }
+    }
+    found
+}
+
+/// Writes, after every channel holding a line with variants, one copy of the
+/// channel per supported variant, with the variant texts in place of the
+/// translation. `script` is the merged script; `texts` and `variants` are
+/// the translation entries.
+pub fn apply(script: &str, texts: &[String], variants: &[Variants], supported: &[String], options: &WriteOptions) -> Result<String> {
+    let by_text: HashMap<&str, &Variants> = texts.iter()
+        .zip(variants)
+        .filter(|(_, entry)| !entry.is_empty())
+        .map(|(text, entry)| (text.as_str(), entry))
+        .collect();
+    let tokens = crate::tokenize_spanned(script)?;
+    let mut insertions = Vec::new();
+    for channel in channels(&tokens) {
+        let names: BTreeSet<&String> = channel.lines.iter()
+            .filter_map(|(_, text)| by_text.get(text.as_str()))
+            .flat_map(|entry| entry.keys())
+            .filter(|name| supported.contains(name))
+            .collect();
+        let line_start = script[..channel.name_start].rfind('\n').map_or(0, |i| i + 1);
+        let indent: String = script[line_start..].chars().take_while(|c| *c == ' ' || *c == '\t').collect();
+        for name in names {
+            let mut copy = String::new();
+            let mut at = channel.table.start;
+            for (span, text) in channel.lines.iter() {
+                copy.push_str(&script[at..span.start]);
+                let variant = by_text.get(text.as_str()).and_then(|entry| entry.get(name)).unwrap_or(text);
+                copy.push_str(&crate::string_to_script(variant, options));
+                at = span.end;
+            }
+            copy.push_str(&script[at..channel.table.end]);
+            insertions.push((channel.table.end, format!(",\n{}{} = {}", indent, channel_name(&channel.name, name), copy)));
+        }
+    }
+    let mut output = String::with_capacity(script.len());
+    let mut at = 0;
+    for (offset, text) in insertions {
+        output.push_str(&script[at..offset]);
+        output.push_str(&text);
+        at = offset;
+    }
+    output.push_str(&script[at..]);
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_variants() {
+        let script = "ast = {\n\tblock_00000 = {\n\t\ttext = {\n\t\t\tja = {\n\t\t\t\t{ name = {\"Hiyori\"}, \"You're up, brother?\" },\n\t\t\t\t{ \"Morning.\" },\n\t\t\t},\n\t\t},\n\t},\n}\n";
+        let texts = vec!["You're up, brother?".to_string(), "Morning.".to_string()];
+        let variants = vec![
+            Variants::from([("female".to_string(), "You're up, sis?".to_string()), ("plural".to_string(), "-".to_string())]),
+            Variants::new(),
+        ];
+        let supported = vec!["female".to_string()];
+        assert_eq!(unsupported(&variants, &supported), vec![(0, "plural")]);
+
+        let merged = apply(script, &texts, &variants, &supported, &WriteOptions::default()).unwrap();
+        assert!(merged.contains("\t\t\t},\n\t\t\tja_female = {\n\t\t\t\t{ name = {\"Hiyori\"}, \"You're up, sis?\" },\n\t\t\t\t{ \"Morning.\" },\n\t\t\t},\n\t\t},"), "{}", merged);
+        let ast = crate::parse_tokens(&crate::tokenize(&merged).unwrap()).unwrap();
+        assert_eq!(crate::extract_secnario(&ast, None, &crate::ScenarioOptions::default()).unwrap(), texts);
+    }
+}
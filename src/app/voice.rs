@@ -0,0 +1,118 @@
+use std::collections::HashMap;
+use crate::{LuaTable, Value};
+
+fn strip_key(value: &mut Value, key: &str) {
+    if let Value::Table(table) = value {
+        table.remove(key);
+        table.values_mut().for_each(|item| strip_key(item, key));
+    }
+}
+
+/// Removes every `vo` table from the script.
+pub fn strip_vo(ast: &mut LuaTable) {
+    if let Some(ast_table) = ast.get_mut("ast") {
+        strip_key(ast_table, "vo");
+    }
+}
+
+/// The `name` shown on the first named line of any channel of a `text` table.
+pub fn speaker(text: &LuaTable) -> Option<&String> {
+    text.fields()
+        .filter_map(|(_, channel)| channel.as_table())
+        .flat_map(|channel| channel.array.iter())
+        .filter_map(Value::as_table)
+        .find_map(|line| line.get("name"))?
+        .as_table()?
+        .array
+        .first()?
+        .as_string()
+}
+
+/// The `ch` of the `vo` entry in a `text` table, naming the character who is heard.
+pub fn vo_character(text: &LuaTable) -> Option<&String> {
+    text.get("vo")?
+        .as_table()?
+        .array
+        .iter()
+        .filter_map(Value::as_table)
+        .find_map(|vo| vo.get("ch"))?
+        .as_string()
+}
+
+/// The `text` tables of every block, with the block's name.
+fn text_tables(ast: &LuaTable, astver: Option<crate::compat::AstVersion>) -> impl Iterator<Item = (&String, &LuaTable)> {
+    crate::iter_blocks(ast, astver).filter_map(|(block_key, block)| Some((block_key, block.get("text")?.as_table()?)))
+}
+
+/// Maps each vo `ch` to the `name` shown on the lines it voices, taking the
+/// first name seen, so narrated lines voiced by the same character can be
+/// attributed to them.
+pub fn speaker_names(ast: &LuaTable, astver: Option<crate::compat::AstVersion>) -> HashMap<String, String> {
+    let mut names = HashMap::new();
+    for (_, text) in text_tables(ast, astver) {
+        if let (Some(ch), Some(name)) = (vo_character(text), speaker(text)) {
+            names.entry(ch.clone()).or_insert_with(|| name.clone());
+        }
+    }
+    names
+}
+
+/// Returns `(block, speaker)` for every named line whose `text` table has no `vo` entry.
+pub fn missing_vo(ast: &LuaTable, astver: Option<crate::compat::AstVersion>) -> Vec<(String, String)> {
+    let mut missing = Vec::new();
+    for (block_key, text) in text_tables(ast, astver) {
+        if text.contains_key("vo") {
+            continue;
+        }
+        if let Some(name) = speaker(text) {
+            missing.push((block_key.clone(), name.clone()));
+        }
+    }
+    missing
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_vo() {
+        let input = r#"ast = {
+            block_00000 = {
+                text = {
+                    vo = {
+                        {"vo", file="fem_hiy_00052", ch="hiy"},
+                    },
+                    ja = {
+                        {
+                            name = {"妃愛"},
+                            "「お兄、あさー……むふー……」",
+                        },
+                    },
+                },
+            },
+        }
+        "#;
+
+        let tokens = crate::tokenize(input).unwrap();
+        let mut ast = crate::parse_tokens(&tokens).unwrap();
+        assert!(missing_vo(&ast, None).is_empty());
+        strip_vo(&mut ast);
+        assert_eq!(missing_vo(&ast, None), vec![("block_00000".to_string(), "妃愛".to_string())]);
+        assert!(!crate::reconstruct_script(&ast, &crate::WriteOptions::default()).unwrap().contains("vo"));
+    }
+
+    #[test]
+    fn test_speaker_names() {
+        let input = r#"ast = {
+            block_00000 = { text = { vo = { {"vo", file="fem_hiy_00052", ch="hiy"} }, ja = { { name = {"妃愛"}, "「お兄」" } } } },
+            block_00001 = { text = { vo = { {"vo", file="fem_hiy_00053", ch="hiy"} }, ja = { { "（眠い……）" } } } },
+        }
+        "#;
+        let ast = crate::parse_tokens(&crate::tokenize(input).unwrap()).unwrap();
+        assert_eq!(speaker_names(&ast, None).get("hiy").map(String::as_str), Some("妃愛"));
+        let blocks = crate::extract_blocks(&ast, None, "ja").unwrap();
+        let narration = blocks.iter().flat_map(|block| block.texts.iter()).find(|(kind, _, _)| *kind == crate::LineKind::Narration);
+        assert_eq!(narration.and_then(|(_, _, voice)| voice.as_deref()), Some("hiy"));
+    }
+}
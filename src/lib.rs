@@ -0,0 +1,17 @@
+//! Extracts the text of Artemis engine `.ast` scripts for translation and
+//! merges it back. [`syntax`] reads and writes scripts on its own, without
+//! std or any dependency; everything else, from files to the command line,
+//! needs the `cli` feature, on by default.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+pub mod syntax;
+
+pub use syntax::{LuaTable, Value, WriteOptions};
+
+#[cfg(feature = "cli")]
+mod app;
+#[cfg(feature = "cli")]
+pub use app::*;
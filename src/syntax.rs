@@ -0,0 +1,58 @@
+//! The script syntax alone: the tokenizer, the parser and the writer, with
+//! no files, logging or command line, so that it builds with `alloc` only
+//! when the `std` feature is off.
+
+use alloc::string::String;
+use core::fmt;
+
+mod lexer;
+mod parser;
+mod table;
+mod value;
+mod writer;
+
+pub use lexer::{Span, SpannedToken, Token, Tokenizer, line_column, long_bracket_level, tokenize_spanned};
+pub use parser::{DEFAULT_MAX_DEPTH, DuplicateKeys, ParseError, ReadOptions, SpanTable, parse, parse_spans};
+pub use table::{CommentSlot, LuaTable};
+pub use value::Value;
+pub use writer::{AsciiEscape, IndentStyle, Newlines, NonFinite, QuoteStyle, Separator, WriteOptions, string_to_script, write, write_script};
+
+#[cfg(feature = "cli")]
+pub(crate) use parser::{TokenStream, next_is_equal, parse_error, parse_stream, parse_top_level, parse_value};
+#[cfg(test)]
+pub(crate) use lexer::tokenize;
+#[cfg(test)]
+pub(crate) use parser::parse_tokens;
+
+type Result<T, E = Error> = core::result::Result<T, E>;
+
+/// Why a script could not be read or written.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    /// A token could not be read, at this 1-based line and column
+    Lex { message: String, line: usize, column: usize },
+    /// The tokens do not make a script
+    Parse(ParseError),
+    /// A value has no literal, such as NaN, or the writer failed
+    Write(String),
+    /// A [`Value`] converted to a type it does not hold
+    Type(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Lex { message, line, column } => write!(f, "{} at line {}, column {}", message, line, column),
+            Error::Parse(e) => write!(f, "{}", e),
+            Error::Write(message) | Error::Type(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl core::error::Error for Error {}
+
+impl From<fmt::Error> for Error {
+    fn from(_: fmt::Error) -> Self {
+        Error::Write("Failed to write the script".into())
+    }
+}
@@ -0,0 +1,418 @@
+use alloc::{format, string::{String, ToString}, vec::Vec};
+use core::{ops::Range, str::Chars};
+use super::{Error, ReadOptions, writer::{format_float, unusual_literal}};
+
+/// A token of a script.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Token {
+    Equal,                // "="
+    OpenBrace,            // "{"
+    CloseBrace,           // "}"
+    Comma,                // "," or ";"
+    Identifier(String),   // "astver", "text" 等
+    StringLiteral(String),// "2.0", "俺たちの新しい日常" 等
+    IntegerLiteral(i64, Option<String>),  // 整数, 以及与默认写法不同的原文
+    FloatLiteral(f64, Option<String>),    // 浮点数, 以及与默认写法不同的原文
+    SpTagContent(Option<i64>),
+    StringKey(String),    // ["save title"]
+    Comment(String),      // "-- 注释", 仅在保留注释时产生
+}
+
+/// Byte range of a token in the script.
+pub type Span = Range<usize>;
+
+/// Character iterator over a script that knows the byte offset of the next character.
+#[derive(Clone)]
+struct Cursor<'a> {
+    chars: Chars<'a>,
+    input_len: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(input: &'a str) -> Self {
+        Cursor { chars: input.chars(), input_len: input.len() }
+    }
+
+    fn offset(&self) -> usize {
+        self.input_len - self.chars.as_str().len()
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.clone().next()
+    }
+
+    fn rest(&self) -> &'a str {
+        self.chars.as_str()
+    }
+
+    fn skip_bytes(&mut self, bytes: usize) {
+        self.chars = self.chars.as_str()[bytes..].chars();
+    }
+}
+
+impl Iterator for Cursor<'_> {
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        self.chars.next()
+    }
+}
+
+fn push_char(bytes: &mut Vec<u8>, ch: char) {
+    bytes.extend_from_slice(ch.encode_utf8(&mut [0; 4]).as_bytes());
+}
+
+/// Lua `\ddd`: up to three decimal digits naming a single byte.
+fn lex_decimal_escape(first: char, chars: &mut Cursor) -> Result<u8, String> {
+    let mut digits = first.to_string();
+    while digits.len() < 3 {
+        match chars.peek() {
+            Some(ch) if ch.is_ascii_digit() => digits.push(chars.next().unwrap()),
+            _ => break,
+        }
+    }
+    digits.parse::<u8>().map_err(|_| format!("Decimal escape \\{} is larger than 255", digits))
+}
+
+/// Lua `\xXX`: exactly two hex digits naming a single byte, the `\x` already consumed.
+fn lex_hex_escape(chars: &mut Cursor) -> Result<u8, String> {
+    let hex: String = [chars.next(), chars.next()].into_iter().flatten().collect();
+    if hex.len() != 2 || !hex.chars().all(|ch| ch.is_ascii_hexdigit()) {
+        return Err(format!("Malformed hex escape \\x{}", hex));
+    }
+    u8::from_str_radix(&hex, 16).map_err(|e| e.to_string())
+}
+
+/// Lua `\u{XXXX}`, the leading `\u` already consumed.
+fn lex_unicode_escape(chars: &mut Cursor) -> Result<char, String> {
+    if chars.next() != Some('{') {
+        return Err("Expected '{' after \\u".to_string());
+    }
+    let mut hex = String::new();
+    loop {
+        match chars.next() {
+            Some('}') => break,
+            Some(ch) if ch.is_ascii_hexdigit() => hex.push(ch),
+            _ => return Err(format!("Malformed unicode escape \\u{{{}", hex)),
+        }
+    }
+    u32::from_str_radix(&hex, 16)
+        .ok()
+        .and_then(char::from_u32)
+        .ok_or_else(|| format!("Invalid unicode escape \\u{{{}}}", hex))
+}
+
+/// Reads a long-bracket string whose first `[` is consumed, taking its text
+/// as written: no escapes, a line break right after the opening bracket
+/// dropped and `\r\n` read as `\n`, as Lua does.
+fn lex_long_string(level: usize, chars: &mut Cursor) -> Result<String, String> {
+    chars.skip_bytes(level + 1);
+    let rest = chars.rest();
+    let start = if rest.starts_with("\r\n") { 2 } else { usize::from(rest.starts_with('\n')) };
+    let close = format!("]{}]", "=".repeat(level));
+    let end = rest[start..].find(&close).ok_or_else(|| format!("Unexpected end of file, expected {} to close the string", close))? + start;
+    let text = rest[start..end].replace("\r\n", "\n");
+    chars.skip_bytes(end + close.len());
+    Ok(text)
+}
+
+/// Number of `=` in a Lua long bracket `[==[` at the start of `s`.
+pub fn long_bracket_level(s: &str) -> Option<usize> {
+    let level = s.strip_prefix('[')?.chars().take_while(|&c| c == '=').count();
+    (s[1 + level..].starts_with('[')).then_some(level)
+}
+
+/// Byte length of an exponent like `e-3` or `E2` at the start of `s`.
+fn exponent_len(s: &str) -> Option<usize> {
+    let digits = s.strip_prefix(['e', 'E'])?;
+    let sign = usize::from(digits.starts_with(['+', '-']));
+    let count = digits[sign..].chars().take_while(char::is_ascii_digit).count();
+    (count > 0).then_some(1 + sign + count)
+}
+
+/// Skips a Lua comment whose first `-` is consumed: `--` to the end of the
+/// line, or a long `--[[ ... ]]` / `--[==[ ... ]==]` block.
+fn skip_comment(chars: &mut Cursor) -> Result<(), String> {
+    chars.next();
+    let rest = chars.rest();
+    match long_bracket_level(rest) {
+        Some(level) => {
+            let close = format!("]{}]", "=".repeat(level));
+            let end = rest.find(&close).ok_or_else(|| "Unterminated block comment".to_string())?;
+            chars.skip_bytes(end + close.len());
+        }
+        None => chars.skip_bytes(rest.find('\n').unwrap_or(rest.len())),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+pub(crate) fn tokenize(input: &str) -> Result<Vec<Token>, Error> {
+    Ok(tokenize_spanned(input)?.into_iter().map(|(token, _)| token).collect())
+}
+
+/// 1-based line and column (in characters) of a byte offset.
+pub fn line_column(input: &str, offset: usize) -> (usize, usize) {
+    let before = &input[..offset.min(input.len())];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    (before.matches('\n').count() + 1, before[line_start..].chars().count() + 1)
+}
+
+/// Every token of `input`, with the byte range it came from.
+pub fn tokenize_spanned(input: &str) -> Result<Vec<SpannedToken>, Error> {
+    Tokenizer::new(input).collect()
+}
+
+/// A token and the byte range it came from.
+pub type SpannedToken = (Token, Span);
+
+/// Reads tokens one at a time as the parser asks for them, so a script is
+/// never held as a whole token list. Stops after the first error.
+pub struct Tokenizer<'a> {
+    input: &'a str,
+    chars: Cursor<'a>,
+    failed: bool,
+    /// Whether comments are read as [`Token::Comment`] rather than skipped
+    keep_comments: bool,
+    /// Whether unknown escapes such as `\k` are kept as written rather
+    /// than an error, and where that is reported
+    options: ReadOptions,
+}
+
+impl<'a> Tokenizer<'a> {
+    pub fn new(input: &'a str) -> Self {
+        Tokenizer { input, chars: Cursor::new(input), failed: false, keep_comments: false, options: ReadOptions::default() }
+    }
+
+    pub fn keeping_comments(mut self) -> Self {
+        self.keep_comments = true;
+        self
+    }
+
+    /// Reads escapes as `options` say: unknown ones fail with `strict`.
+    pub fn options(mut self, options: &ReadOptions) -> Self {
+        self.options = *options;
+        self
+    }
+
+    /// Starts reading at byte `offset`, still reporting lines and columns of the whole input.
+    pub fn starting_at(input: &'a str, offset: usize) -> Self {
+        let mut tokenizer = Tokenizer::new(input);
+        tokenizer.chars.skip_bytes(offset);
+        tokenizer
+    }
+}
+
+impl Iterator for Tokenizer<'_> {
+    type Item = Result<SpannedToken, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        loop {
+            let start = self.chars.offset();
+            let ch = self.chars.next()?;
+            match lex_token(ch, &mut self.chars, &self.options) {
+                Ok(Some(token)) => return Some(Ok((token, start..self.chars.offset()))),
+                Ok(None) if self.keep_comments && self.input[start..].starts_with("--") => {
+                    let comment = self.input[start..self.chars.offset()].trim_end().to_string();
+                    return Some(Ok((Token::Comment(comment), start..self.chars.offset())));
+                }
+                Ok(None) => continue,
+                Err(message) => {
+                    self.failed = true;
+                    let (line, column) = line_column(self.input, start);
+                    return Some(Err(Error::Lex { message, line, column }));
+                }
+            }
+        }
+    }
+}
+
+fn skip_spaces(chars: &mut Cursor) {
+    while chars.peek().is_some_and(char::is_whitespace) {
+        chars.next();
+    }
+}
+
+/// Reads a string literal whose opening `quote` is consumed, decoding escapes.
+/// Unknown escapes are kept as written, or fail with `options.strict`.
+fn lex_string(quote: char, chars: &mut Cursor, options: &ReadOptions) -> Result<String, String> {
+    let mut s = Vec::new();
+    let mut closed = false;
+    while let Some(ch) = chars.peek() {
+        match ch {
+            '\\' => {
+                chars.next(); // Consume the backslash
+                if let Some(escaped) = chars.next() {
+                    match escaped {
+                        'n' => s.push(b'\n'),
+                        't' => s.push(b'\t'),
+                        'r' => s.push(b'\r'),
+                        'a' => s.push(0x07),
+                        'b' => s.push(0x08),
+                        'f' => s.push(0x0C),
+                        'v' => s.push(0x0B),
+                        'x' => s.push(lex_hex_escape(chars)?),
+                        // `\z` skips the line break and indentation that follow it
+                        'z' => {
+                            while chars.peek().is_some_and(char::is_whitespace) {
+                                chars.next();
+                            }
+                        }
+                        // a backslash before a line break keeps the line break
+                        '\n' => s.push(b'\n'),
+                        '\r' => {
+                            if chars.peek() == Some('\n') {
+                                chars.next();
+                            }
+                            s.push(b'\n');
+                        }
+                        '"' => s.push(b'"'),
+                        '\'' => s.push(b'\''),
+                        '\\' => s.push(b'\\'),
+                        '0'..='9' => s.push(lex_decimal_escape(escaped, chars)?),
+                        'u' => push_char(&mut s, lex_unicode_escape(chars)?),
+                        _ if options.strict => return Err(format!("Unknown escape sequence \\{}", escaped)),
+                        _ => {
+                            // engine specific codes such as \k survive untouched
+                            if let Some(warn) = options.warn {
+                                warn(&format!("warning: passing through unknown escape sequence \\{}", escaped));
+                            }
+                            s.push(b'\\');
+                            push_char(&mut s, escaped);
+                        }
+                    }
+                } else {
+                    return Err("Incomplete escape sequence".to_string());
+                }
+            }
+            _ if ch == quote => {
+                chars.next(); // skip the closing quote
+                closed = true;
+                break;
+            }
+            _ => push_char(&mut s, chars.next().unwrap()),
+        }
+    }
+    if !closed {
+        return Err(format!("Unexpected end of file, expected {} to close the string", quote));
+    }
+    // decimal escapes may spell out multi-byte sequences
+    String::from_utf8(s).map_err(|_| "Escaped bytes are not valid UTF-8".to_string())
+}
+
+/// Reads the token starting with `ch`, `None` for whitespace and comments.
+fn lex_token(ch: char, chars: &mut Cursor, options: &ReadOptions) -> Result<Option<Token>, String> {
+    let token = match ch {
+        '=' => Token::Equal,
+        '{' => Token::OpenBrace,
+        '}' => Token::CloseBrace,
+        // Lua accepts either separator in a table constructor
+        ',' | ';' => Token::Comma,
+        '"' | '\'' => Token::StringLiteral(lex_string(ch, chars, options)?),
+        '[' => {
+            // `[[long string]]`, `[==[long string]==]`
+            let level = chars.rest().chars().take_while(|&c| c == '=').count();
+            if chars.rest()[level..].starts_with('[') {
+                return Ok(Some(Token::StringLiteral(lex_long_string(level, chars)?)));
+            }
+            skip_spaces(chars);
+            // `["save title"] = ...`
+            if let Some(quote @ ('"' | '\'')) = chars.peek() {
+                chars.next();
+                let key = lex_string(quote, chars, options)?;
+                skip_spaces(chars);
+                if chars.next() != Some(']') {
+                    return Err("Expected ']' after bracketed key".to_string());
+                }
+                return Ok(Some(Token::StringKey(key)));
+            }
+            // `[1] = ...`, or `[]`
+            let mut num_string = String::new();
+            if chars.peek() == Some('-') {
+                num_string.push('-');
+                chars.next();
+            }
+            while let Some(ch) = chars.peek().filter(char::is_ascii_digit) {
+                num_string.push(ch);
+                chars.next();
+            }
+            skip_spaces(chars);
+            match chars.next() {
+                Some(']') => {}
+                Some(ch) => return Err(format!("Unexpected character in brackets: {}", ch)),
+                None => return Err("Unexpected end of input while parsing sp content".to_string()),
+            }
+            if num_string.is_empty() {
+                Token::SpTagContent(None)
+            } else {
+                let key = num_string.parse::<i64>().map_err(|e| format!("Invalid bracketed key [{}]: {}", num_string, e))?;
+                Token::SpTagContent(Some(key))
+            }
+        }
+        _ if ch.is_whitespace() || ch == '\n' || ch == '\r' => return Ok(None),
+        '-' if chars.peek() == Some('-') => {
+            skip_comment(chars)?;
+            return Ok(None);
+        }
+        _ if ch.is_ascii_digit()
+            || (ch == '-' && (chars.peek().is_some_and(|next| next.is_ascii_digit()) || chars.rest().strip_prefix('.').is_some_and(|rest| rest.starts_with(|c: char| c.is_ascii_digit()))))
+            || (ch == '.' && chars.peek().is_some_and(|next| next.is_ascii_digit())) => {
+            let mut number = ch.to_string();
+            if ch == '-' {
+                number.push(chars.next().unwrap());
+            }
+            if number.ends_with('0') && matches!(chars.peek(), Some('x' | 'X')) {
+                number.push(chars.next().unwrap());
+                let mut digits = String::new();
+                while let Some(ch) = chars.peek().filter(char::is_ascii_hexdigit) {
+                    digits.push(ch);
+                    chars.next();
+                }
+                let value = i64::from_str_radix(&digits, 16)
+                    .map_err(|e| format!("Invalid hexadecimal literal 0x{}: {}", digits, e))?;
+                let value = if ch == '-' { -value } else { value };
+                return Ok(Some(Token::IntegerLiteral(value, unusual_literal(&(number + &digits), value.to_string()))));
+            }
+            let mut is_float = number.ends_with('.');
+            while let Some(ch) = chars.peek() {
+                if ch == '.' {
+                    is_float = true;
+                    number.push(chars.next().unwrap());
+                } else if ch.is_ascii_digit() {
+                    number.push(chars.next().unwrap());
+                } else if let Some(exponent) = exponent_len(chars.rest()) {
+                    // 1e-3, 2.5E2
+                    is_float = true;
+                    number.push_str(&chars.rest()[..exponent]);
+                    chars.skip_bytes(exponent);
+                } else {
+                    break;
+                }
+            }
+            if is_float {
+                let value = number.parse().map_err(|e| format!("Invalid number {}: {}", number, e))?;
+                Token::FloatLiteral(value, unusual_literal(&number, format_float(value)))
+            } else {
+                let value: i64 = number.parse().map_err(|e| format!("Invalid number {}: {}", number, e))?;
+                Token::IntegerLiteral(value, unusual_literal(&number, value.to_string()))
+            }
+        }
+        _ if ch.is_alphanumeric() || ch == '_' => {
+            let mut name = ch.to_string();
+            while let Some(ch) = chars.peek() {
+                if ch.is_alphanumeric() || ch == '_' {
+                    name.push(chars.next().unwrap());
+                } else {
+                    break;
+                }
+            }
+            Token::Identifier(name)
+        }
+        _ => return Err(format!("Unexpected character: {}", ch)),
+    };
+    Ok(Some(token))
+}
+
@@ -0,0 +1,313 @@
+use alloc::{collections::{BTreeMap, BTreeSet}, format, string::{String, ToString}, vec, vec::Vec};
+use core::{fmt, iter::Peekable, mem};
+use super::{CommentSlot, Error, LuaTable, Result, Span, SpannedToken, Token, Tokenizer, Value, value::sp_key};
+
+/// A parse failure at a byte offset, which [`line_column`] turns into a
+/// line and column. When parsing a bare token list the position is the
+/// token index instead.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub position: usize,
+    pub message: String,
+    /// Where the table left open by a truncated file starts
+    pub opened: Option<usize>,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (at {})", self.message, self.position)?;
+        if let Some(opened) = self.opened {
+            write!(f, ", the table opened at {} is never closed", opened)?;
+        }
+        Ok(())
+    }
+}
+
+/// Tables nested deeper than this are refused unless
+/// [`ReadOptions::max_depth`] says otherwise: the parser recurses once per
+/// table, and a runaway fan edit should fail with an error rather than
+/// overflow the stack.
+pub const DEFAULT_MAX_DEPTH: usize = 200;
+
+/// What to do with a key written twice in the same table.
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum DuplicateKeys {
+    /// Fail with the position of the second key
+    Error,
+    /// Keep the first value
+    First,
+    /// Keep the last value, as Lua does
+    #[default]
+    Last,
+    /// Keep every value, in order, as a table
+    KeepAll,
+}
+
+/// How a script is read.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ReadOptions {
+    pub duplicate_keys: DuplicateKeys,
+    /// How deeply tables may nest [default: [`DEFAULT_MAX_DEPTH`]]
+    pub max_depth: Option<usize>,
+    /// Fail on unknown escape sequences such as `\k` instead of keeping them as written
+    pub strict: bool,
+    /// Called with a warning for every unknown escape kept as written
+    pub warn: Option<fn(&str)>,
+}
+
+pub(crate) fn parse_error(position: usize, message: impl Into<String>) -> Error {
+    Error::Parse(ParseError { position, message: message.into(), opened: None })
+}
+
+/// Tokens on their way from a [`Tokenizer`] into the parser, with one token
+/// of lookahead.
+pub(crate) struct TokenStream<I: Iterator<Item = Result<SpannedToken>>> {
+    tokens: Peekable<I>,
+    /// End of the last token read, where a missing token is reported
+    pub(crate) end: usize,
+    /// Start of every table being read, innermost last
+    open_tables: Vec<usize>,
+    duplicate_keys: DuplicateKeys,
+    /// Path of the value being read, `ast.block_00000[0]`
+    pub(crate) path: String,
+    /// Filled in with the range of every value when set
+    pub(crate) spans: Option<SpanTable>,
+    /// Comments read since the parser last took them
+    comments: Vec<String>,
+    /// Most tables that may be open at once
+    pub(crate) max_depth: usize,
+}
+
+impl<I: Iterator<Item = Result<SpannedToken>>> TokenStream<I> {
+    pub(crate) fn new(tokens: I, options: &ReadOptions) -> Self {
+        let max_depth = options.max_depth.unwrap_or(DEFAULT_MAX_DEPTH);
+        TokenStream { tokens: tokens.peekable(), end: 0, open_tables: Vec::new(), duplicate_keys: options.duplicate_keys, path: String::new(), spans: None, comments: Vec::new(), max_depth }
+    }
+
+    /// Sets aside the comments ahead of the next token, for the parser to
+    /// attach to the entry they precede.
+    fn skip_comments(&mut self) {
+        while let Some(Ok((Token::Comment(_), _))) = self.tokens.peek() {
+            if let Some(Ok((Token::Comment(comment), _))) = self.tokens.next() {
+                self.comments.push(comment);
+            }
+        }
+    }
+
+    /// The next token without consuming it, `None` at the end of input.
+    pub(crate) fn peek(&mut self) -> Result<Option<&SpannedToken>> {
+        self.skip_comments();
+        if let Some(Err(_)) = self.tokens.peek() {
+            return Err(self.tokens.next().unwrap().unwrap_err());
+        }
+        Ok(self.tokens.peek().map(|token| token.as_ref().unwrap()))
+    }
+
+    /// Notes that the value at the current path runs from `start` to the last token read.
+    fn record(&mut self, start: usize) {
+        if let Some(spans) = self.spans.as_mut() {
+            spans.insert(self.path.clone(), start..self.end);
+        }
+    }
+
+    /// Reads a value with `segment` (`.key`, `[0]`) added to the path.
+    fn value_at<T>(&mut self, segment: &str, read: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        let length = self.path.len();
+        self.path.push_str(segment);
+        let value = read(self);
+        self.path.truncate(length);
+        value
+    }
+
+    pub(crate) fn next(&mut self) -> Result<SpannedToken> {
+        self.skip_comments();
+        let token = self.tokens.next().ok_or_else(|| match self.open_tables.last() {
+            Some(&opened) => Error::Parse(ParseError { position: self.end, message: "Unexpected end of input, expected '}'".to_string(), opened: Some(opened) }),
+            None => parse_error(self.end, "Unexpected end of input"),
+        })??;
+        self.end = token.1.end;
+        Ok(token)
+    }
+}
+
+/// Parses an already tokenized script, reporting token indices in errors.
+#[cfg(test)]
+pub(crate) fn parse_tokens(tokens: &[Token]) -> Result<LuaTable> {
+    let tokens = tokens.iter().cloned().enumerate().map(|(index, token)| Ok((token, index..index + 1)));
+    parse_stream(tokens, &ReadOptions::default())
+}
+
+/// Settles a key written twice in the same table according to the policy.
+/// `collected` is whether `slot` already gathers the earlier values of a
+/// `keep-all` key.
+fn merge_duplicate(slot: &mut Value, value: Value, key: &str, position: usize, policy: DuplicateKeys, collected: bool) -> Result<()> {
+    match policy {
+        DuplicateKeys::Error => return Err(parse_error(position, format!("Duplicate key {}", key))),
+        DuplicateKeys::First => {}
+        DuplicateKeys::Last => *slot = value,
+        DuplicateKeys::KeepAll => match slot {
+            Value::Table(values) if collected => values.push(value),
+            _ => {
+                let first = mem::replace(slot, Value::Integer(0, None));
+                *slot = Value::from(vec![first, value]);
+            }
+        },
+    }
+    Ok(())
+}
+
+pub(crate) fn parse_stream(tokens: impl Iterator<Item = Result<SpannedToken>>, options: &ReadOptions) -> Result<LuaTable> {
+    parse_top_level(&mut TokenStream::new(tokens, options))
+}
+
+/// Reads a script into its top-level assignments, keeping its comments.
+///
+/// ```
+/// use artemis_ast::syntax::{ReadOptions, parse};
+///
+/// let ast = parse("ast = { block_00000 = { line = 18 } }", &ReadOptions::default())?;
+/// let block = ast["ast"].as_table().unwrap()["block_00000"].as_table().unwrap();
+/// assert_eq!(block["line"].as_integer(), Some(18));
+/// # Ok::<(), artemis_ast::syntax::Error>(())
+/// ```
+pub fn parse(input: &str, options: &ReadOptions) -> Result<LuaTable> {
+    parse_stream(Tokenizer::new(input).keeping_comments().options(options), options)
+}
+
+/// Byte range of every value in a script, keyed by its path as the
+/// `equivalent` check reports it: `ast.block_00000[0].time`.
+pub type SpanTable = BTreeMap<String, Span>;
+
+/// Parses `input` only for the position of its values.
+pub fn parse_spans(input: &str) -> Result<SpanTable> {
+    let mut stream = TokenStream::new(Tokenizer::new(input), &ReadOptions::default());
+    stream.spans = Some(SpanTable::new());
+    parse_top_level(&mut stream)?;
+    Ok(stream.spans.unwrap_or_default())
+}
+
+pub(crate) fn parse_top_level<I: Iterator<Item = Result<SpannedToken>>>(stream: &mut TokenStream<I>) -> Result<LuaTable> {
+    let duplicate_keys = stream.duplicate_keys;
+    let mut result: LuaTable = LuaTable::new();
+    let mut collected = BTreeSet::new();
+    
+    while stream.peek()?.is_some() {
+        match stream.next()? {
+            (Token::Identifier(s), key_span) => {
+                let comments = mem::take(&mut stream.comments);
+                let (token, span) = stream.next()?;
+                if token == Token::Equal {
+                    let value = stream.value_at(&s, parse_value)?;
+                    result.add_comments(CommentSlot::Field(s.clone()), comments);
+                    match result.get_mut(&s) {
+                        Some(slot) => {
+                            merge_duplicate(slot, value, &s, key_span.start, duplicate_keys, collected.contains(&s))?;
+                            collected.insert(s);
+                        }
+                        None => {
+                            result.insert(s, value);
+                        }
+                    }
+                } else {
+                    return Err(parse_error(span.start, "Expected '=' after Identifier"));
+                }
+            },
+            // Token::SpTagContent(s) => {
+            //     index += 1;
+            //     if let Token::Equal = tokens[index] {
+            //         let value = parse_value(tokens, &mut index)?;
+            //         result.insert(s.clone(), value);
+            //     } else {
+            //         anyhow::bail!("Expected '=' after SpContent in root level");
+            //     }
+            // }
+            (_, span) => return Err(parse_error(span.start, "Unexpected token at top level")),
+        }
+    }
+    result.add_comments(CommentSlot::End, mem::take(&mut stream.comments));
+    Ok(result)
+}
+
+/// Consumes the `=` after a key if there is one.
+pub(crate) fn next_is_equal<I: Iterator<Item = Result<SpannedToken>>>(stream: &mut TokenStream<I>) -> Result<bool> {
+    let equal = matches!(stream.peek()?, Some((Token::Equal, _)));
+    if equal {
+        stream.next()?;
+    }
+    Ok(equal)
+}
+
+pub(crate) fn parse_value<I: Iterator<Item = Result<SpannedToken>>>(stream: &mut TokenStream<I>) -> Result<Value> {
+    let (token, span) = stream.next()?;
+    token_value(token, span, stream)
+}
+
+/// The value starting with `token`, which has been consumed.
+fn token_value<I: Iterator<Item = Result<SpannedToken>>>(token: Token, span: Span, stream: &mut TokenStream<I>) -> Result<Value> {
+    let value = match token {
+        Token::OpenBrace => {
+            if stream.open_tables.len() >= stream.max_depth {
+                return Err(parse_error(span.start, format!("Tables nested more than {} deep", stream.max_depth)));
+            }
+            stream.open_tables.push(span.start);
+            let table = parse_table(stream)?;
+            stream.open_tables.pop();
+            Ok(table)
+        }
+        Token::StringLiteral(s) => Ok(Value::String(s)),
+        Token::IntegerLiteral(i, literal) => Ok(Value::Integer(i, literal)),
+        Token::FloatLiteral(f, literal) => Ok(Value::Float(f, literal)),
+        Token::Identifier(s) => Ok(Value::BareWord(s)),
+        Token::SpTagContent(sp) => Ok(Value::SpContent(sp)),
+        token => Err(parse_error(span.start, format!("Unexpected token: {:?}", token))),
+    }?;
+    stream.record(span.start);
+    Ok(value)
+}
+
+/// Reads the entries of a table whose `{` has been consumed: `key = value`
+/// becomes a field, anything else a positional entry.
+fn parse_table<I: Iterator<Item = Result<SpannedToken>>>(stream: &mut TokenStream<I>) -> Result<Value> {
+    let mut table = LuaTable::new();
+    let mut collected = BTreeSet::new();
+    loop {
+        let (token, span) = stream.next()?;
+        if token == Token::Comma {
+            continue;
+        }
+        let comments = mem::take(&mut stream.comments);
+        let key = match token {
+            Token::CloseBrace => {
+                table.add_comments(CommentSlot::End, comments);
+                return Ok(Value::Table(table));
+            }
+            Token::Identifier(key) if next_is_equal(stream)? => key,
+            Token::SpTagContent(sp) if next_is_equal(stream)? => sp_key(sp),
+            Token::StringKey(key) => {
+                if !next_is_equal(stream)? {
+                    return Err(parse_error(span.start, "Expected '=' after bracketed key"));
+                }
+                key
+            }
+            token => {
+                table.add_comments(CommentSlot::Entry(table.array.len()), comments);
+                let value = stream.value_at(&format!("[{}]", table.array.len()), |stream| token_value(token, span, stream))?;
+                table.push(value);
+                continue;
+            }
+        };
+        table.add_comments(CommentSlot::Field(key.clone()), comments);
+        let value = stream.value_at(&format!(".{}", key), parse_value)?;
+        match table.get_mut(&key) {
+            Some(slot) => {
+                merge_duplicate(slot, value, &key, span.start, stream.duplicate_keys, collected.contains(&key))?;
+                collected.insert(key);
+            }
+            None => {
+                table.insert(key, value);
+            }
+        }
+    }
+}
+
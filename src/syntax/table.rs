@@ -0,0 +1,188 @@
+use alloc::{collections::BTreeMap, string::String, vec::Vec};
+use core::{mem, ops::Index};
+use super::Value;
+
+/// Where a comment stood in a table constructor, so it can be written back
+/// before the same entry.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CommentSlot {
+    /// Before the positional entry with this index
+    Entry(usize),
+    /// Before the named field
+    Field(String),
+    /// After the last entry, before the `}`
+    End,
+}
+
+/// A Lua table constructor such as `{"fg", ch="妃愛", mode=1}`: the
+/// positional entries in order, and the named fields in the order they were
+/// written. A field written twice keeps its first position and last value,
+/// as Lua would. The top-level assignments of a script (`astver = 2.0`,
+/// `ast = {...}`) are read into one too, so they are written back in their
+/// original order.
+#[derive(Debug, Default, Clone)]
+pub struct LuaTable {
+    /// Entries without a key, `t[1]`, `t[2]`, ...
+    pub array: Vec<Value>,
+    fields: Vec<(String, Value)>,
+    /// Position of each field in `fields`, so a lookup in a table of
+    /// thousands of blocks does not scan them all
+    index: BTreeMap<String, usize>,
+    /// `--` comments in the order they were written. Those of an entry that
+    /// is removed are no longer written.
+    comments: Vec<(CommentSlot, String)>,
+}
+
+impl LuaTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, value: Value) {
+        self.array.push(value);
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.index.get(key).map(|&index| &self.fields[index].1)
+    }
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut Value> {
+        self.index.get(key).map(|&index| &mut self.fields[index].1)
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Sets a field, returning the value it replaces.
+    pub fn insert(&mut self, key: String, value: Value) -> Option<Value> {
+        match self.get_mut(&key) {
+            Some(slot) => Some(mem::replace(slot, value)),
+            None => {
+                self.index.insert(key.clone(), self.fields.len());
+                self.fields.push((key, value));
+                None
+            }
+        }
+    }
+
+    /// Adds a field right after the field `after`, or last if there is none.
+    pub fn insert_after(&mut self, after: &str, key: String, value: Value) {
+        if let Some(index) = self.index.get(&key).copied() {
+            self.fields.remove(index);
+            self.reindex(index);
+        }
+        let index = self.index.get(after).map_or(self.fields.len(), |index| index + 1);
+        self.fields.insert(index, (key, value));
+        self.reindex(index);
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<Value> {
+        let index = self.index.remove(key)?;
+        let (_, value) = self.fields.remove(index);
+        self.reindex(index);
+        Some(value)
+    }
+
+    /// Keeps only the fields whose key passes `keep`.
+    pub fn retain_fields(&mut self, mut keep: impl FnMut(&str) -> bool) {
+        self.fields.retain(|(key, _)| keep(key));
+        self.index.clear();
+        self.reindex(0);
+    }
+
+    /// Brings the positions of the fields from `start` on up to date after
+    /// one was added or removed there.
+    fn reindex(&mut self, start: usize) {
+        for (index, (key, _)) in self.fields.iter().enumerate().skip(start) {
+            match self.index.get_mut(key) {
+                Some(slot) => *slot = index,
+                None => {
+                    self.index.insert(key.clone(), index);
+                }
+            }
+        }
+    }
+
+    pub fn fields(&self) -> impl Iterator<Item = (&String, &Value)> {
+        self.fields.iter().map(|(key, value)| (key, value))
+    }
+
+    pub fn fields_mut(&mut self) -> impl Iterator<Item = (&String, &mut Value)> {
+        self.fields.iter_mut().map(|(key, value)| (&*key, value))
+    }
+
+    /// Positional entries, then field values.
+    pub fn values(&self) -> impl Iterator<Item = &Value> {
+        self.array.iter().chain(self.fields.iter().map(|(_, value)| value))
+    }
+
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut Value> {
+        self.array.iter_mut().chain(self.fields.iter_mut().map(|(_, value)| value))
+    }
+
+    /// Number of positional entries plus fields.
+    pub fn len(&self) -> usize {
+        self.array.len() + self.fields.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.array.is_empty() && self.fields.is_empty()
+    }
+
+    pub fn add_comments(&mut self, slot: CommentSlot, comments: impl IntoIterator<Item = String>) {
+        self.comments.extend(comments.into_iter().map(|comment| (slot.clone(), comment)));
+    }
+
+    pub fn comments<'a>(&'a self, slot: &'a CommentSlot) -> impl Iterator<Item = &'a String> {
+        self.comments.iter().filter(move |(at, _)| at == slot).map(|(_, comment)| comment)
+    }
+}
+
+/// `table["ast"]`, panicking when there is no such field, as `HashMap` does.
+impl Index<&str> for LuaTable {
+    type Output = Value;
+
+    fn index(&self, key: &str) -> &Value {
+        self.get(key).unwrap_or_else(|| panic!("no field {} in table", key))
+    }
+}
+
+impl From<Vec<Value>> for LuaTable {
+    fn from(array: Vec<Value>) -> Self {
+        LuaTable { array, ..Default::default() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{format, vec};
+    use super::*;
+
+    #[test]
+    fn test_mixed_table() {
+        let ast = crate::syntax::parse_tokens(&crate::syntax::tokenize(r#"t = {"fg", ch="妃愛", mode=1, "x", mode=2}"#).unwrap()).unwrap();
+        let table = ast["t"].as_table().unwrap();
+        let positional: Vec<&str> = table.array.iter().filter_map(Value::as_string).map(String::as_str).collect();
+        assert_eq!(positional, vec!["fg", "x"]);
+        let keys: Vec<&String> = table.fields().map(|(key, _)| key).collect();
+        assert_eq!(keys, vec!["ch", "mode"]);
+        assert_eq!(table.get("mode").and_then(Value::as_integer), Some(2));
+    }
+
+    #[test]
+    fn test_many_fields() {
+        let script: String = (0..20_000).map(|i| format!("block_{:05} = {{ line = {} }}\n", i, i)).collect();
+        let ast = crate::syntax::parse(&script, &crate::syntax::ReadOptions::default()).unwrap();
+        assert_eq!(ast.len(), 20_000);
+        assert_eq!(ast["block_12345"].as_table().unwrap().get("line").and_then(Value::as_integer), Some(12345));
+
+        let mut table = ast;
+        table.insert_after("block_00000", "label".into(), Value::from(1));
+        assert_eq!(table.remove("block_00001").and_then(|block| block.as_table()?.get("line")?.as_integer()), Some(1));
+        table.retain_fields(|key| key != "block_00002");
+        let keys: Vec<&String> = table.fields().take(3).map(|(key, _)| key).collect();
+        assert_eq!(keys, ["block_00000", "label", "block_00003"]);
+        assert_eq!(table.get("block_19999").and_then(Value::as_table).and_then(|block| block.get("line")).and_then(Value::as_integer), Some(19999));
+    }
+}
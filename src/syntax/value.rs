@@ -0,0 +1,133 @@
+use alloc::{format, string::{String, ToString}, vec::Vec};
+use super::{Error, LuaTable};
+
+/// A parsed script value. New variants may be added (booleans, nil, raw
+/// Lua), so matches outside this crate need a wildcard arm.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub enum Value {
+    /// The number, and its literal when written otherwise than the writer
+    /// would (`0x1F`), so it is written back unchanged
+    Integer(i64, Option<String>),
+    /// The number, and its literal when written otherwise than the writer
+    /// would (`2.20`, `1e5`)
+    Float(f64, Option<String>),
+    String(String),
+    /// An unquoted name in value position (`true`, `nil`, a variable),
+    /// written back without quotes
+    BareWord(String),
+    Table(LuaTable),
+    SpContent(Option<i64>),
+}
+
+impl Value {
+    pub fn as_string(&self) -> Option<&String> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_string_mut(&mut self) -> Option<&mut String> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn is_table(&self) -> bool {
+        matches!(self, Value::Table(_))
+    }
+
+    pub fn as_table(&self) -> Option<&LuaTable> {
+        match self {
+            Value::Table(t) => Some(t),
+            _ => None,
+        }
+    }
+
+    pub fn as_table_mut(&mut self) -> Option<&mut LuaTable> {
+        match self {
+            Value::Table(t) => Some(t),
+            _ => None,
+        }
+    }
+
+    pub fn as_integer(&self) -> Option<i64> {
+        match self {
+            Value::Integer(i, _) => Some(*i),
+            _ => None,
+        }
+    }
+
+    pub fn as_float(&self) -> Option<f64> {
+        match self {
+            Value::Float(f, _) => Some(*f),
+            _ => None,
+        }
+    }
+}
+
+impl From<i64> for Value {
+    fn from(i: i64) -> Self {
+        Value::Integer(i, None)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(f: f64) -> Self {
+        Value::Float(f, None)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Self {
+        Value::String(s.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Self {
+        Value::String(s)
+    }
+}
+
+impl From<Vec<Value>> for Value {
+    fn from(a: Vec<Value>) -> Self {
+        Value::Table(a.into())
+    }
+}
+
+impl From<LuaTable> for Value {
+    fn from(t: LuaTable) -> Self {
+        Value::Table(t)
+    }
+}
+
+macro_rules! try_from_value {
+    ($target:ty, $variant:ident, $name:literal) => {
+        impl TryFrom<Value> for $target {
+            type Error = Error;
+
+            fn try_from(value: Value) -> Result<Self, Error> {
+                match value {
+                    Value::$variant(inner, ..) => Ok(inner),
+                    other => Err(Error::Type(format!("Expected {}, found {:?}", $name, other))),
+                }
+            }
+        }
+    };
+}
+
+try_from_value!(i64, Integer, "an integer");
+try_from_value!(f64, Float, "a float");
+try_from_value!(String, String, "a string");
+try_from_value!(LuaTable, Table, "a table");
+
+/// `[1]` keys are stored with their brackets, `[]` for an empty pair.
+pub(crate) fn sp_key(sp: Option<i64>) -> String {
+    match sp {
+        Some(sp) => format!("[{}]", sp),
+        None => "[]".to_string(),
+    }
+}
@@ -0,0 +1,473 @@
+use alloc::{format, string::{String, ToString}};
+use core::fmt;
+use super::{CommentSlot, Error, LuaTable, Result, Value, value::sp_key};
+
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[derive(Clone, Copy, Debug)]
+pub enum AsciiEscape {
+    /// Lua 5.3 style \u{XXXX}
+    Unicode,
+    /// Lua 5.1 style \ddd for every UTF-8 byte
+    Decimal,
+}
+
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[derive(Clone, Copy, Debug, Default)]
+pub enum QuoteStyle {
+    /// "text"
+    #[default]
+    Double,
+    /// 'text'
+    Single,
+}
+
+impl QuoteStyle {
+    fn char(self) -> char {
+        match self {
+            QuoteStyle::Double => '"',
+            QuoteStyle::Single => '\'',
+        }
+    }
+}
+
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum, serde::Deserialize, schemars::JsonSchema), serde(rename_all = "lowercase"))]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum IndentStyle {
+    #[default]
+    Tabs,
+    Spaces,
+}
+
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum, serde::Deserialize, schemars::JsonSchema), serde(rename_all = "lowercase"))]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Newlines {
+    /// "a\nb", quoting strings that would otherwise go in long brackets
+    Escape,
+    /// Line breaks as they are, in [[long brackets]]
+    Raw,
+}
+
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[derive(Clone, Copy, Debug, Default)]
+pub enum NonFinite {
+    /// Fail, as no literal reads back as NaN or an infinity
+    #[default]
+    Error,
+    /// Write the infinities as 1e999 and -1e999, which read back as them; NaN still fails
+    Huge,
+}
+
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[derive(Clone, Copy, Debug, Default)]
+pub enum Separator {
+    /// { a, b }
+    #[default]
+    Comma,
+    /// { a; b }
+    Semicolon,
+}
+
+impl Separator {
+    fn char(self) -> char {
+        match self {
+            Separator::Comma => ',',
+            Separator::Semicolon => ';',
+        }
+    }
+}
+
+/// How a script is written back.
+#[cfg_attr(feature = "cli", derive(clap::Args), command(about = None, long_about = None))]
+#[derive(Debug, Default)]
+pub struct WriteOptions {
+    /// Write non-ASCII characters as escapes, for engines with encoding quirks
+    #[cfg_attr(feature = "cli", arg(long, value_enum, global = true))]
+    pub escape_non_ascii: Option<AsciiEscape>,
+    /// Quotes around the string literals that are written
+    #[cfg_attr(feature = "cli", arg(long, value_enum, global = true, default_value_t))]
+    pub quote_style: QuoteStyle,
+    /// Separator between table entries in rebuilt scripts; merge keeps whatever the script uses
+    #[cfg_attr(feature = "cli", arg(long, value_enum, global = true, default_value_t))]
+    pub separator: Separator,
+    /// Indent rebuilt scripts with tabs or spaces [default: tabs]
+    #[cfg_attr(feature = "cli", arg(long, value_enum, global = true))]
+    pub indent: Option<IndentStyle>,
+    /// Spaces per level with --indent spaces [default: 4]
+    #[cfg_attr(feature = "cli", arg(long, global = true))]
+    pub indent_width: Option<usize>,
+    /// Write a separator after the last entry of every table too
+    #[cfg_attr(feature = "cli", arg(long, global = true))]
+    pub trailing_comma: bool,
+    /// Write fields as `key = value` instead of `key=value`
+    #[cfg_attr(feature = "cli", arg(long, global = true))]
+    pub spaced_equals: bool,
+    /// Write strings containing quotes or line breaks as [[long brackets]] instead of escaping them
+    #[cfg_attr(feature = "cli", arg(long, global = true))]
+    pub long_strings: bool,
+    /// How line breaks inside strings are written [default: escaped, except in long strings]
+    #[cfg_attr(feature = "cli", arg(long, value_enum, global = true))]
+    pub newlines: Option<Newlines>,
+    /// Round written floats to this many decimals, to drop noise such as 2.2000000000000002 [default: the shortest form that reads back the same]
+    #[cfg_attr(feature = "cli", arg(long, global = true))]
+    pub float_precision: Option<usize>,
+    /// What to do with a float that is NaN or infinite
+    #[cfg_attr(feature = "cli", arg(long, value_enum, global = true, default_value_t))]
+    pub non_finite: NonFinite,
+    /// Write each table on one line without indentation, spaces or comments, for shipping builds
+    #[cfg_attr(feature = "cli", arg(long, global = true, conflicts_with_all = ["indent", "indent_width", "trailing_comma", "spaced_equals"]))]
+    pub minify: bool,
+    /// Whether scripts are read with `--strict`, so no unknown escape was kept to be written back as read
+    #[cfg_attr(feature = "cli", arg(skip))]
+    pub strict: bool,
+}
+
+impl WriteOptions {
+    /// One level of indentation.
+    pub fn indent_unit(&self) -> String {
+        match self.indent.unwrap_or_default() {
+            IndentStyle::Tabs => "\t".to_string(),
+            IndentStyle::Spaces => " ".repeat(self.indent_width.unwrap_or(4)),
+        }
+    }
+}
+
+
+/// Whether a backslash followed by `next` is read back as written: an
+/// engine code such as `\k`, which the tokenizer passes through unless
+/// `--strict`, and `next` is not itself written as an escape.
+fn passes_through(next: Option<char>, options: &WriteOptions) -> bool {
+    !options.strict && next.is_some_and(|next| {
+        !matches!(next, 'n' | 't' | 'r' | 'a' | 'b' | 'f' | 'v' | 'x' | 'z' | 'u' | '"' | '\'' | '\\' | '0'..='9')
+            && !next.is_ascii_control()
+            && (next.is_ascii() || options.escape_non_ascii.is_none())
+    })
+}
+
+/// Quotes a string for output, re-encoding non-ASCII characters when asked
+/// to, so that the tokenizer reads back exactly `s`.
+fn quote_string(s: &str, options: &WriteOptions) -> String {
+    let quote = options.quote_style.char();
+    let mut quoted = String::from(quote);
+    let mut chars = s.chars().peekable();
+    while let Some(ch) = chars.next() {
+        if ch == '\\' {
+            quoted.push_str(if passes_through(chars.peek().copied(), options) { "\\" } else { "\\\\" });
+            continue;
+        }
+        if ch == quote {
+            quoted.push('\\');
+            quoted.push(ch);
+            continue;
+        }
+        let control = match ch {
+            '\n' => Some("\\n".to_string()),
+            '\t' => Some("\\t".to_string()),
+            '\r' => Some("\\r".to_string()),
+            '\x07' => Some("\\a".to_string()),
+            '\x08' => Some("\\b".to_string()),
+            '\x0C' => Some("\\f".to_string()),
+            '\x0B' => Some("\\v".to_string()),
+            // three digits, so a following digit is not read as part of the escape
+            _ if ch.is_ascii_control() => Some(format!("\\{:03}", ch as u32)),
+            _ => None,
+        };
+        if let Some(escape) = control {
+            quoted.push_str(&escape);
+            continue;
+        }
+        match options.escape_non_ascii {
+            Some(AsciiEscape::Unicode) if !ch.is_ascii() => quoted.push_str(&format!("\\u{{{:X}}}", ch as u32)),
+            Some(AsciiEscape::Decimal) if !ch.is_ascii() => {
+                for byte in ch.encode_utf8(&mut [0; 4]).bytes() {
+                    quoted.push_str(&format!("\\{}", byte));
+                }
+            }
+            _ => quoted.push(ch),
+        }
+    }
+    quoted.push(quote);
+    quoted
+}
+
+/// `s` as a long-bracket string, with a level whose closing bracket does
+/// not occur in it, or `None` when it has no quotes or line breaks to
+/// spare. Long strings cannot hold escapes, so text with a carriage return
+/// or that is to be written in ASCII only stays quoted.
+fn long_string(s: &str, options: &WriteOptions) -> Option<String> {
+    if !s.contains(['"', '\'', '\n']) || s.contains('\r') || options.escape_non_ascii.is_some_and(|_| !s.is_ascii()) {
+        return None;
+    }
+    let level = (0..).find(|&level| {
+        let close = format!("]{}]", "=".repeat(level));
+        format!("{}{}", s, close).find(&close) == Some(s.len())
+    })?;
+    let equals = "=".repeat(level);
+    // Lua drops a line break right after the opening bracket
+    let newline = if s.starts_with('\n') { "\n" } else { "" };
+    Some(format!("[{}[{}{}]{}]", equals, newline, s, equals))
+}
+
+/// A string value as written: in long brackets with `--long-strings` when
+/// that saves escaping, or with `--newlines raw` when it has line breaks,
+/// quoted otherwise. `--newlines escape` keeps strings with line breaks
+/// quoted even with `--long-strings`; a string long brackets cannot hold is
+/// quoted whatever the policy.
+pub fn string_to_script(s: &str, options: &WriteOptions) -> String {
+    let long = match options.newlines {
+        Some(Newlines::Escape) if s.contains('\n') => false,
+        Some(Newlines::Raw) if s.contains('\n') => true,
+        _ => options.long_strings,
+    };
+    let long = if long { long_string(s, options) } else { None };
+    long.unwrap_or_else(|| quote_string(s, options))
+}
+
+/// Writes a table key bare when it is an identifier or one of the `[1]`
+/// keys read from integer brackets, and as `["save title"]` otherwise.
+fn key_to_script(key: &str, options: &WriteOptions) -> String {
+    let identifier = key.starts_with(|c: char| !c.is_numeric())
+        && key.chars().all(|c| c.is_alphanumeric() || c == '_');
+    let integer = key.strip_prefix('[')
+        .and_then(|key| key.strip_suffix(']'))
+        .is_some_and(|key| key.is_empty() || key.parse::<i64>().is_ok());
+    if identifier || integer {
+        key.to_string()
+    } else {
+        format!("[{}]", quote_string(key, options))
+    }
+}
+
+/// How the writer spells a float: `2.0`, `0.25`.
+pub(crate) fn format_float(f: f64) -> String {
+    // `%` rather than `fract`, which needs std
+    if f % 1.0 == 0.0 {
+        format!("{:.1}", f)
+    } else {
+        f.to_string()
+    }
+}
+
+/// A float as written with `options`: rounded to `--float-precision`
+/// decimals when set, trailing zeros dropped, and otherwise the shortest
+/// spelling that reads back as the same number. Lua has no literal for NaN,
+/// and the infinities are only written, as `1e999`, with `--non-finite huge`.
+fn write_float(f: f64, options: &WriteOptions) -> Result<String> {
+    match options.non_finite {
+        _ if f.is_finite() => {}
+        NonFinite::Huge if f.is_infinite() => return Ok(if f > 0.0 { "1e999" } else { "-1e999" }.to_string()),
+        _ => return Err(Error::Write(format!("{} has no numeric literal, see --non-finite", f))),
+    }
+    let Some(precision) = options.float_precision else {
+        return Ok(format_float(f));
+    };
+    let rounded = format!("{:.*}", precision, f);
+    let rounded = if rounded.contains('.') { rounded.trim_end_matches('0').trim_end_matches('.') } else { &rounded };
+    // -0.001 rounded to two decimals
+    let rounded = if rounded == "-0" { "0" } else { rounded };
+    Ok(format!("{}{}", rounded, if rounded.contains('.') { "" } else { ".0" }))
+}
+
+/// `literal` if it is not how the writer would spell the number.
+pub(crate) fn unusual_literal(literal: &str, written: String) -> Option<String> {
+    (literal != written).then(|| literal.to_string())
+}
+
+/// What a kept number literal reads as: the integer, if it is one, and the float.
+fn literal_value(literal: &str) -> Option<(Option<i64>, f64)> {
+    let (negative, digits) = literal.strip_prefix('-').map_or((false, literal), |digits| (true, digits));
+    let hex = digits.strip_prefix("0x").or_else(|| digits.strip_prefix("0X"));
+    let integer = match hex {
+        Some(hex) => i64::from_str_radix(hex, 16).ok().map(|i| if negative { -i } else { i }),
+        None => literal.parse().ok(),
+    };
+    let float = match integer {
+        Some(i) => i as f64,
+        None => literal.parse().ok()?,
+    };
+    Some((integer, float))
+}
+
+/// Writes `value` as it appears in a script, nested `indent_level` deep.
+fn write_value<W: fmt::Write>(w: &mut W, value: &Value, indent_level: usize, options: &WriteOptions) -> Result<()> {
+    match value {
+        Value::String(s) => w.write_str(&string_to_script(s, options))?,
+        Value::BareWord(word) => w.write_str(word)?,
+        // a literal is only trusted while it still reads as the value, in
+        // case the number was changed in place
+        Value::Float(f, Some(literal)) if literal_value(literal).is_some_and(|(_, value)| value == *f) => w.write_str(literal)?,
+        Value::Float(f, _) => w.write_str(&write_float(*f, options)?)?,
+        Value::Integer(i, Some(literal)) if literal_value(literal).is_some_and(|(value, _)| value == Some(*i)) => w.write_str(literal)?,
+        Value::Integer(i, _) => write!(w, "{}", i)?,
+        Value::Table(t) if options.minify => {
+            let separator = options.separator.char();
+            w.write_str("{")?;
+            for (index, value) in t.array.iter().enumerate() {
+                if index > 0 {
+                    write!(w, "{}", separator)?;
+                }
+                write_value(w, value, 0, options)?;
+            }
+            for (index, (key, value)) in t.fields().enumerate() {
+                if index > 0 || !t.array.is_empty() {
+                    write!(w, "{}", separator)?;
+                }
+                write!(w, "{}=", key_to_script(key, options))?;
+                write_value(w, value, 0, options)?;
+            }
+            w.write_str("}")?;
+        }
+        Value::Table(t) => {
+            let indent = options.indent_unit().repeat(indent_level);
+            let next_indent = options.indent_unit().repeat(indent_level + 1);
+            let separator = options.separator.char();
+            w.write_str("{")?;
+            let mut first = true;
+            let mut entry = |w: &mut W, slot: CommentSlot| -> Result<()> {
+                if !first {
+                    write!(w, "{}", separator)?;
+                }
+                first = false;
+                write!(w, "\n{}{}", next_indent, comments_to_script(t, &slot, &next_indent))?;
+                Ok(())
+            };
+            for (index, value) in t.array.iter().enumerate() {
+                entry(w, CommentSlot::Entry(index))?;
+                write_value(w, value, indent_level + 1, options)?;
+            }
+            for (key, value) in t.fields() {
+                entry(w, CommentSlot::Field(key.clone()))?;
+                let equals = if options.spaced_equals { " = " } else { "=" };
+                write!(w, "{}{}", key_to_script(key, options), equals)?;
+                write_value(w, value, indent_level + 1, options)?;
+            }
+            if options.trailing_comma && !t.is_empty() {
+                write!(w, "{}", separator)?;
+            }
+            for comment in t.comments(&CommentSlot::End) {
+                write!(w, "\n{}{}", next_indent, comment)?;
+            }
+            if !t.is_empty() || t.comments(&CommentSlot::End).next().is_some() {
+                write!(w, "\n{}", indent)?;
+            }
+            w.write_str("}")?;
+        }
+        Value::SpContent(sp) => w.write_str(&sp_key(*sp))?,
+    }
+    Ok(())
+}
+
+/// The comments kept for `slot`, each on its own line and followed by `indent`.
+fn comments_to_script(table: &LuaTable, slot: &CommentSlot, indent: &str) -> String {
+    table.comments(slot).map(|comment| format!("{}\n{}", comment, indent)).collect()
+}
+
+/// Writes the script out as it is built, its top-level keys in the order
+/// they were read, so `astver` stays ahead of `ast` whatever the hash seed.
+pub fn write_script<W: fmt::Write>(ast: &LuaTable, w: &mut W, options: &WriteOptions) -> Result<()> {
+    for (key, value) in ast.fields() {
+        if !options.minify {
+            w.write_str(&comments_to_script(ast, &CommentSlot::Field(key.clone()), ""))?;
+        }
+        w.write_str(key)?;
+        w.write_str(if options.minify { "=" } else { " = " })?;
+        write_value(w, value, 0, options)?;
+        w.write_str("\n")?;
+    }
+    if !options.minify {
+        w.write_str(&comments_to_script(ast, &CommentSlot::End, ""))?;
+    }
+    Ok(())
+}
+
+
+/// The whole script as a string.
+///
+/// ```
+/// use artemis_ast::syntax::{ReadOptions, WriteOptions, parse, write};
+///
+/// let script = "ast = {\n\tblock_00000={\n\t\tline=18\n\t}\n}\n";
+/// let ast = parse(script, &ReadOptions::default())?;
+/// assert_eq!(write(&ast, &WriteOptions::default())?, script);
+/// # Ok::<(), artemis_ast::syntax::Error>(())
+/// ```
+pub fn write(ast: &LuaTable, options: &WriteOptions) -> Result<String> {
+    let mut script = String::new();
+    write_script(ast, &mut script, options)?;
+    Ok(script)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{vec, vec::Vec};
+    use super::*;
+    use crate::syntax::{ReadOptions, Token, Tokenizer, tokenize};
+
+    #[test]
+    fn test_numeric_escapes() {
+        let tokens = tokenize(r#"text = "\227\129\130\u{3044}\65""#).unwrap();
+        assert_eq!(tokens[2], Token::StringLiteral("あいA".to_string()));
+
+        for mode in [AsciiEscape::Unicode, AsciiEscape::Decimal] {
+            let options = WriteOptions { escape_non_ascii: Some(mode), ..Default::default() };
+            let quoted = quote_string("あいA", &options);
+            assert!(quoted.is_ascii());
+            assert_eq!(tokenize(&quoted).unwrap(), vec![Token::StringLiteral("あいA".to_string())]);
+        }
+    }
+
+    #[test]
+    fn test_lua_escapes() {
+        let tokens = tokenize("text = \"a\\r\\a\\b\\f\\v\\x41\\z\n\t\tb\\\nc\"").unwrap();
+        let text = "a\r\x07\x08\x0C\x0BAb\nc";
+        assert_eq!(tokens[2], Token::StringLiteral(text.to_string()));
+        assert!(tokenize(r#""\x4""#).is_err());
+
+        let quoted = quote_string(&format!("{}\x011", text), &WriteOptions::default());
+        assert_eq!(quoted, r#""a\r\a\b\f\vAb\nc\0011""#);
+        assert_eq!(tokenize(&quoted).unwrap(), vec![Token::StringLiteral(format!("{}\x011", text))]);
+    }
+
+    #[test]
+    fn test_escaping_round_trip() {
+        let texts = ["say \"hi\"", "it's", "C:\\save\\new", "a\\nb", "end\\", "\\\"", "two\nlines\r\n", "wait\\kthen", "\\\\k"];
+        for (quote_style, strict) in [(QuoteStyle::Double, false), (QuoteStyle::Single, false), (QuoteStyle::Double, true)] {
+            let options = WriteOptions { quote_style, strict, ..Default::default() };
+            for text in texts {
+                let quoted = quote_string(text, &options);
+                let tokens: Vec<_> = Tokenizer::new(&quoted).options(&ReadOptions { strict, ..Default::default() }).map(|token| token.unwrap().0).collect();
+                assert_eq!(tokens, vec![Token::StringLiteral(text.to_string())], "{}", quoted);
+            }
+        }
+        assert_eq!(quote_string("C:\\new\\k", &WriteOptions::default()), r#""C:\\new\k""#);
+        assert_eq!(quote_string("C:\\new\\k", &WriteOptions { strict: true, ..Default::default() }), r#""C:\\new\\k""#);
+        let options = WriteOptions { escape_non_ascii: Some(AsciiEscape::Unicode), ..Default::default() };
+        for text in ["\\あ", "\\\t"] {
+            let tokens: Vec<_> = Tokenizer::new(&quote_string(text, &options)).map(|token| token.unwrap().0).collect();
+            assert_eq!(tokens, vec![Token::StringLiteral(text.to_string())]);
+        }
+    }
+
+    #[test]
+    fn test_single_quoted_strings() {
+        let single = WriteOptions { quote_style: QuoteStyle::Single, ..Default::default() };
+        assert_eq!(quote_string("it's", &single), r"'it\'s'");
+        assert_eq!(quote_string("it's", &WriteOptions::default()), "\"it's\"");
+    }
+
+    #[test]
+    fn test_write_float() {
+        let options = WriteOptions::default();
+        assert_eq!(write_float(0.1 + 0.2, &options).unwrap(), "0.30000000000000004");
+        assert_eq!(write_float(2.0, &options).unwrap(), "2.0");
+        let rounded = WriteOptions { float_precision: Some(3), ..Default::default() };
+        assert_eq!(write_float(0.1 + 0.2, &rounded).unwrap(), "0.3");
+        assert_eq!(write_float(0.12549, &rounded).unwrap(), "0.125");
+        assert_eq!(write_float(-0.0001, &rounded).unwrap(), "0.0");
+        assert_eq!(write_float(3.9999, &rounded).unwrap(), "4.0");
+        assert!(write_float(f64::INFINITY, &options).is_err());
+        let huge = WriteOptions { non_finite: NonFinite::Huge, ..Default::default() };
+        assert_eq!(write_float(f64::NEG_INFINITY, &huge).unwrap(), "-1e999");
+        assert_eq!(tokenize("-1e999").unwrap(), vec![Token::FloatLiteral(f64::NEG_INFINITY, Some("-1e999".to_string()))]);
+        assert!(write_float(f64::NAN, &huge).is_err());
+    }
+}